@@ -0,0 +1,29 @@
+//! Generates the Huffman decoding LUT at build time.
+//!
+//! The generator lives in `src/lut_generator.rs` so the unit tests can
+//! exercise it too; it is compiled into the build script as a shared
+//! module, together with the code tables it reads, and its output is
+//! written to `OUT_DIR` for `src/huffman.rs` to include.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+#[path = "src/tables.rs"]
+mod tables;
+
+#[path = "src/lut_generator.rs"]
+mod lut_generator;
+
+fn main() {
+    println!("cargo:rerun-if-changed=src/tables.rs");
+    println!("cargo:rerun-if-changed=src/lut_generator.rs");
+
+    let lut = lut_generator::LutGenerator::new().generate_lut();
+    lut_generator::validate_lut(&lut).expect("generated Huffman LUT failed validation");
+    let code = lut_generator::format_lut_as_rust_code(&lut);
+
+    let out_dir = env::var("OUT_DIR").expect("OUT_DIR is set for build scripts");
+    fs::write(Path::new(&out_dir).join("decoding_lut.rs"), code)
+        .expect("write generated LUT to OUT_DIR");
+}
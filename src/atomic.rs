@@ -0,0 +1,549 @@
+//! Lock-free primitives shared by the pools and metrics.
+
+use std::cell::UnsafeCell;
+use std::mem::MaybeUninit;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+/// A cache-line-padded monotonically increasing counter.
+#[repr(align(64))]
+#[derive(Debug, Default)]
+pub struct AtomicCounter {
+    value: AtomicU64,
+}
+
+impl AtomicCounter {
+    pub const fn new() -> Self {
+        Self {
+            value: AtomicU64::new(0),
+        }
+    }
+
+    /// Adds `n` to the counter, returning the previous value.
+    pub fn fetch_add(&self, n: u64) -> u64 {
+        self.value.fetch_add(n, Ordering::Relaxed)
+    }
+
+    /// Increments the counter by one.
+    pub fn increment(&self) -> u64 {
+        self.fetch_add(1)
+    }
+
+    pub fn load(&self) -> u64 {
+        self.value.load(Ordering::Relaxed)
+    }
+
+    pub fn store(&self, n: u64) {
+        self.value.store(n, Ordering::Relaxed);
+    }
+
+    /// Replaces the counter with `n`, returning the previous value. A
+    /// `swap(0)` drains the counter without losing concurrent increments.
+    pub fn swap(&self, n: u64) -> u64 {
+        self.value.swap(n, Ordering::Relaxed)
+    }
+
+    /// Replaces `current` with `new` if the counter still holds `current`,
+    /// returning the observed value on failure. The building block for
+    /// read-modify-write loops that are not plain additions.
+    pub fn compare_exchange(&self, current: u64, new: u64) -> Result<u64, u64> {
+        self.value
+            .compare_exchange_weak(current, new, Ordering::AcqRel, Ordering::Relaxed)
+    }
+}
+
+const BITS_PER_WORD: usize = 64;
+
+/// A fixed-size bitmap with atomic per-bit allocation primitives.
+///
+/// Pools use a set bit to mark a slot as allocated (or available, depending
+/// on the caller's polarity); `find_and_set` / `find_and_clear` atomically
+/// claim a bit in either direction.
+#[derive(Debug)]
+pub struct AtomicBitmap {
+    words: Vec<AtomicU64>,
+    size: usize,
+}
+
+impl AtomicBitmap {
+    /// Creates a bitmap of `size` bits, all clear.
+    pub fn new(size: usize) -> Self {
+        let words = (0..size.div_ceil(BITS_PER_WORD))
+            .map(|_| AtomicU64::new(0))
+            .collect();
+        Self { words, size }
+    }
+
+    /// The number of bits in the bitmap.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns whether bit `index` is set.
+    pub fn is_set(&self, index: usize) -> bool {
+        assert!(index < self.size);
+        let word = self.words[index / BITS_PER_WORD].load(Ordering::Relaxed);
+        word & (1u64 << (index % BITS_PER_WORD)) != 0
+    }
+
+    /// Sets bit `index`, returning its previous value.
+    pub fn set(&self, index: usize) -> bool {
+        assert!(index < self.size);
+        let mask = 1u64 << (index % BITS_PER_WORD);
+        let prev = self.words[index / BITS_PER_WORD].fetch_or(mask, Ordering::AcqRel);
+        prev & mask != 0
+    }
+
+    /// Clears bit `index`, returning its previous value.
+    pub fn clear(&self, index: usize) -> bool {
+        assert!(index < self.size);
+        let mask = 1u64 << (index % BITS_PER_WORD);
+        let prev = self.words[index / BITS_PER_WORD].fetch_and(!mask, Ordering::AcqRel);
+        prev & mask != 0
+    }
+
+    /// Atomically finds the lowest clear bit, sets it, and returns its
+    /// index, or `None` when every bit is set.
+    pub fn find_and_set(&self) -> Option<usize> {
+        for (word_index, word) in self.words.iter().enumerate() {
+            let valid = self.valid_mask(word_index);
+            let mut current = word.load(Ordering::Relaxed);
+            while current & valid != valid {
+                let bit = (!current & valid).trailing_zeros();
+                match word.compare_exchange_weak(
+                    current,
+                    current | (1u64 << bit),
+                    Ordering::AcqRel,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => return Some(word_index * BITS_PER_WORD + bit as usize),
+                    Err(observed) => current = observed,
+                }
+            }
+        }
+        None
+    }
+
+    /// Atomically finds the lowest set bit, clears it, and returns its
+    /// index, or `None` when every bit is clear.
+    pub fn find_and_clear(&self) -> Option<usize> {
+        for (word_index, word) in self.words.iter().enumerate() {
+            let mut current = word.load(Ordering::Relaxed) & self.valid_mask(word_index);
+            while current != 0 {
+                let bit = current.trailing_zeros();
+                match word.compare_exchange_weak(
+                    current,
+                    current & !(1u64 << bit),
+                    Ordering::AcqRel,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => return Some(word_index * BITS_PER_WORD + bit as usize),
+                    Err(observed) => current = observed & self.valid_mask(word_index),
+                }
+            }
+        }
+        None
+    }
+
+    /// Counts the set bits, ignoring any padding bits beyond `size`.
+    pub fn count_set(&self) -> usize {
+        self.words
+            .iter()
+            .enumerate()
+            .map(|(word_index, word)| {
+                (word.load(Ordering::Relaxed) & self.valid_mask(word_index)).count_ones() as usize
+            })
+            .sum()
+    }
+
+    /// The mask of bits in `word_index` that fall inside `size`.
+    fn valid_mask(&self, word_index: usize) -> u64 {
+        let first = word_index * BITS_PER_WORD;
+        let bits = usize::min(BITS_PER_WORD, self.size - first);
+        if bits == BITS_PER_WORD {
+            u64::MAX
+        } else {
+            (1u64 << bits) - 1
+        }
+    }
+}
+
+/// The default shard count for [`ShardedCounter`].
+const DEFAULT_SHARDS: usize = 16;
+
+/// A counter striped across cache-line-padded shards to avoid serializing
+/// hot `fetch_add`s on one cache line.
+///
+/// Each thread is pinned to a shard, so increments from different threads
+/// mostly touch different lines; `load` sums every shard. The surface
+/// mirrors [`AtomicCounter`] so metrics can swap between them freely.
+#[derive(Debug)]
+pub struct ShardedCounter {
+    shards: Vec<AtomicCounter>,
+}
+
+impl Default for ShardedCounter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ShardedCounter {
+    pub fn new() -> Self {
+        Self::with_shards(DEFAULT_SHARDS)
+    }
+
+    pub fn with_shards(shards: usize) -> Self {
+        assert!(shards > 0, "at least one shard required");
+        Self {
+            shards: (0..shards).map(|_| AtomicCounter::new()).collect(),
+        }
+    }
+
+    /// Adds `n` to the calling thread's shard, returning that shard's
+    /// previous value. (A globally ordered previous total cannot be
+    /// produced without defeating the sharding.)
+    pub fn fetch_add(&self, n: u64) -> u64 {
+        self.shards[self.shard_index()].fetch_add(n)
+    }
+
+    /// Increments the counter by one.
+    pub fn increment(&self) -> u64 {
+        self.fetch_add(1)
+    }
+
+    /// Sums every shard.
+    pub fn load(&self) -> u64 {
+        self.shards.iter().map(AtomicCounter::load).sum()
+    }
+
+    /// The shard assigned to the calling thread.
+    fn shard_index(&self) -> usize {
+        use std::sync::atomic::AtomicUsize;
+        static NEXT_THREAD: AtomicUsize = AtomicUsize::new(0);
+        thread_local! {
+            static THREAD_SLOT: usize = NEXT_THREAD.fetch_add(1, Ordering::Relaxed);
+        }
+        THREAD_SLOT.with(|slot| slot % self.shards.len())
+    }
+}
+
+/// A fixed-capacity lock-free Treiber stack.
+///
+/// Nodes live in a preallocated array and are linked by index. Both list
+/// heads pack a generation counter next to the top index and bump it on
+/// every successful exchange, so a head that was popped and re-pushed
+/// between a competitor's load and its CAS (the classic ABA interleaving)
+/// can never be mistaken for an unchanged one.
+pub struct AtomicStack<T> {
+    nodes: Vec<Node<T>>,
+    /// Tagged head of the value list.
+    head: AtomicU64,
+    /// Tagged head of the free-node list.
+    free: AtomicU64,
+}
+
+struct Node<T> {
+    /// Index + 1 of the next node; 0 terminates the list.
+    next: AtomicU32,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+// SAFETY: a node's value is only accessed by the thread that exclusively
+// popped it off one of the lists.
+unsafe impl<T: Send> Sync for AtomicStack<T> {}
+unsafe impl<T: Send> Send for AtomicStack<T> {}
+
+/// Packs a generation tag and a top index (+1, 0 meaning empty) into one
+/// word.
+fn pack(generation: u32, top: u32) -> u64 {
+    (u64::from(generation) << 32) | u64::from(top)
+}
+
+fn unpack(tagged: u64) -> (u32, u32) {
+    ((tagged >> 32) as u32, tagged as u32)
+}
+
+impl<T> AtomicStack<T> {
+    /// Creates an empty stack able to hold `capacity` values.
+    pub fn new(capacity: usize) -> Self {
+        assert!(
+            capacity > 0 && capacity < u32::MAX as usize,
+            "capacity must fit the index encoding"
+        );
+        let nodes: Vec<Node<T>> = (0..capacity)
+            .map(|index| Node {
+                // Seed the free list as a chain through every node.
+                next: AtomicU32::new(if index + 1 < capacity {
+                    index as u32 + 2
+                } else {
+                    0
+                }),
+                value: UnsafeCell::new(MaybeUninit::uninit()),
+            })
+            .collect();
+        Self {
+            nodes,
+            head: AtomicU64::new(pack(0, 0)),
+            free: AtomicU64::new(pack(0, 1)),
+        }
+    }
+
+    /// The maximum number of values the stack can hold.
+    pub fn capacity(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Pushes a value, returning it back when the stack is full.
+    pub fn push(&self, value: T) -> Result<(), T> {
+        let Some(node_index) = self.pop_from(&self.free) else {
+            return Err(value);
+        };
+        // SAFETY: the node was exclusively claimed off the free list, and
+        // the release CAS in `push_onto` orders this write before the node
+        // becomes visible to poppers.
+        unsafe { (*self.nodes[node_index as usize].value.get()).write(value) };
+        self.push_onto(&self.head, node_index);
+        Ok(())
+    }
+
+    /// Pops the most recently pushed value.
+    pub fn pop(&self) -> Option<T> {
+        let node_index = self.pop_from(&self.head)?;
+        // SAFETY: the node was exclusively claimed off the value list, so
+        // its slot holds the initialized value published by `push`.
+        let value = unsafe { (*self.nodes[node_index as usize].value.get()).assume_init_read() };
+        self.push_onto(&self.free, node_index);
+        Some(value)
+    }
+
+    /// Pushes node `node_index` onto `list`.
+    fn push_onto(&self, list: &AtomicU64, node_index: u32) {
+        let mut current = list.load(Ordering::Relaxed);
+        loop {
+            let (generation, top) = unpack(current);
+            self.nodes[node_index as usize]
+                .next
+                .store(top, Ordering::Relaxed);
+            match list.compare_exchange_weak(
+                current,
+                pack(generation.wrapping_add(1), node_index + 1),
+                Ordering::Release,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return,
+                Err(observed) => current = observed,
+            }
+        }
+    }
+
+    /// Pops a node index off `list`.
+    fn pop_from(&self, list: &AtomicU64) -> Option<u32> {
+        let mut current = list.load(Ordering::Acquire);
+        loop {
+            let (generation, top) = unpack(current);
+            if top == 0 {
+                return None;
+            }
+            let node_index = top - 1;
+            // This read may race with another thread re-linking the node,
+            // but a stale value is discarded below: the generation bump
+            // forces the CAS to fail if the head changed at all.
+            let next = self.nodes[node_index as usize].next.load(Ordering::Relaxed);
+            match list.compare_exchange_weak(
+                current,
+                pack(generation.wrapping_add(1), next),
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => return Some(node_index),
+                Err(observed) => current = observed,
+            }
+        }
+    }
+}
+
+impl<T> Drop for AtomicStack<T> {
+    fn drop(&mut self) {
+        while self.pop().is_some() {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn counter_sums_across_threads() {
+        let counter = Arc::new(AtomicCounter::new());
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let counter = Arc::clone(&counter);
+                std::thread::spawn(move || {
+                    for _ in 0..1000 {
+                        counter.increment();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(counter.load(), 4000);
+    }
+
+    #[test]
+    fn sharded_counter_sums_across_threads() {
+        const THREADS: usize = 8;
+        const INCREMENTS: usize = 10_000;
+        let counter = Arc::new(ShardedCounter::new());
+        let handles: Vec<_> = (0..THREADS)
+            .map(|_| {
+                let counter = Arc::clone(&counter);
+                std::thread::spawn(move || {
+                    for _ in 0..INCREMENTS {
+                        counter.increment();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(counter.load(), (THREADS * INCREMENTS) as u64);
+    }
+
+    #[test]
+    fn sharded_counter_fetch_add_accumulates() {
+        let counter = ShardedCounter::with_shards(4);
+        counter.fetch_add(5);
+        counter.fetch_add(7);
+        assert_eq!(counter.load(), 12);
+    }
+
+    #[test]
+    fn stack_is_lifo_and_bounded() {
+        let stack = AtomicStack::new(3);
+        assert_eq!(stack.capacity(), 3);
+        assert!(stack.push(1).is_ok());
+        assert!(stack.push(2).is_ok());
+        assert!(stack.push(3).is_ok());
+        assert_eq!(stack.push(4), Err(4));
+        assert_eq!(stack.pop(), Some(3));
+        assert_eq!(stack.pop(), Some(2));
+        assert!(stack.push(5).is_ok());
+        assert_eq!(stack.pop(), Some(5));
+        assert_eq!(stack.pop(), Some(1));
+        assert_eq!(stack.pop(), None);
+    }
+
+    #[test]
+    fn stack_conserves_elements_under_contention() {
+        const THREADS: usize = 4;
+        const CYCLES: usize = 5_000;
+
+        struct Payload {
+            id: usize,
+            drops: Arc<super::AtomicCounter>,
+        }
+        impl Drop for Payload {
+            fn drop(&mut self) {
+                self.drops.increment();
+            }
+        }
+
+        let drops = Arc::new(AtomicCounter::new());
+        let created = Arc::new(AtomicCounter::new());
+        let stack = Arc::new(AtomicStack::new(16));
+
+        let handles: Vec<_> = (0..THREADS)
+            .map(|thread| {
+                let stack = Arc::clone(&stack);
+                let drops = Arc::clone(&drops);
+                let created = Arc::clone(&created);
+                std::thread::spawn(move || {
+                    let mut popped_ids = Vec::new();
+                    for cycle in 0..CYCLES {
+                        let payload = Payload {
+                            id: thread * CYCLES + cycle,
+                            drops: Arc::clone(&drops),
+                        };
+                        created.increment();
+                        let _ = stack.push(payload);
+                        if let Some(popped) = stack.pop() {
+                            popped_ids.push(popped.id);
+                        }
+                    }
+                    popped_ids
+                })
+            })
+            .collect();
+
+        let mut seen = std::collections::HashSet::new();
+        for handle in handles {
+            for id in handle.join().unwrap() {
+                assert!(seen.insert(id), "payload {id} popped twice");
+            }
+        }
+        drop(stack);
+        assert_eq!(
+            drops.load(),
+            created.load(),
+            "every payload dropped exactly once"
+        );
+    }
+
+    #[test]
+    fn bitmap_set_and_clear() {
+        let bitmap = AtomicBitmap::new(100);
+        assert_eq!(bitmap.size(), 100);
+        assert!(!bitmap.set(70));
+        assert!(bitmap.is_set(70));
+        assert_eq!(bitmap.count_set(), 1);
+        assert!(bitmap.clear(70));
+        assert!(!bitmap.is_set(70));
+        assert_eq!(bitmap.count_set(), 0);
+    }
+
+    #[test]
+    fn find_and_set_fills_every_bit() {
+        let bitmap = AtomicBitmap::new(67);
+        for expected in 0..67 {
+            assert_eq!(bitmap.find_and_set(), Some(expected));
+        }
+        assert_eq!(bitmap.find_and_set(), None);
+        assert_eq!(bitmap.count_set(), 67);
+    }
+
+    #[test]
+    fn concurrent_drain_returns_each_index_once() {
+        const BITS: usize = 130;
+        let bitmap = Arc::new(AtomicBitmap::new(BITS));
+        for index in 0..BITS {
+            bitmap.set(index);
+        }
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let bitmap = Arc::clone(&bitmap);
+                std::thread::spawn(move || {
+                    let mut drained = Vec::new();
+                    while let Some(index) = bitmap.find_and_clear() {
+                        drained.push(index);
+                    }
+                    drained
+                })
+            })
+            .collect();
+
+        let mut seen = [false; BITS];
+        for handle in handles {
+            for index in handle.join().unwrap() {
+                assert!(!seen[index], "index {index} drained twice");
+                seen[index] = true;
+            }
+        }
+        assert!(seen.iter().all(|&b| b), "every index drained");
+        assert_eq!(bitmap.count_set(), 0);
+    }
+}
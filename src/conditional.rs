@@ -0,0 +1,243 @@
+//! Conditional requests (RFC 7232): ETag generation and precondition
+//! evaluation, so caches can revalidate with 304 instead of refetching.
+
+use crate::http1::{Method, Request};
+use crate::websocket::sha1;
+use std::fmt::Write as _;
+
+/// The outcome of evaluating a request's preconditions (RFC 7232 §6).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Precondition {
+    /// No precondition blocks the request; serve the representation.
+    Proceed,
+    /// The client's copy is current; answer 304 without a body.
+    NotModified,
+    /// A state-changing request's precondition failed; answer 412.
+    PreconditionFailed,
+}
+
+/// Computes a strong entity tag for the exact byte content: the quoted
+/// SHA-1 of the representation (RFC 7232 §2.3).
+pub fn strong_etag(bytes: &[u8]) -> String {
+    let digest = sha1::digest(bytes);
+    let mut out = String::with_capacity(42);
+    out.push('"');
+    for byte in digest {
+        let _ = write!(out, "{byte:02x}");
+    }
+    out.push('"');
+    out
+}
+
+/// Evaluates the request's conditional headers against the current
+/// representation (RFC 7232 §6).
+///
+/// `If-None-Match` is checked first — with weak comparison, so a `W/`
+/// prefix on either side is ignored — and when present makes the request
+/// ignore `If-Modified-Since`. `last_modified` is the representation's
+/// modification time in Unix seconds; `None` skips date validation.
+pub fn evaluate(request: &Request<'_>, etag: &str, last_modified: Option<u64>) -> Precondition {
+    if let Some(if_none_match) = request.header("If-None-Match") {
+        let matched = if_none_match.trim() == "*"
+            || if_none_match
+                .split(',')
+                .any(|candidate| weak_match(candidate.trim(), etag));
+        if !matched {
+            return Precondition::Proceed;
+        }
+        // A match blocks the request: cache hit for reads, lost-update
+        // protection for writes (§3.2).
+        return match request.method {
+            Method::Get | Method::Head => Precondition::NotModified,
+            _ => Precondition::PreconditionFailed,
+        };
+    }
+
+    if let (Some(since), Some(modified)) = (
+        request.header("If-Modified-Since").and_then(parse_imf_fixdate),
+        last_modified,
+    ) {
+        if modified <= since {
+            return Precondition::NotModified;
+        }
+    }
+    Precondition::Proceed
+}
+
+/// Compares two entity tags weakly (RFC 7232 §2.3.2): the `W/` prefix on
+/// either side is ignored, the quoted opaque parts must match exactly.
+fn weak_match(a: &str, b: &str) -> bool {
+    let opaque = |tag: &str| tag.strip_prefix("W/").unwrap_or(tag).to_owned();
+    !a.is_empty() && opaque(a) == opaque(b)
+}
+
+/// Parses an IMF-fixdate (`Sun, 06 Nov 1994 08:49:37 GMT`) into Unix
+/// seconds, returning `None` for the obsolete date forms or any
+/// ill-formed value.
+fn parse_imf_fixdate(value: &str) -> Option<u64> {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let mut parts = value.split(' ');
+    let weekday = parts.next()?;
+    if weekday.len() != 4 || !weekday.ends_with(',') {
+        return None;
+    }
+    let day: u64 = parts.next()?.parse().ok()?;
+    let month_name = parts.next()?;
+    let month = MONTHS.iter().position(|m| *m == month_name)? as u64 + 1;
+    let year: i64 = parts.next()?.parse().ok()?;
+    let time = parts.next()?;
+    if parts.next() != Some("GMT") || parts.next().is_some() {
+        return None;
+    }
+    let mut fields = time.split(':');
+    let hour: u64 = fields.next()?.parse().ok()?;
+    let minute: u64 = fields.next()?.parse().ok()?;
+    let second: u64 = fields.next()?.parse().ok()?;
+    if day == 0 || day > 31 || hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    let days = days_from_civil(year, month, day);
+    u64::try_from(days * 86_400).ok()?.checked_add(hour * 3600 + minute * 60 + second)
+}
+
+/// Days since the Unix epoch for a civil date; the inverse of the
+/// response builder's date decomposition.
+fn days_from_civil(year: i64, month: u64, day: u64) -> i64 {
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month = month as i64;
+    let month_march = if month > 2 { month - 3 } else { month + 9 };
+    let day_of_year = (153 * month_march + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http1::{Header, Version};
+    use std::borrow::Cow;
+
+    fn request<'a>(method: Method, headers: Vec<Header<'a>>) -> Request<'a> {
+        Request {
+            method,
+            target: "/asset.css",
+            version: Version::Http11,
+            headers,
+            body: Cow::Borrowed(&[]),
+            trailers: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn strong_etag_is_quoted_and_content_addressed() {
+        let etag = strong_etag(b"hello");
+        assert!(etag.starts_with('"') && etag.ends_with('"'));
+        assert_eq!(etag.len(), 42);
+        assert_eq!(etag, strong_etag(b"hello"));
+        assert_ne!(etag, strong_etag(b"hello!"));
+    }
+
+    #[test]
+    fn matching_etag_yields_not_modified() {
+        let etag = strong_etag(b"body");
+        let headers = vec![Header {
+            name: "If-None-Match",
+            value: &etag,
+        }];
+        assert_eq!(
+            evaluate(&request(Method::Get, headers), &etag, None),
+            Precondition::NotModified
+        );
+
+        // Weak comparison and the `*` form also match.
+        let weak = format!("W/{etag}");
+        let headers = vec![Header {
+            name: "If-None-Match",
+            value: &weak,
+        }];
+        assert_eq!(
+            evaluate(&request(Method::Get, headers), &etag, None),
+            Precondition::NotModified
+        );
+        let headers = vec![Header {
+            name: "If-None-Match",
+            value: "*",
+        }];
+        assert_eq!(
+            evaluate(&request(Method::Get, headers), &etag, None),
+            Precondition::NotModified
+        );
+    }
+
+    #[test]
+    fn matching_etag_fails_a_state_changing_request() {
+        let etag = strong_etag(b"body");
+        let headers = vec![Header {
+            name: "If-None-Match",
+            value: &etag,
+        }];
+        assert_eq!(
+            evaluate(&request(Method::Put, headers), &etag, None),
+            Precondition::PreconditionFailed
+        );
+    }
+
+    #[test]
+    fn stale_if_modified_since_proceeds() {
+        let headers = vec![Header {
+            name: "If-Modified-Since",
+            value: "Sun, 06 Nov 1994 08:49:37 GMT",
+        }];
+        // Modified one second after the client's copy: serve it.
+        assert_eq!(
+            evaluate(&request(Method::Get, headers.clone()), "\"x\"", Some(784_111_778)),
+            Precondition::Proceed
+        );
+        // Unchanged since: revalidate with 304.
+        assert_eq!(
+            evaluate(&request(Method::Get, headers), "\"x\"", Some(784_111_777)),
+            Precondition::NotModified
+        );
+    }
+
+    #[test]
+    fn if_none_match_shadows_if_modified_since() {
+        let headers = vec![
+            Header {
+                name: "If-None-Match",
+                value: "\"other\"",
+            },
+            Header {
+                name: "If-Modified-Since",
+                value: "Sun, 06 Nov 1994 08:49:37 GMT",
+            },
+        ];
+        // The ETag mismatch wins even though the date says unchanged.
+        assert_eq!(
+            evaluate(&request(Method::Get, headers), "\"x\"", Some(0)),
+            Precondition::Proceed
+        );
+    }
+
+    #[test]
+    fn imf_fixdate_parses_round_trip() {
+        assert_eq!(
+            parse_imf_fixdate("Sun, 06 Nov 1994 08:49:37 GMT"),
+            Some(784_111_777)
+        );
+        assert_eq!(parse_imf_fixdate("Thu, 01 Jan 1970 00:00:00 GMT"), Some(0));
+        for bad in [
+            "06 Nov 1994 08:49:37 GMT",
+            "Sunday, 06-Nov-94 08:49:37 GMT",
+            "Sun, 06 Nov 1994 08:49:37 PST",
+            "Sun, 06 Xxx 1994 08:49:37 GMT",
+        ] {
+            assert_eq!(parse_imf_fixdate(bad), None, "{bad:?} should not parse");
+        }
+    }
+}
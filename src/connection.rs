@@ -0,0 +1,2537 @@
+//! The per-connection state machine: protocol detection, buffered reads, and
+//! HTTP/1.x request processing.
+
+use crate::error::Error;
+use crate::http1::{Http1ParseError, Http1Parser, Method, Request, Version};
+use crate::http2::{
+    self, FrameType, HeaderBlockAssembler, Http2FrameBuilder, Http2Parser, Http2ParseError,
+};
+use crate::metrics::{ConnectionMetrics, ParserMetrics};
+use crate::streams::{
+    PriorityTree, StreamManager, ENHANCE_YOUR_CALM, FRAME_SIZE_ERROR, PROTOCOL_ERROR,
+    REFUSED_STREAM,
+};
+use std::collections::{BTreeSet, HashMap};
+use std::io::{Read, Write};
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// The client preface that opens an HTTP/2 connection (RFC 7540 §3.5).
+pub const HTTP2_PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+/// Streams that support socket-level read/write deadlines.
+///
+/// [`Connection`] sets the configured timeouts through this trait so a
+/// stalled peer surfaces as [`Error::Timeout`] instead of holding the
+/// connection open indefinitely.
+pub trait Timeouts {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()>;
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()>;
+}
+
+impl Timeouts for std::net::TcpStream {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()> {
+        std::net::TcpStream::set_read_timeout(self, timeout)
+    }
+
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()> {
+        std::net::TcpStream::set_write_timeout(self, timeout)
+    }
+}
+
+/// Maps the I/O errors a timed-out socket produces to [`Error::Timeout`].
+fn map_io_error(err: std::io::Error) -> Error {
+    match err.kind() {
+        std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut => Error::Timeout,
+        _ => Error::Io(err),
+    }
+}
+
+/// Tunable limits and timeouts for a connection.
+#[derive(Debug, Clone)]
+pub struct ConnectionConfig {
+    /// Initial size of the read buffer.
+    pub read_buffer_size: usize,
+    /// Upper bound on the size of a single request.
+    pub max_request_size: usize,
+    /// Most pipelined requests drained from the buffer in one batch.
+    pub max_pipeline_depth: usize,
+    /// Response bytes are accumulated up to this size before an automatic
+    /// flush; [`Connection::flush`] forces out whatever is pending.
+    pub write_buffer_size: usize,
+    pub read_timeout: Duration,
+    pub write_timeout: Duration,
+    /// How long an idle keep-alive connection is retained.
+    pub keep_alive_timeout: Duration,
+    /// Minimum sustained receive rate. A client that stays below this for
+    /// longer than `throughput_grace` fails with [`Error::Timeout`], even
+    /// though each trickled byte resets the per-read activity clock
+    /// (the "slowloris" pattern). `0` disables the guard.
+    pub min_bytes_per_sec: u64,
+    /// How long a connection may stay under `min_bytes_per_sec` before the
+    /// throughput guard fires. Also the width of the measurement window.
+    pub throughput_grace: Duration,
+    /// Most requests a single keep-alive connection may serve before it is
+    /// retired, so one client cannot monopolize a worker. `0` removes the
+    /// cap.
+    pub max_requests_per_connection: u64,
+    /// Upper bound on one reassembled header block across a HEADERS frame
+    /// and its CONTINUATION frames, independent of the frame-size limit.
+    pub max_header_block_size: usize,
+    /// Most CONTINUATION frames accepted for one header block, so a flood
+    /// of tiny fragments cannot stall the connection.
+    pub max_continuation_frames: usize,
+    /// Upper bound on the decompressed size of one HTTP/2 header list,
+    /// counted as RFC 7540 §6.5.2 counts `SETTINGS_MAX_HEADER_LIST_SIZE`:
+    /// name plus value plus 32 octets of overhead per field. Bounds HPACK
+    /// expansion, so a small block cannot decompress into gigabytes.
+    pub max_header_list_size: u32,
+}
+
+impl Default for ConnectionConfig {
+    fn default() -> Self {
+        Self {
+            read_buffer_size: 8192,
+            max_request_size: 1024 * 1024,
+            max_pipeline_depth: 32,
+            write_buffer_size: 8192,
+            read_timeout: Duration::from_secs(30),
+            write_timeout: Duration::from_secs(30),
+            keep_alive_timeout: Duration::from_secs(60),
+            min_bytes_per_sec: 0,
+            throughput_grace: Duration::from_secs(5),
+            max_requests_per_connection: 0,
+            max_header_block_size: 64 * 1024,
+            max_continuation_frames: 32,
+            max_header_list_size: 16 * 1024,
+        }
+    }
+}
+
+/// The application protocol detected on a fresh connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    Http1,
+    Http2,
+    /// A TLS handshake record; the stream must go through a
+    /// [`crate::tls::TlsAcceptor`] before HTTP detection can continue.
+    Tls,
+    /// The initial bytes match no protocol this crate speaks.
+    Unknown,
+}
+
+impl Protocol {
+    /// Maps an ALPN protocol identifier (RFC 7301) to the protocol the
+    /// connection should speak, e.g. from
+    /// [`crate::tls::TlsStream::negotiated_protocol`]. Identifiers this
+    /// crate does not recognize yield `None`, leaving the connection to
+    /// fall back to detection.
+    pub fn from_alpn(identifier: &[u8]) -> Option<Self> {
+        match identifier {
+            b"h2" => Some(Protocol::Http2),
+            b"http/1.1" | b"http/1.0" => Some(Protocol::Http1),
+            _ => None,
+        }
+    }
+}
+
+/// The outcome of protocol detection.
+#[derive(Debug, Clone, Copy)]
+pub struct Detection {
+    pub protocol: Protocol,
+    /// Bytes of the buffer consumed by detection itself.
+    pub consumed: usize,
+}
+
+const HTTP1_METHODS: [&[u8]; 9] = [
+    b"GET ", b"HEAD ", b"POST ", b"PUT ", b"DELETE ", b"CONNECT ", b"OPTIONS ", b"TRACE ",
+    b"PATCH ",
+];
+
+/// Returns whether the buffer starts with a complete HTTP/1.x method and
+/// its trailing space.
+fn starts_with_http1_method(buf: &[u8]) -> bool {
+    HTTP1_METHODS.iter().any(|m| buf.starts_with(m))
+}
+
+/// Returns whether the buffer could still become an HTTP/1.x method once
+/// more bytes arrive.
+fn is_http1_method_prefix(buf: &[u8]) -> bool {
+    HTTP1_METHODS.iter().any(|m| m.starts_with(buf))
+}
+
+/// Inspects the first bytes of a connection and classifies the protocol.
+///
+/// Returns `None` when more data is required to decide — including when the
+/// buffered bytes are a valid prefix of a method or of the HTTP/2 preface.
+/// `consumed` is 0 for HTTP/1 (the request line is parsed in place) and the
+/// preface length for HTTP/2.
+pub fn detect_protocol(buf: &[u8]) -> Option<Detection> {
+    // A TLS record header: content-type Handshake (0x16), version 3.x. The
+    // record itself is left in place for the TLS acceptor to read.
+    if buf.first() == Some(&0x16) {
+        if buf.len() < 3 {
+            return None;
+        }
+        if buf[1] == 0x03 && buf[2] <= 0x04 {
+            return Some(Detection {
+                protocol: Protocol::Tls,
+                consumed: 0,
+            });
+        }
+    }
+    if buf.len() >= HTTP2_PREFACE.len() {
+        if &buf[..HTTP2_PREFACE.len()] == HTTP2_PREFACE {
+            return Some(Detection {
+                protocol: Protocol::Http2,
+                consumed: HTTP2_PREFACE.len(),
+            });
+        }
+    } else if HTTP2_PREFACE.starts_with(buf) {
+        // `GET ` et al. are not preface prefixes, so an empty or ambiguous
+        // buffer lands here and asks for more data.
+        return None;
+    }
+    if starts_with_http1_method(buf) {
+        return Some(Detection {
+            protocol: Protocol::Http1,
+            consumed: 0,
+        });
+    }
+    if is_http1_method_prefix(buf) {
+        return None;
+    }
+    Some(Detection {
+        protocol: Protocol::Unknown,
+        consumed: 0,
+    })
+}
+
+/// Protocol-level state for an HTTP/2 connection.
+#[derive(Debug, Clone)]
+pub struct Http2State {
+    pub parser: Http2Parser,
+    /// Whether the client connection preface has been consumed. The preface
+    /// arrives after the 101 response on an h2c upgrade, but has already
+    /// been read when the connection was detected by prior knowledge.
+    pub preface_received: bool,
+    /// The highest stream id seen from the client, advertised in GOAWAY.
+    pub last_stream_id: u32,
+    /// Stream bookkeeping; its concurrency limit follows
+    /// `SETTINGS_MAX_CONCURRENT_STREAMS`.
+    pub streams: StreamManager,
+    /// The dependency tree the client shapes with PRIORITY frames.
+    pub priorities: PriorityTree,
+    /// Connection-level flow-control windows.
+    pub flow: http2::FlowController,
+    /// A partial frame reassembled across reads. Bounded by the frame
+    /// header plus `SETTINGS_MAX_FRAME_SIZE`, since
+    /// [`Http2Parser::parse_frame_header`] rejects anything larger.
+    pub frame_buffer: Vec<u8>,
+    /// A header block mid-reassembly across HEADERS + CONTINUATION,
+    /// bounded by the connection's header-block caps.
+    pub header_block: HeaderBlockAssembler,
+    /// When the server last sent a SETTINGS frame the peer has not yet
+    /// acknowledged; `None` when nothing is outstanding.
+    settings_sent_at: Option<Instant>,
+}
+
+impl Http2State {
+    /// Records that a SETTINGS frame was just written and now awaits the
+    /// peer's acknowledgement.
+    pub fn note_settings_sent(&mut self) {
+        self.note_settings_sent_at(Instant::now());
+    }
+
+    fn note_settings_sent_at(&mut self, now: Instant) {
+        self.settings_sent_at = Some(now);
+    }
+
+    /// Clears the outstanding SETTINGS; called when the peer's ACK
+    /// arrives.
+    pub fn acknowledge_settings(&mut self) {
+        self.settings_sent_at = None;
+    }
+
+    /// Checks whether an outstanding SETTINGS has gone unacknowledged for
+    /// longer than `timeout`; RFC 7540 §6.5.3 lets the connection treat
+    /// that as a connection error of type SETTINGS_TIMEOUT.
+    pub fn check_settings_ack(&self, timeout: Duration) -> Result<(), Http2ParseError> {
+        self.check_settings_ack_at(timeout, Instant::now())
+    }
+
+    fn check_settings_ack_at(
+        &self,
+        timeout: Duration,
+        now: Instant,
+    ) -> Result<(), Http2ParseError> {
+        match self.settings_sent_at {
+            Some(sent) if now.duration_since(sent) > timeout => {
+                Err(Http2ParseError::SettingsTimeout)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Default for Http2State {
+    fn default() -> Self {
+        Self {
+            parser: Http2Parser::default(),
+            preface_received: false,
+            last_stream_id: 0,
+            // The server's own cap until the peer's SETTINGS arrive.
+            streams: StreamManager::new(Some(100)),
+            priorities: PriorityTree::new(),
+            flow: http2::FlowController::default(),
+            frame_buffer: Vec::new(),
+            header_block: HeaderBlockAssembler::new(),
+            settings_sent_at: None,
+        }
+    }
+}
+
+/// Protocol-level state for an HTTP/1.x connection.
+#[derive(Debug, Clone, Default)]
+pub struct Http1State {
+    /// Whether the connection persists after the current response.
+    pub keep_alive: bool,
+    /// Number of requests queued ahead of the current one by a pipelining
+    /// client.
+    pub pipeline_depth: usize,
+    /// Whether an interim `100 Continue` has been written for the request
+    /// currently being received.
+    pub continue_sent: bool,
+}
+
+/// The lifecycle state of a connection.
+#[derive(Debug, Clone)]
+pub enum ConnectionState {
+    /// Waiting for enough bytes to classify the protocol.
+    Detecting,
+    Http1(Http1State),
+    /// Boxed: the HTTP/2 state (stream tables, priority tree, frame
+    /// accumulator) dwarfs every other variant.
+    Http2(Box<Http2State>),
+    /// Finish in-flight work, then close.
+    Closing,
+    Closed,
+}
+
+/// What the caller should do after a call to [`Connection::process`].
+#[derive(Debug)]
+pub enum ConnectionAction {
+    /// More input is needed; read and call `process` again.
+    NeedMore,
+    /// One or more complete requests, in arrival order, are ready for the
+    /// handler.
+    Requests(Vec<HttpRequest>),
+    /// An HTTP/2 CONNECT stream requests a tunnel (RFC 7540 §8.3), or —
+    /// when `protocol` is set — an extended CONNECT such as WebSockets
+    /// over HTTP/2 (RFC 8441).
+    Connect {
+        stream_id: u32,
+        authority: String,
+        protocol: Option<String>,
+    },
+    /// The connection is done; drop it.
+    Close,
+}
+
+/// An owned request handed across the connection boundary, detached from the
+/// read buffer so the buffer can be reused immediately.
+#[derive(Debug, Clone)]
+pub struct HttpRequest {
+    pub method: Method,
+    pub target: String,
+    pub version: Version,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpRequest {
+    fn from_parsed(request: &Request<'_>) -> Self {
+        Self {
+            method: request.method,
+            target: request.target.to_owned(),
+            version: request.version,
+            headers: request
+                .headers
+                .iter()
+                .map(|h| (h.name.to_owned(), h.value.to_owned()))
+                .collect(),
+            body: request.body.to_vec(),
+        }
+    }
+
+    /// Returns the value of the first header with the given name,
+    /// compared case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Returns the path portion of the request target, excluding any query.
+    pub fn path(&self) -> &str {
+        match self.target.find('?') {
+            Some(idx) => &self.target[..idx],
+            None => &self.target,
+        }
+    }
+}
+
+/// A single client connection over any `Read + Write` stream.
+pub struct Connection<S> {
+    stream: S,
+    peer_addr: SocketAddr,
+    config: ConnectionConfig,
+    state: ConnectionState,
+    parser: Http1Parser,
+    parser_metrics: ParserMetrics,
+    metrics: ConnectionMetrics,
+    read_buffer: Vec<u8>,
+    read_len: usize,
+    write_buffer: Vec<u8>,
+    timeouts_applied: bool,
+    last_activity: Instant,
+    /// Start of the current throughput measurement window.
+    window_start: Instant,
+    /// Bytes received since `window_start`.
+    window_bytes: u64,
+}
+
+impl<S: Read + Write + Timeouts> Connection<S> {
+    pub fn new(stream: S, peer_addr: SocketAddr, config: ConnectionConfig) -> Self {
+        let read_buffer = vec![0; config.read_buffer_size];
+        Self {
+            stream,
+            peer_addr,
+            config,
+            state: ConnectionState::Detecting,
+            parser: Http1Parser::new(),
+            parser_metrics: ParserMetrics::new(),
+            metrics: ConnectionMetrics::default(),
+            read_buffer,
+            read_len: 0,
+            write_buffer: Vec::new(),
+            timeouts_applied: false,
+            last_activity: Instant::now(),
+            window_start: Instant::now(),
+            window_bytes: 0,
+        }
+    }
+
+    /// Like [`Connection::new`], but seeds the initial state from an
+    /// already-negotiated protocol — typically the ALPN result of a TLS
+    /// handshake ([`crate::tls::TlsStream::negotiated_protocol`]) — instead
+    /// of sniffing the stream. `h2` connections still expect the client
+    /// preface; anything other than HTTP/1.x or HTTP/2 falls back to
+    /// detection.
+    pub fn new_with_protocol(
+        stream: S,
+        peer_addr: SocketAddr,
+        config: ConnectionConfig,
+        protocol: Option<Protocol>,
+    ) -> Self {
+        let mut conn = Self::new(stream, peer_addr, config);
+        match protocol {
+            Some(Protocol::Http1) => {
+                conn.state = ConnectionState::Http1(Http1State::default());
+            }
+            Some(Protocol::Http2) => {
+                conn.state = ConnectionState::Http2(Box::default());
+            }
+            Some(Protocol::Tls) | Some(Protocol::Unknown) | None => {}
+        }
+        conn
+    }
+
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.peer_addr
+    }
+
+    pub fn state(&self) -> &ConnectionState {
+        &self.state
+    }
+
+    /// Whether the connection has stopped accepting new requests; the
+    /// final response should then advertise `Connection: close`.
+    pub fn is_closing(&self) -> bool {
+        matches!(
+            self.state,
+            ConnectionState::Closing | ConnectionState::Closed
+        )
+    }
+
+    pub fn metrics(&self) -> &ConnectionMetrics {
+        &self.metrics
+    }
+
+    pub fn parser_metrics(&self) -> &ParserMetrics {
+        &self.parser_metrics
+    }
+
+    /// Whether the connection has been idle past its keep-alive timeout.
+    ///
+    /// The periodic timeout sweep is also where abandoned HTTP/2 streams
+    /// — finished or half-closed but never cleaned up — are reaped.
+    pub fn is_timed_out(&mut self) -> bool {
+        if let ConnectionState::Http2(http2) = &mut self.state {
+            http2.streams.cleanup_idle(self.config.keep_alive_timeout);
+        }
+        self.last_activity.elapsed() > self.config.keep_alive_timeout
+    }
+
+    /// Stops accepting new requests and closes once in-flight work is done.
+    ///
+    /// For HTTP/1.1 the connection drops out of keep-alive — the response
+    /// to the current request should carry `Connection: close` — and no
+    /// further request is parsed. For HTTP/2 a GOAWAY advertising the last
+    /// processed stream is sent before draining. Subsequent `process` calls
+    /// return [`ConnectionAction::Close`].
+    pub fn begin_graceful_shutdown(&mut self) -> Result<(), Error> {
+        match &mut self.state {
+            ConnectionState::Detecting => self.state = ConnectionState::Closed,
+            ConnectionState::Http1(http1) => {
+                http1.keep_alive = false;
+                self.state = ConnectionState::Closing;
+            }
+            ConnectionState::Http2(http2) => {
+                let last_stream_id = http2.last_stream_id;
+                let goaway = Http2FrameBuilder::new().goaway(last_stream_id, 0);
+                self.write_all(&goaway)?;
+                self.flush()?;
+                self.state = ConnectionState::Closing;
+            }
+            ConnectionState::Closing | ConnectionState::Closed => {}
+        }
+        Ok(())
+    }
+
+    /// Applies the configured socket timeouts once, before the first read
+    /// or write touches the stream.
+    fn apply_timeouts(&mut self) -> Result<(), Error> {
+        if self.timeouts_applied {
+            return Ok(());
+        }
+        self.stream.set_read_timeout(Some(self.config.read_timeout))?;
+        self.stream.set_write_timeout(Some(self.config.write_timeout))?;
+        self.timeouts_applied = true;
+        Ok(())
+    }
+
+    /// Reads whatever the stream has available into the internal buffer,
+    /// returning the number of bytes read (0 at EOF). A full buffer grows
+    /// — doubling up to the request-size cap — so a request larger than
+    /// the initial buffer can still complete instead of deadlocking on
+    /// `IncompleteRequest`. A read that stalls past the configured
+    /// `read_timeout` fails with [`Error::Timeout`].
+    pub fn read_available(&mut self) -> Result<usize, Error> {
+        if self.read_len == self.read_buffer.len() {
+            self.grow_read_buffer()?;
+        }
+        self.apply_timeouts()?;
+        let n = self
+            .stream
+            .read(&mut self.read_buffer[self.read_len..])
+            .map_err(map_io_error)?;
+        self.read_len += n;
+        self.metrics.bytes_read += n as u64;
+        if n > 0 {
+            self.last_activity = Instant::now();
+            self.window_bytes += n as u64;
+            self.check_throughput()?;
+        }
+        Ok(n)
+    }
+
+    /// Enforces the minimum-throughput (slowloris) guard. Once a full
+    /// grace window has elapsed, a connection whose average receive rate
+    /// over that window fell below `min_bytes_per_sec` fails with
+    /// [`Error::Timeout`]; a healthy window starts the next measurement.
+    fn check_throughput(&mut self) -> Result<(), Error> {
+        if self.config.min_bytes_per_sec == 0 {
+            return Ok(());
+        }
+        let elapsed = self.window_start.elapsed();
+        if elapsed < self.config.throughput_grace {
+            return Ok(());
+        }
+        let rate = self.window_bytes.saturating_mul(1000) / (elapsed.as_millis().max(1) as u64);
+        if rate < self.config.min_bytes_per_sec {
+            return Err(Error::Timeout);
+        }
+        self.window_start = Instant::now();
+        self.window_bytes = 0;
+        Ok(())
+    }
+
+    /// Doubles the read buffer, bounded by `max_request_size`. A buffer
+    /// already at the cap holds a request that can never complete, so the
+    /// connection fails with `RequestTooLarge` rather than stalling.
+    fn grow_read_buffer(&mut self) -> Result<(), Error> {
+        let cap = self.config.max_request_size;
+        if self.read_buffer.len() >= cap {
+            return Err(Http1ParseError::RequestTooLarge.into());
+        }
+        let grown = (self.read_buffer.len() * 2).min(cap);
+        self.read_buffer.resize(grown, 0);
+        Ok(())
+    }
+
+    /// Appends the slice to the write buffer, flushing to the stream once
+    /// the buffer crosses the configured threshold. Response fragments
+    /// (status line, headers, body) therefore coalesce into one stream
+    /// write instead of a syscall each.
+    pub fn write_all(&mut self, data: &[u8]) -> Result<(), Error> {
+        self.write_buffer.extend_from_slice(data);
+        if self.write_buffer.len() >= self.config.write_buffer_size {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Writes any buffered response bytes to the stream in one call. A
+    /// write that stalls past the configured `write_timeout` fails with
+    /// [`Error::Timeout`].
+    pub fn flush(&mut self) -> Result<(), Error> {
+        if self.write_buffer.is_empty() {
+            return Ok(());
+        }
+        self.apply_timeouts()?;
+        self.stream.write_all(&self.write_buffer).map_err(map_io_error)?;
+        self.stream.flush().map_err(map_io_error)?;
+        self.metrics.bytes_written += self.write_buffer.len() as u64;
+        self.write_buffer.clear();
+        self.last_activity = Instant::now();
+        Ok(())
+    }
+
+    /// Advances the state machine over the currently buffered bytes.
+    ///
+    /// Any response bytes produced along the way (interim responses, frame
+    /// acknowledgements) are flushed before returning.
+    pub fn process(&mut self) -> Result<ConnectionAction, Error> {
+        let result = self.process_state();
+        self.flush()?;
+        result
+    }
+
+    fn process_state(&mut self) -> Result<ConnectionAction, Error> {
+        match &self.state {
+            ConnectionState::Detecting => {
+                let detection = match detect_protocol(&self.read_buffer[..self.read_len]) {
+                    Some(d) => d,
+                    None => return Ok(ConnectionAction::NeedMore),
+                };
+                match detection.protocol {
+                    Protocol::Http1 => {
+                        self.consume(detection.consumed);
+                        self.state = ConnectionState::Http1(Http1State::default());
+                        self.process_http1()
+                    }
+                    Protocol::Http2 => {
+                        // Prior knowledge: detection consumed the preface.
+                        self.consume(detection.consumed);
+                        self.state = ConnectionState::Http2(Box::new(Http2State {
+                            preface_received: true,
+                            ..Http2State::default()
+                        }));
+                        self.process_http2()
+                    }
+                    Protocol::Tls => Err(Error::TlsError(
+                        "TLS handshake received on a plaintext connection; \
+                         terminate TLS via TlsAcceptor before handing the \
+                         stream to Connection"
+                            .into(),
+                    )),
+                    Protocol::Unknown => {
+                        Err(Error::ParseError("unrecognized application protocol".into()))
+                    }
+                }
+            }
+            ConnectionState::Http1(_) => self.process_http1(),
+            ConnectionState::Http2(_) => self.process_http2(),
+            ConnectionState::Closing | ConnectionState::Closed => Ok(ConnectionAction::Close),
+        }
+    }
+
+    /// Parses and dispatches buffered HTTP/1.x input, draining as many
+    /// pipelined requests as are completely buffered (bounded by
+    /// `max_pipeline_depth`). A trailing partial request stays buffered for
+    /// the next read.
+    fn process_http1(&mut self) -> Result<ConnectionAction, Error> {
+        let mut requests = Vec::new();
+        while requests.len() < self.config.max_pipeline_depth {
+            let start = Instant::now();
+            let parsed = self
+                .parser
+                .parse_request(&self.read_buffer[..self.read_len]);
+            match parsed {
+                Ok((request, consumed)) => {
+                    self.parser_metrics.record_parse(start.elapsed());
+                    // The connection-level cap is authoritative over the
+                    // parser's own limit and counts the request as it
+                    // arrived on the wire, chunked framing included.
+                    if consumed > self.config.max_request_size {
+                        self.parser_metrics.record_error();
+                        if requests.is_empty() {
+                            return Err(Http1ParseError::RequestTooLarge.into());
+                        }
+                        break;
+                    }
+                    if let Some(settings) = Self::h2c_upgrade_settings(&request) {
+                        let owned = HttpRequest::from_parsed(&request);
+                        self.consume(consumed);
+                        self.upgrade_to_h2c(&settings)?;
+                        self.metrics.requests_served += 1;
+                        requests.push(owned);
+                        break;
+                    }
+                    let expectation_failed = request
+                        .header("Expect")
+                        .is_some_and(|v| !v.eq_ignore_ascii_case("100-continue"));
+                    let keep_alive = Self::keep_alive_for(&request);
+                    let owned = HttpRequest::from_parsed(&request);
+                    self.consume(consumed);
+                    if expectation_failed {
+                        self.write_all(
+                            b"HTTP/1.1 417 Expectation Failed\r\n\
+                              Content-Length: 0\r\nConnection: close\r\n\r\n",
+                        )?;
+                        self.state = ConnectionState::Closing;
+                        if requests.is_empty() {
+                            return Ok(ConnectionAction::Close);
+                        }
+                        break;
+                    }
+                    requests.push(owned);
+                    self.metrics.requests_served += 1;
+                    let limit = self.config.max_requests_per_connection;
+                    if limit != 0 && self.metrics.requests_served >= limit {
+                        // The reuse quota is spent; this request is the last
+                        // one this connection will serve.
+                        self.state = ConnectionState::Closing;
+                        break;
+                    }
+                    if keep_alive {
+                        if let ConnectionState::Http1(http1) = &mut self.state {
+                            http1.keep_alive = true;
+                            http1.continue_sent = false;
+                        }
+                    } else {
+                        // The connection must not outlive this exchange; the
+                        // caller still gets the request, but no further ones
+                        // are accepted.
+                        self.state = ConnectionState::Closing;
+                        break;
+                    }
+                }
+                Err(Http1ParseError::IncompleteRequest) => {
+                    if requests.is_empty() {
+                        if self.declared_body_too_large() {
+                            self.parser_metrics.record_error();
+                            return Err(Http1ParseError::RequestTooLarge.into());
+                        }
+                        if let Some(action) = self.handle_expectation()? {
+                            return Ok(action);
+                        }
+                        return Ok(ConnectionAction::NeedMore);
+                    }
+                    break;
+                }
+                Err(e) => {
+                    self.parser_metrics.record_error();
+                    if requests.is_empty() {
+                        return Err(e.into());
+                    }
+                    // Deliver what was drained; the malformed bytes are
+                    // still buffered and will error on the next call.
+                    break;
+                }
+            }
+        }
+        if let ConnectionState::Http1(http1) = &mut self.state {
+            http1.pipeline_depth = requests.len();
+        }
+        Ok(ConnectionAction::Requests(requests))
+    }
+
+    /// Rejects a buffered head whose declared `Content-Length` already
+    /// exceeds the connection's request-size cap, without waiting for —
+    /// or buffering — any of the body.
+    fn declared_body_too_large(&self) -> bool {
+        match self.parser.parse_head(&self.read_buffer[..self.read_len]) {
+            Ok((head, _)) => head
+                .header("Content-Length")
+                .and_then(|v| v.trim().parse::<usize>().ok())
+                .is_some_and(|len| len > self.config.max_request_size),
+            Err(_) => false,
+        }
+    }
+
+    /// Determines connection persistence per RFC 7230 §6.3: HTTP/1.1
+    /// defaults to keep-alive unless the client sends `Connection: close`,
+    /// while HTTP/1.0 defaults to close unless it sends
+    /// `Connection: keep-alive`.
+    fn keep_alive_for(request: &Request<'_>) -> bool {
+        let has_token = |token: &str| {
+            request.header("Connection").is_some_and(|v| {
+                v.split(',').any(|t| t.trim().eq_ignore_ascii_case(token))
+            })
+        };
+        match request.version {
+            Version::Http11 => !has_token("close"),
+            Version::Http10 => has_token("keep-alive"),
+        }
+    }
+
+    /// Once the header section of a request is buffered, honors any `Expect`
+    /// header before the body arrives: writes the interim `100 Continue` for
+    /// `100-continue`, or fails the request with 417 for anything else.
+    fn handle_expectation(&mut self) -> Result<Option<ConnectionAction>, Error> {
+        if let ConnectionState::Http1(http1) = &self.state {
+            if http1.continue_sent {
+                return Ok(None);
+            }
+        }
+        let expectation = match self.parser.parse_head(&self.read_buffer[..self.read_len]) {
+            Ok((head, _)) => head.header("Expect").map(str::to_owned),
+            // Head not fully buffered (or malformed — reported on a later
+            // full parse); nothing to do yet.
+            Err(_) => None,
+        };
+        match expectation {
+            Some(value) if value.eq_ignore_ascii_case("100-continue") => {
+                self.write_all(b"HTTP/1.1 100 Continue\r\n\r\n")?;
+                if let ConnectionState::Http1(http1) = &mut self.state {
+                    http1.continue_sent = true;
+                }
+                Ok(None)
+            }
+            Some(_) => {
+                self.write_all(
+                    b"HTTP/1.1 417 Expectation Failed\r\n\
+                      Content-Length: 0\r\nConnection: close\r\n\r\n",
+                )?;
+                self.state = ConnectionState::Closing;
+                Ok(Some(ConnectionAction::Close))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the decoded `HTTP2-Settings` pairs when the request is a
+    /// well-formed h2c upgrade (RFC 7540 §3.2). A request with a malformed
+    /// settings header is served as plain HTTP/1.1.
+    fn h2c_upgrade_settings(request: &Request<'_>) -> Option<Vec<(u16, u32)>> {
+        if request.version != Version::Http11 {
+            return None;
+        }
+        let has_token = |header: &str, token: &str| {
+            request.header(header).is_some_and(|v| {
+                v.split(',').any(|t| t.trim().eq_ignore_ascii_case(token))
+            })
+        };
+        if !has_token("Connection", "upgrade") || !has_token("Upgrade", "h2c") {
+            return None;
+        }
+        let encoded = request.header("HTTP2-Settings")?;
+        let payload = http2::decode_base64url(encoded.trim().as_bytes())?;
+        http2::parse_settings(&payload).ok()
+    }
+
+    /// Emits the `101 Switching Protocols` response and transitions into
+    /// HTTP/2, seeded with the settings the client sent in the upgrade.
+    fn upgrade_to_h2c(&mut self, settings: &[(u16, u32)]) -> Result<(), Error> {
+        self.write_all(
+            b"HTTP/1.1 101 Switching Protocols\r\n\
+              Connection: Upgrade\r\nUpgrade: h2c\r\n\r\n",
+        )?;
+        let mut http2 = Http2State::default();
+        http2.parser.update_settings(settings)?;
+        self.state = ConnectionState::Http2(Box::new(http2));
+        Ok(())
+    }
+
+    /// Advertises `ENHANCE_YOUR_CALM` in a GOAWAY and surfaces the header
+    /// flood as a connection error.
+    fn refuse_header_flood(&mut self, error: Http2ParseError) -> Result<ConnectionAction, Error> {
+        let last_stream_id = match &self.state {
+            ConnectionState::Http2(http2) => http2.last_stream_id,
+            _ => 0,
+        };
+        let goaway = Http2FrameBuilder::new().goaway(last_stream_id, ENHANCE_YOUR_CALM);
+        self.write_all(&goaway)?;
+        self.flush()?;
+        Err(error.into())
+    }
+
+    /// Processes buffered HTTP/2 frames: consumes the client preface,
+    /// acknowledges SETTINGS, answers PING, and ignores frames the
+    /// connection does not yet act on.
+    fn process_http2(&mut self) -> Result<ConnectionAction, Error> {
+        // What a parsed frame asks the connection to do, captured so the
+        // borrow of the read buffer ends before any write.
+        enum FrameEffect {
+            ApplySettings(Vec<(u16, u32)>),
+            AckSettings,
+            OpenStream { block: Vec<u8> },
+            BeginHeaders { fragment: Vec<u8> },
+            ContinueHeaders { fragment: Vec<u8>, end_headers: bool },
+            Reprioritize(http2::Priority),
+            ConsumeData(u32),
+            CreditSendWindow(u32),
+            Pong([u8; 8]),
+            Goaway,
+            Nothing,
+        }
+
+        loop {
+            let preface_pending = match &self.state {
+                ConnectionState::Http2(http2) => !http2.preface_received,
+                _ => return Ok(ConnectionAction::Close),
+            };
+            if preface_pending {
+                if self.read_len < HTTP2_PREFACE.len() {
+                    return Ok(ConnectionAction::NeedMore);
+                }
+                if &self.read_buffer[..HTTP2_PREFACE.len()] != HTTP2_PREFACE {
+                    return Err(Http2ParseError::InvalidPreface.into());
+                }
+                self.consume(HTTP2_PREFACE.len());
+                if let ConnectionState::Http2(http2) = &mut self.state {
+                    http2.preface_received = true;
+                }
+            }
+
+            self.fill_frame_accumulator();
+            let from_accumulator = match &self.state {
+                ConnectionState::Http2(http2) => !http2.frame_buffer.is_empty(),
+                _ => false,
+            };
+
+            let parsed = {
+                let ConnectionState::Http2(http2) = &self.state else {
+                    unreachable!("checked above");
+                };
+                let source: &[u8] = if from_accumulator {
+                    &http2.frame_buffer
+                } else {
+                    &self.read_buffer[..self.read_len]
+                };
+                match http2.parser.parse_frame(source) {
+                    Ok((frame, consumed)) => {
+                        let effect = match frame.header.frame_type {
+                            // SETTINGS applies to the connection as a whole
+                            // (RFC 7540 §6.5); a stream id is a protocol
+                            // violation.
+                            FrameType::Settings if frame.header.stream_id != 0 => {
+                                return Err(Http2ParseError::InvalidSettings.into());
+                            }
+                            FrameType::Settings if frame.header.flags & http2::FLAG_ACK != 0 => {
+                                // §6.5: an acknowledgement carries no
+                                // payload; anything else is FRAME_SIZE_ERROR.
+                                if !frame.payload.is_empty() {
+                                    return Err(Http2ParseError::InvalidFrameSize.into());
+                                }
+                                FrameEffect::AckSettings
+                            }
+                            FrameType::Settings => {
+                                let pairs = http2::parse_settings(frame.payload)?;
+                                FrameEffect::ApplySettings(pairs)
+                            }
+                            // A HEADERS frame without END_HEADERS only
+                            // opens a block; CONTINUATION frames finish it.
+                            FrameType::Headers
+                                if frame.header.flags & http2::FLAG_END_HEADERS == 0 =>
+                            {
+                                FrameEffect::BeginHeaders {
+                                    fragment: http2::headers_fragment(
+                                        frame.header.flags,
+                                        frame.payload,
+                                    )?
+                                    .to_vec(),
+                                }
+                            }
+                            FrameType::Headers => FrameEffect::OpenStream {
+                                block: http2::headers_fragment(
+                                    frame.header.flags,
+                                    frame.payload,
+                                )?
+                                .to_vec(),
+                            },
+                            FrameType::Continuation => FrameEffect::ContinueHeaders {
+                                fragment: frame.payload.to_vec(),
+                                end_headers: frame.header.flags & http2::FLAG_END_HEADERS != 0,
+                            },
+                            FrameType::Priority => {
+                                FrameEffect::Reprioritize(http2::parse_priority(frame.payload)?)
+                            }
+                            // DATA is stream payload; stream 0 carries no
+                            // data and such a frame is a connection error
+                            // of type PROTOCOL_ERROR (RFC 7540 §6.1).
+                            FrameType::Data if frame.header.stream_id == 0 => {
+                                return Err(Http2ParseError::InvalidStreamId.into());
+                            }
+                            FrameType::Data => {
+                                FrameEffect::ConsumeData(frame.payload.len() as u32)
+                            }
+                            FrameType::WindowUpdate if frame.header.stream_id == 0 => {
+                                FrameEffect::CreditSendWindow(http2::parse_window_update(
+                                    frame.payload,
+                                )?)
+                            }
+                            FrameType::Ping if frame.header.flags & http2::FLAG_ACK == 0 => {
+                                let mut payload = [0u8; 8];
+                                if frame.payload.len() == 8 {
+                                    payload.copy_from_slice(frame.payload);
+                                }
+                                FrameEffect::Pong(payload)
+                            }
+                            FrameType::Goaway => FrameEffect::Goaway,
+                            _ => FrameEffect::Nothing,
+                        };
+                        Ok((effect, consumed, frame.header.stream_id))
+                    }
+                    Err(e) => Err(e),
+                }
+            };
+
+            match parsed {
+                Ok((effect, consumed, stream_id)) => {
+                    if from_accumulator {
+                        if let ConnectionState::Http2(http2) = &mut self.state {
+                            http2.frame_buffer.drain(..consumed);
+                        }
+                    } else {
+                        self.consume(consumed);
+                    }
+                    if stream_id != 0 {
+                        if let ConnectionState::Http2(http2) = &mut self.state {
+                            http2.last_stream_id = http2.last_stream_id.max(stream_id);
+                        }
+                    }
+                    // Resolve block reassembly first: a finished
+                    // CONTINUATION sequence becomes an ordinary OpenStream,
+                    // and a flood tears the connection down.
+                    let effect = match effect {
+                        FrameEffect::BeginHeaders { fragment } => {
+                            let max_block = self.config.max_header_block_size;
+                            let begun = match &mut self.state {
+                                ConnectionState::Http2(http2) => {
+                                    http2.header_block.begin(stream_id, &fragment, max_block)
+                                }
+                                _ => Ok(()),
+                            };
+                            if let Err(e) = begun {
+                                return self.refuse_header_flood(e);
+                            }
+                            FrameEffect::Nothing
+                        }
+                        FrameEffect::ContinueHeaders {
+                            fragment,
+                            end_headers,
+                        } => {
+                            let max_block = self.config.max_header_block_size;
+                            let max_continuations = self.config.max_continuation_frames;
+                            let appended = match &mut self.state {
+                                ConnectionState::Http2(http2)
+                                    if http2.header_block.in_progress()
+                                        && http2.header_block.stream_id() == stream_id =>
+                                {
+                                    http2.header_block.append(
+                                        &fragment,
+                                        end_headers,
+                                        max_block,
+                                        max_continuations,
+                                    )
+                                }
+                                // A stray CONTINUATION advances nothing,
+                                // like the other frames the connection
+                                // does not yet act on.
+                                _ => Ok(None),
+                            };
+                            match appended {
+                                Ok(Some(block)) => FrameEffect::OpenStream { block },
+                                Ok(None) => FrameEffect::Nothing,
+                                Err(e) => return self.refuse_header_flood(e),
+                            }
+                        }
+                        other => other,
+                    };
+                    match effect {
+                        FrameEffect::ApplySettings(pairs) => {
+                            if let ConnectionState::Http2(http2) = &mut self.state {
+                                http2.parser.update_settings(&pairs)?;
+                                let limit = http2.parser.settings.max_concurrent_streams;
+                                http2.streams.set_max_concurrent(limit);
+                            }
+                            let ack = Http2FrameBuilder::new().settings_ack();
+                            self.write_all(&ack)?;
+                        }
+                        FrameEffect::AckSettings => {
+                            if let ConnectionState::Http2(http2) = &mut self.state {
+                                http2.acknowledge_settings();
+                            }
+                        }
+                        FrameEffect::BeginHeaders { .. } | FrameEffect::ContinueHeaders { .. } => {
+                            unreachable!("resolved into OpenStream or Nothing above")
+                        }
+                        FrameEffect::OpenStream { block } => {
+                            // The block must pass through the decoder even
+                            // for a refused stream: HPACK dynamic-table
+                            // state is shared by every later block.
+                            let (refused, headers) = match &mut self.state {
+                                ConnectionState::Http2(http2) => {
+                                    let refused = http2.streams.open(stream_id).is_err();
+                                    // The cap is this server's, not the
+                                    // peer's advertised setting: the peer's
+                                    // value bounds what *it* will accept.
+                                    let limit = Some(self.config.max_header_list_size);
+                                    let headers = http2
+                                        .parser
+                                        .hpack_decoder
+                                        .decode_block(&block, limit)?;
+                                    (refused, headers)
+                                }
+                                _ => (false, Vec::new()),
+                            };
+                            if refused {
+                                let rst = Http2FrameBuilder::new()
+                                    .rst_stream(stream_id, REFUSED_STREAM);
+                                self.write_all(&rst)?;
+                                continue;
+                            }
+                            if http2::validate_request_pseudo_headers(&headers).is_err()
+                                || http2::validate_lowercase_header_names(&headers).is_err()
+                            {
+                                // Malformed (§8.1.2.6): the stream dies,
+                                // the connection does not.
+                                if let ConnectionState::Http2(http2) = &mut self.state {
+                                    http2.streams.close(stream_id);
+                                }
+                                let rst = Http2FrameBuilder::new()
+                                    .rst_stream(stream_id, PROTOCOL_ERROR);
+                                self.write_all(&rst)?;
+                                continue;
+                            }
+                            let pseudo = |name: &[u8]| {
+                                headers
+                                    .iter()
+                                    .find(|(n, _)| n.as_slice() == name)
+                                    .map(|(_, v)| v.clone())
+                            };
+                            if pseudo(b":method").as_deref() == Some(b"CONNECT") {
+                                // A tunnel has no request target of its own;
+                                // :scheme and :path are malformed here
+                                // (RFC 7540 §8.3).
+                                if pseudo(b":scheme").is_some() || pseudo(b":path").is_some() {
+                                    if let ConnectionState::Http2(http2) = &mut self.state {
+                                        http2.streams.close(stream_id);
+                                    }
+                                    let rst = Http2FrameBuilder::new()
+                                        .rst_stream(stream_id, PROTOCOL_ERROR);
+                                    self.write_all(&rst)?;
+                                    continue;
+                                }
+                                let authority = pseudo(b":authority")
+                                    .and_then(|v| String::from_utf8(v).ok())
+                                    .unwrap_or_default();
+                                let protocol = pseudo(b":protocol")
+                                    .and_then(|v| String::from_utf8(v).ok());
+                                return Ok(ConnectionAction::Connect {
+                                    stream_id,
+                                    authority,
+                                    protocol,
+                                });
+                            }
+                        }
+                        FrameEffect::Reprioritize(priority) => {
+                            let rejected = match &mut self.state {
+                                ConnectionState::Http2(http2) => http2
+                                    .priorities
+                                    .reprioritize(
+                                        stream_id,
+                                        priority.exclusive,
+                                        priority.dependency,
+                                        priority.weight,
+                                    )
+                                    .is_err(),
+                                _ => false,
+                            };
+                            if rejected {
+                                let rst = Http2FrameBuilder::new()
+                                    .rst_stream(stream_id, PROTOCOL_ERROR);
+                                self.write_all(&rst)?;
+                            }
+                        }
+                        FrameEffect::ConsumeData(len) => {
+                            let max_request_size = self.config.max_request_size as u64;
+                            let (owed, overgrown) = match &mut self.state {
+                                ConnectionState::Http2(http2) => {
+                                    http2.flow.consume_recv_window(len).map_err(Error::from)?;
+                                    // A stream whose accumulated DATA
+                                    // outgrows the request cap is reset on
+                                    // its own; the connection survives.
+                                    let overgrown =
+                                        http2.streams.get_mut(stream_id).is_some_and(|stream| {
+                                            stream.received_bytes += u64::from(len);
+                                            stream.received_bytes > max_request_size
+                                        });
+                                    if overgrown {
+                                        http2.streams.close(stream_id);
+                                    }
+                                    (http2.flow.window_update_needed(), overgrown)
+                                }
+                                _ => (None, false),
+                            };
+                            if overgrown {
+                                let rst = Http2FrameBuilder::new()
+                                    .rst_stream(stream_id, ENHANCE_YOUR_CALM);
+                                self.write_all(&rst)?;
+                            }
+                            if let Some(increment) = owed {
+                                let update =
+                                    Http2FrameBuilder::new().window_update(0, increment);
+                                self.write_all(&update)?;
+                                if let ConnectionState::Http2(http2) = &mut self.state {
+                                    http2.flow.replenish_recv_window(increment);
+                                }
+                            }
+                        }
+                        FrameEffect::CreditSendWindow(increment) => {
+                            if let ConnectionState::Http2(http2) = &mut self.state {
+                                http2.flow.credit_send_window(increment).map_err(Error::from)?;
+                            }
+                        }
+                        FrameEffect::Pong(payload) => {
+                            let pong = Http2FrameBuilder::new().ping_ack(&payload);
+                            self.write_all(&pong)?;
+                        }
+                        FrameEffect::Goaway => {
+                            self.state = ConnectionState::Closing;
+                            return Ok(ConnectionAction::Close);
+                        }
+                        FrameEffect::Nothing => {}
+                    }
+                }
+                Err(Http2ParseError::IncompleteFrame) => {
+                    // Park the partial frame in the accumulator so the read
+                    // buffer is free for whatever arrives next.
+                    if !from_accumulator && self.read_len > 0 {
+                        if let ConnectionState::Http2(http2) = &mut self.state {
+                            http2
+                                .frame_buffer
+                                .extend_from_slice(&self.read_buffer[..self.read_len]);
+                        }
+                        let buffered = self.read_len;
+                        self.consume(buffered);
+                    }
+                    return Ok(ConnectionAction::NeedMore);
+                }
+                Err(Http2ParseError::StreamFrameSizeError { stream_id }) => {
+                    // Fatal to the stream only (§4.2): reset it and
+                    // discard the oversized DATA frame once it is whole.
+                    let (have, total) = {
+                        let ConnectionState::Http2(http2) = &self.state else {
+                            unreachable!("checked above");
+                        };
+                        let source: &[u8] = if from_accumulator {
+                            &http2.frame_buffer
+                        } else {
+                            &self.read_buffer[..self.read_len]
+                        };
+                        let length =
+                            u32::from_be_bytes([0, source[0], source[1], source[2]]) as usize;
+                        (source.len(), http2::FRAME_HEADER_LEN + length)
+                    };
+                    if have < total {
+                        // Park what has arrived; the remainder of the
+                        // frame is still skipped, just later.
+                        if !from_accumulator && self.read_len > 0 {
+                            if let ConnectionState::Http2(http2) = &mut self.state {
+                                http2
+                                    .frame_buffer
+                                    .extend_from_slice(&self.read_buffer[..self.read_len]);
+                            }
+                            let buffered = self.read_len;
+                            self.consume(buffered);
+                        }
+                        return Ok(ConnectionAction::NeedMore);
+                    }
+                    if from_accumulator {
+                        if let ConnectionState::Http2(http2) = &mut self.state {
+                            http2.frame_buffer.drain(..total);
+                        }
+                    } else {
+                        self.consume(total);
+                    }
+                    if let ConnectionState::Http2(http2) = &mut self.state {
+                        http2.streams.close(stream_id);
+                    }
+                    let rst = Http2FrameBuilder::new().rst_stream(stream_id, FRAME_SIZE_ERROR);
+                    self.write_all(&rst)?;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// Moves newly read bytes into the partial-frame accumulator until it
+    /// holds one complete frame, leaving any bytes beyond that frame in
+    /// the read buffer.
+    fn fill_frame_accumulator(&mut self) {
+        loop {
+            let take = {
+                let ConnectionState::Http2(http2) = &self.state else {
+                    return;
+                };
+                let have = http2.frame_buffer.len();
+                if have == 0 || self.read_len == 0 {
+                    return;
+                }
+                let needed = if have >= http2::FRAME_HEADER_LEN {
+                    let b = &http2.frame_buffer;
+                    http2::FRAME_HEADER_LEN + u32::from_be_bytes([0, b[0], b[1], b[2]]) as usize
+                } else {
+                    http2::FRAME_HEADER_LEN
+                };
+                if have >= needed {
+                    return;
+                }
+                (needed - have).min(self.read_len)
+            };
+            if let ConnectionState::Http2(http2) = &mut self.state {
+                http2.frame_buffer.extend_from_slice(&self.read_buffer[..take]);
+            }
+            self.consume(take);
+        }
+    }
+
+    /// Discards `n` processed bytes from the front of the read buffer.
+    fn consume(&mut self, n: usize) {
+        if n == 0 {
+            return;
+        }
+        self.read_buffer.copy_within(n..self.read_len, 0);
+        self.read_len -= n;
+    }
+}
+
+/// Identifies a connection registered with a [`ConnectionRegistry`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ConnectionId(u64);
+
+/// Tracks active connections by last activity so an event loop can reap
+/// every idle one in a single sweep, instead of asking each connection
+/// [`Connection::is_timed_out`] in turn.
+///
+/// Activity stamps are kept in a time-ordered set, so a sweep walks only
+/// the expired prefix and stops at the first live connection. A `touch`
+/// re-queues the connection and leaves the old entry behind; sweeps
+/// recognize such stale entries by their outdated stamp and skip them.
+#[derive(Debug)]
+pub struct ConnectionRegistry {
+    timeout: Duration,
+    inner: Mutex<RegistryInner>,
+}
+
+#[derive(Debug, Default)]
+struct RegistryInner {
+    next_id: u64,
+    /// The authoritative last-activity stamp per connection.
+    stamps: HashMap<ConnectionId, Instant>,
+    /// Connections ordered by the stamp they held when last touched.
+    queue: BTreeSet<(Instant, ConnectionId)>,
+}
+
+impl ConnectionRegistry {
+    /// Creates a registry reaping connections idle for longer than
+    /// `timeout`.
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            inner: Mutex::new(RegistryInner::default()),
+        }
+    }
+
+    /// Registers a connection as active now, returning its id.
+    pub fn register(&self) -> ConnectionId {
+        self.register_at(Instant::now())
+    }
+
+    /// Records activity on `id` now. Unknown ids are ignored.
+    pub fn touch(&self, id: ConnectionId) {
+        self.touch_at(id, Instant::now());
+    }
+
+    /// Removes `id` from the registry, e.g. when its connection closes on
+    /// its own.
+    pub fn deregister(&self, id: ConnectionId) {
+        let mut inner = self.inner.lock().expect("registry lock poisoned");
+        inner.stamps.remove(&id);
+    }
+
+    /// The number of connections currently tracked.
+    pub fn len(&self) -> usize {
+        self.inner.lock().expect("registry lock poisoned").stamps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns — and forgets — every connection whose last activity is
+    /// older than the timeout; the caller closes them.
+    pub fn reap_timed_out(&self) -> Vec<ConnectionId> {
+        self.reap_at(Instant::now())
+    }
+
+    fn register_at(&self, now: Instant) -> ConnectionId {
+        let mut inner = self.inner.lock().expect("registry lock poisoned");
+        inner.next_id += 1;
+        let id = ConnectionId(inner.next_id);
+        inner.stamps.insert(id, now);
+        inner.queue.insert((now, id));
+        id
+    }
+
+    fn touch_at(&self, id: ConnectionId, now: Instant) {
+        let mut inner = self.inner.lock().expect("registry lock poisoned");
+        if let Some(stamp) = inner.stamps.get_mut(&id) {
+            *stamp = now;
+            inner.queue.insert((now, id));
+        }
+    }
+
+    fn reap_at(&self, now: Instant) -> Vec<ConnectionId> {
+        let mut inner = self.inner.lock().expect("registry lock poisoned");
+        let mut reaped = Vec::new();
+        while let Some(&(stamp, id)) = inner.queue.first() {
+            if now.duration_since(stamp) <= self.timeout {
+                // Everything behind this entry is newer still.
+                break;
+            }
+            inner.queue.remove(&(stamp, id));
+            match inner.stamps.get(&id) {
+                // Only the entry carrying the authoritative stamp counts;
+                // older ones were superseded by a touch.
+                Some(&current) if current == stamp => {
+                    inner.stamps.remove(&id);
+                    reaped.push(id);
+                }
+                _ => {}
+            }
+        }
+        reaped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::collections::VecDeque;
+    use std::io;
+
+    /// An in-memory stream: reads drain `input`, writes append to `written`.
+    pub(crate) struct MockStream {
+        pub input: VecDeque<u8>,
+        pub written: Vec<u8>,
+        /// Number of `write` calls observed, for asserting on batching.
+        pub write_calls: usize,
+        /// When set, reads fail like a socket whose `SO_RCVTIMEO` expired.
+        pub stall_reads: bool,
+        /// When set, writes fail like a socket whose `SO_SNDTIMEO` expired.
+        pub stall_writes: bool,
+        pub read_timeout: Cell<Option<Duration>>,
+        pub write_timeout: Cell<Option<Duration>>,
+    }
+
+    impl MockStream {
+        pub fn new(initial: &[u8]) -> Self {
+            Self {
+                input: initial.iter().copied().collect(),
+                written: Vec::new(),
+                write_calls: 0,
+                stall_reads: false,
+                stall_writes: false,
+                read_timeout: Cell::new(None),
+                write_timeout: Cell::new(None),
+            }
+        }
+    }
+
+    impl Timeouts for MockStream {
+        fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+            self.read_timeout.set(timeout);
+            Ok(())
+        }
+
+        fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+            self.write_timeout.set(timeout);
+            Ok(())
+        }
+    }
+
+    impl Read for MockStream {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.stall_reads {
+                return Err(io::ErrorKind::WouldBlock.into());
+            }
+            let n = buf.len().min(self.input.len());
+            for slot in buf.iter_mut().take(n) {
+                *slot = self.input.pop_front().unwrap();
+            }
+            Ok(n)
+        }
+    }
+
+    impl Write for MockStream {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.stall_writes {
+                return Err(io::ErrorKind::WouldBlock.into());
+            }
+            self.write_calls += 1;
+            self.written.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn test_addr() -> SocketAddr {
+        "127.0.0.1:4000".parse().unwrap()
+    }
+
+    fn connection(initial: &[u8]) -> Connection<MockStream> {
+        Connection::new(
+            MockStream::new(initial),
+            test_addr(),
+            ConnectionConfig::default(),
+        )
+    }
+
+    #[test]
+    fn processes_simple_get() {
+        let mut conn = connection(b"GET /hello HTTP/1.1\r\nHost: x\r\n\r\n");
+        conn.read_available().unwrap();
+        match conn.process().unwrap() {
+            ConnectionAction::Requests(reqs) => {
+                assert_eq!(reqs.len(), 1);
+                assert_eq!(reqs[0].method, Method::Get);
+                assert_eq!(reqs[0].path(), "/hello");
+            }
+            other => panic!("expected a request, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn interim_continue_is_written_before_body_is_consumed() {
+        let mut conn =
+            connection(b"POST /upload HTTP/1.1\r\nContent-Length: 5\r\nExpect: 100-continue\r\n\r\n");
+        conn.read_available().unwrap();
+        // Headers only: the connection must emit the interim response and
+        // ask for more data.
+        assert!(matches!(conn.process().unwrap(), ConnectionAction::NeedMore));
+        assert_eq!(conn.stream.written, b"HTTP/1.1 100 Continue\r\n\r\n");
+
+        // Now the client, having seen 100, sends the body.
+        conn.stream.input.extend(b"hello");
+        conn.read_available().unwrap();
+        match conn.process().unwrap() {
+            ConnectionAction::Requests(reqs) => assert_eq!(reqs[0].body, b"hello"),
+            other => panic!("expected a request, got {other:?}"),
+        }
+        // The interim response must not be repeated.
+        assert_eq!(conn.stream.written, b"HTTP/1.1 100 Continue\r\n\r\n");
+    }
+
+    #[test]
+    fn h2c_upgrade_switches_to_http2() {
+        // HTTP2-Settings: max_frame_size = 65536, base64url-encoded.
+        let mut conn = connection(
+            b"GET / HTTP/1.1\r\nHost: x\r\nConnection: Upgrade, HTTP2-Settings\r\n\
+              Upgrade: h2c\r\nHTTP2-Settings: AAUAAQAA\r\n\r\n",
+        );
+        conn.read_available().unwrap();
+        match conn.process().unwrap() {
+            ConnectionAction::Requests(reqs) => assert_eq!(reqs.len(), 1),
+            other => panic!("expected the upgraded request, got {other:?}"),
+        }
+        let written = String::from_utf8_lossy(&conn.stream.written).into_owned();
+        assert!(written.starts_with("HTTP/1.1 101 Switching Protocols\r\n"));
+        match conn.state() {
+            ConnectionState::Http2(http2) => {
+                assert!(!http2.preface_received);
+                assert_eq!(http2.parser.settings.max_frame_size, 65_536);
+            }
+            other => panic!("expected Http2 state, got {other:?}"),
+        }
+
+        // The client follows up with the preface and its SETTINGS frame,
+        // which the connection acknowledges.
+        conn.stream.input.extend(HTTP2_PREFACE);
+        conn.stream
+            .input
+            .extend(Http2FrameBuilder::new().settings_frame(&[]));
+        conn.read_available().unwrap();
+        assert!(matches!(conn.process().unwrap(), ConnectionAction::NeedMore));
+        match conn.state() {
+            ConnectionState::Http2(http2) => assert!(http2.preface_received),
+            other => panic!("expected Http2 state, got {other:?}"),
+        }
+        let ack = Http2FrameBuilder::new().settings_ack();
+        assert!(conn.stream.written.ends_with(&ack));
+    }
+
+    #[test]
+    fn prior_knowledge_preface_enters_http2() {
+        let mut input = HTTP2_PREFACE.to_vec();
+        input.extend(Http2FrameBuilder::new().settings_frame(&[]));
+        let mut conn = connection(&input);
+        conn.read_available().unwrap();
+        assert!(matches!(conn.process().unwrap(), ConnectionAction::NeedMore));
+        assert!(matches!(conn.state(), ConnectionState::Http2(_)));
+    }
+
+    #[test]
+    fn malformed_http2_settings_header_is_served_as_http1() {
+        let mut conn = connection(
+            b"GET / HTTP/1.1\r\nHost: x\r\nConnection: Upgrade\r\n\
+              Upgrade: h2c\r\nHTTP2-Settings: !!!\r\n\r\n",
+        );
+        conn.read_available().unwrap();
+        assert!(matches!(
+            conn.process().unwrap(),
+            ConnectionAction::Requests(_)
+        ));
+        assert!(matches!(conn.state(), ConnectionState::Http1(_)));
+    }
+
+    #[test]
+    fn drains_pipelined_requests_in_order() {
+        let mut conn = connection(
+            b"GET /one HTTP/1.1\r\nHost: x\r\n\r\n\
+              GET /two HTTP/1.1\r\nHost: x\r\n\r\n\
+              GET /three HTTP/1.1\r\nHost: x\r\n\r\n",
+        );
+        conn.read_available().unwrap();
+        match conn.process().unwrap() {
+            ConnectionAction::Requests(reqs) => {
+                let paths: Vec<_> = reqs.iter().map(|r| r.path().to_owned()).collect();
+                assert_eq!(paths, ["/one", "/two", "/three"]);
+            }
+            other => panic!("expected requests, got {other:?}"),
+        }
+        match conn.state() {
+            ConnectionState::Http1(http1) => assert_eq!(http1.pipeline_depth, 3),
+            other => panic!("expected Http1 state, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn trailing_partial_request_stays_buffered() {
+        let mut conn = connection(
+            b"GET /one HTTP/1.1\r\nHost: x\r\n\r\nGET /two HTT",
+        );
+        conn.read_available().unwrap();
+        match conn.process().unwrap() {
+            ConnectionAction::Requests(reqs) => assert_eq!(reqs.len(), 1),
+            other => panic!("expected requests, got {other:?}"),
+        }
+        // Completing the second request yields it on the next pass.
+        conn.stream.input.extend(b"P/1.1\r\nHost: x\r\n\r\n");
+        conn.read_available().unwrap();
+        match conn.process().unwrap() {
+            ConnectionAction::Requests(reqs) => {
+                assert_eq!(reqs.len(), 1);
+                assert_eq!(reqs[0].path(), "/two");
+            }
+            other => panic!("expected requests, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn pipeline_depth_is_capped() {
+        let config = ConnectionConfig {
+            max_pipeline_depth: 2,
+            ..ConnectionConfig::default()
+        };
+        let mut conn = Connection::new(
+            MockStream::new(
+                b"GET /one HTTP/1.1\r\nHost: x\r\n\r\n\
+                  GET /two HTTP/1.1\r\nHost: x\r\n\r\n\
+                  GET /three HTTP/1.1\r\nHost: x\r\n\r\n",
+            ),
+            test_addr(),
+            config,
+        );
+        conn.read_available().unwrap();
+        match conn.process().unwrap() {
+            ConnectionAction::Requests(reqs) => assert_eq!(reqs.len(), 2),
+            other => panic!("expected requests, got {other:?}"),
+        }
+        match conn.process().unwrap() {
+            ConnectionAction::Requests(reqs) => assert_eq!(reqs.len(), 1),
+            other => panic!("expected requests, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn http11_defaults_to_keep_alive() {
+        let mut conn = connection(b"GET / HTTP/1.1\r\nHost: x\r\n\r\n");
+        conn.read_available().unwrap();
+        assert!(matches!(conn.process().unwrap(), ConnectionAction::Requests(_)));
+        match conn.state() {
+            ConnectionState::Http1(http1) => assert!(http1.keep_alive),
+            other => panic!("expected Http1 state, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn http11_connection_close_transitions_to_closing() {
+        let mut conn = connection(b"GET / HTTP/1.1\r\nHost: x\r\nConnection: close\r\n\r\n");
+        conn.read_available().unwrap();
+        assert!(matches!(conn.process().unwrap(), ConnectionAction::Requests(_)));
+        assert!(matches!(conn.state(), ConnectionState::Closing));
+        // No further requests are accepted.
+        assert!(matches!(conn.process().unwrap(), ConnectionAction::Close));
+    }
+
+    #[test]
+    fn http10_defaults_to_close() {
+        let mut conn = connection(b"GET / HTTP/1.0\r\nHost: x\r\n\r\n");
+        conn.read_available().unwrap();
+        assert!(matches!(conn.process().unwrap(), ConnectionAction::Requests(_)));
+        assert!(matches!(conn.state(), ConnectionState::Closing));
+    }
+
+    #[test]
+    fn http10_explicit_keep_alive_persists() {
+        let mut conn = connection(b"GET / HTTP/1.0\r\nConnection: keep-alive\r\n\r\n");
+        conn.read_available().unwrap();
+        assert!(matches!(conn.process().unwrap(), ConnectionAction::Requests(_)));
+        match conn.state() {
+            ConnectionState::Http1(http1) => assert!(http1.keep_alive),
+            other => panic!("expected Http1 state, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn reuse_limit_retires_the_connection() {
+        let config = ConnectionConfig {
+            max_requests_per_connection: 2,
+            ..ConnectionConfig::default()
+        };
+        let mut conn = Connection::new(
+            MockStream::new(
+                b"GET /one HTTP/1.1\r\nHost: x\r\n\r\n\
+                  GET /two HTTP/1.1\r\nHost: x\r\n\r\n\
+                  GET /three HTTP/1.1\r\nHost: x\r\n\r\n",
+            ),
+            test_addr(),
+            config,
+        );
+        conn.read_available().unwrap();
+        match conn.process().unwrap() {
+            ConnectionAction::Requests(requests) => assert_eq!(requests.len(), 2),
+            other => panic!("expected requests, got {other:?}"),
+        }
+        assert!(conn.is_closing());
+        // The third request is never parsed.
+        assert!(matches!(conn.process().unwrap(), ConnectionAction::Close));
+    }
+
+    #[test]
+    fn malformed_expectation_yields_417() {
+        let mut conn =
+            connection(b"POST /upload HTTP/1.1\r\nContent-Length: 5\r\nExpect: 42-extensions\r\n\r\n");
+        conn.read_available().unwrap();
+        assert!(matches!(conn.process().unwrap(), ConnectionAction::Close));
+        let written = String::from_utf8(conn.stream.written.clone()).unwrap();
+        assert!(written.starts_with("HTTP/1.1 417 Expectation Failed\r\n"));
+    }
+
+    #[test]
+    fn graceful_shutdown_stops_http1_request_intake() {
+        let mut conn = connection(b"GET /one HTTP/1.1\r\nHost: x\r\n\r\n");
+        conn.read_available().unwrap();
+        assert!(matches!(conn.process().unwrap(), ConnectionAction::Requests(_)));
+
+        conn.begin_graceful_shutdown().unwrap();
+        match conn.state() {
+            ConnectionState::Closing => {}
+            other => panic!("expected Closing, got {other:?}"),
+        }
+
+        // A request arriving after shutdown began is not parsed.
+        conn.stream.input.extend(b"GET /two HTTP/1.1\r\nHost: x\r\n\r\n");
+        conn.read_available().unwrap();
+        assert!(matches!(conn.process().unwrap(), ConnectionAction::Close));
+    }
+
+    #[test]
+    fn graceful_shutdown_sends_goaway_on_http2() {
+        let mut input = HTTP2_PREFACE.to_vec();
+        input.extend(Http2FrameBuilder::new().settings_frame(&[]));
+        let mut conn = connection(&input);
+        conn.read_available().unwrap();
+        assert!(matches!(conn.process().unwrap(), ConnectionAction::NeedMore));
+
+        conn.begin_graceful_shutdown().unwrap();
+        let goaway = Http2FrameBuilder::new().goaway(0, 0);
+        assert!(conn.stream.written.ends_with(&goaway));
+        assert!(matches!(conn.process().unwrap(), ConnectionAction::Close));
+    }
+
+    #[test]
+    fn excess_http2_streams_are_refused() {
+        let builder = Http2FrameBuilder::new();
+        let mut input = HTTP2_PREFACE.to_vec();
+        input.extend(builder.settings_frame(&[(http2::SETTINGS_MAX_CONCURRENT_STREAMS, 2)]));
+        // :method GET, :scheme http, :path / — all static-table indexed.
+        for stream_id in [1u32, 3, 5] {
+            input.extend(builder.frame(
+                FrameType::Headers,
+                http2::FLAG_END_HEADERS,
+                stream_id,
+                &[0x82, 0x86, 0x84],
+            ));
+        }
+        let mut conn = connection(&input);
+        conn.read_available().unwrap();
+        conn.process().unwrap();
+
+        // The first two streams are admitted; the third gets RST_STREAM
+        // with REFUSED_STREAM.
+        let rst = builder.rst_stream(5, crate::streams::REFUSED_STREAM);
+        assert!(conn.stream.written.ends_with(&rst));
+        match conn.state() {
+            ConnectionState::Http2(http2) => assert_eq!(http2.streams.active_count(), 2),
+            other => panic!("expected an HTTP/2 connection, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn consumed_data_triggers_a_window_update() {
+        let builder = Http2FrameBuilder::new();
+        let mut input = HTTP2_PREFACE.to_vec();
+        input.extend(builder.settings_frame(&[]));
+        let mut conn = connection(&input);
+        conn.read_available().unwrap();
+        conn.process().unwrap();
+
+        // Shrink the window so the batching threshold is reachable in a
+        // couple of small frames.
+        let ConnectionState::Http2(http2) = &mut conn.state else {
+            panic!("expected an HTTP/2 connection");
+        };
+        http2.flow = http2::FlowController::new(64);
+
+        conn.stream.input.extend(builder.frame(FrameType::Data, 0, 1, &[0u8; 24]));
+        conn.read_available().unwrap();
+        conn.process().unwrap();
+        let update = builder.window_update(0, 48);
+        assert!(!conn.stream.written.ends_with(&update), "below the threshold");
+
+        conn.stream.input.extend(builder.frame(FrameType::Data, 0, 1, &[0u8; 24]));
+        conn.read_available().unwrap();
+        conn.process().unwrap();
+        assert!(conn.stream.written.ends_with(&update));
+        let ConnectionState::Http2(http2) = &conn.state else {
+            panic!("expected an HTTP/2 connection");
+        };
+        assert_eq!(http2.flow.recv_window(), 64, "window fully replenished");
+    }
+
+    #[test]
+    fn data_past_the_recv_window_is_a_flow_control_error() {
+        let builder = Http2FrameBuilder::new();
+        let mut input = HTTP2_PREFACE.to_vec();
+        input.extend(builder.settings_frame(&[]));
+        let mut conn = connection(&input);
+        conn.read_available().unwrap();
+        conn.process().unwrap();
+
+        let ConnectionState::Http2(http2) = &mut conn.state else {
+            panic!("expected an HTTP/2 connection");
+        };
+        http2.flow = http2::FlowController::new(16);
+
+        conn.stream.input.extend(builder.frame(FrameType::Data, 0, 1, &[0u8; 40]));
+        conn.read_available().unwrap();
+        match conn.process() {
+            Err(Error::Http2(Http2ParseError::FlowControlError)) => {}
+            other => panic!("expected a flow-control error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn data_on_stream_zero_is_rejected_without_debiting_the_window() {
+        let builder = Http2FrameBuilder::new();
+        let mut input = HTTP2_PREFACE.to_vec();
+        input.extend(builder.settings_frame(&[]));
+        input.extend(builder.frame(FrameType::Data, 0, 0, &[0u8; 16]));
+        let mut conn = connection(&input);
+        conn.read_available().unwrap();
+        match conn.process() {
+            Err(Error::Http2(Http2ParseError::InvalidStreamId)) => {}
+            other => panic!("expected InvalidStreamId, got {other:?}"),
+        }
+        let ConnectionState::Http2(http2) = &conn.state else {
+            panic!("expected an HTTP/2 connection");
+        };
+        assert_eq!(
+            http2.flow.recv_window(),
+            i64::from(http2::DEFAULT_WINDOW_SIZE),
+            "the rejected frame must not consume the connection window"
+        );
+    }
+
+    #[test]
+    fn header_list_past_the_configured_cap_is_a_compression_error() {
+        let config = ConnectionConfig {
+            max_header_list_size: 128,
+            ..ConnectionConfig::default()
+        };
+        let builder = Http2FrameBuilder::new();
+        let mut input = HTTP2_PREFACE.to_vec();
+        input.extend(builder.settings_frame(&[]));
+        // One literal header whose value alone exceeds the 128-octet cap;
+        // the peer advertised no limit of its own, so only the server-side
+        // configuration stands between this block and full expansion.
+        let block = crate::hpack::HpackEncoder::new()
+            .encode_block([(b"x-filler".as_slice(), [b'a'; 256].as_slice())]);
+        input.extend(builder.frame(FrameType::Headers, http2::FLAG_END_HEADERS, 1, &block));
+        let mut conn = Connection::new(MockStream::new(&input), test_addr(), config);
+        conn.read_available().unwrap();
+        match conn.process() {
+            Err(Error::Http2(Http2ParseError::CompressionError)) => {}
+            other => panic!("expected CompressionError, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn settings_on_a_nonzero_stream_is_rejected() {
+        let builder = Http2FrameBuilder::new();
+        let mut input = HTTP2_PREFACE.to_vec();
+        input.extend(builder.frame(FrameType::Settings, 0, 1, &[]));
+        let mut conn = connection(&input);
+        conn.read_available().unwrap();
+        match conn.process() {
+            Err(Error::Http2(Http2ParseError::InvalidSettings)) => {}
+            other => panic!("expected InvalidSettings, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn oversized_declared_body_is_rejected_before_it_arrives() {
+        let config = ConnectionConfig {
+            max_request_size: 128,
+            ..ConnectionConfig::default()
+        };
+        // Headers only: the declared length alone is grounds for refusal.
+        let mut conn = Connection::new(
+            MockStream::new(b"POST /upload HTTP/1.1\r\nHost: x\r\nContent-Length: 200\r\n\r\n"),
+            test_addr(),
+            config,
+        );
+        conn.read_available().unwrap();
+        match conn.process() {
+            Err(Error::Http1(Http1ParseError::RequestTooLarge)) => {}
+            other => panic!("expected RequestTooLarge, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn oversized_chunked_body_is_rejected() {
+        let config = ConnectionConfig {
+            max_request_size: 128,
+            ..ConnectionConfig::default()
+        };
+        let mut input = b"POST /upload HTTP/1.1\r\nHost: x\r\nTransfer-Encoding: chunked\r\n\r\n"
+            .to_vec();
+        input.extend_from_slice(b"64\r\n");
+        input.extend_from_slice(&[b'x'; 100]);
+        input.extend_from_slice(b"\r\n0\r\n\r\n");
+        let mut conn = Connection::new(MockStream::new(&input), test_addr(), config);
+        conn.read_available().unwrap();
+        match conn.process() {
+            Err(Error::Http1(Http1ParseError::RequestTooLarge)) => {}
+            other => panic!("expected RequestTooLarge, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn oversized_http2_data_resets_the_stream() {
+        let builder = Http2FrameBuilder::new();
+        let mut input = HTTP2_PREFACE.to_vec();
+        input.extend(builder.settings_frame(&[]));
+        input.extend(builder.frame(
+            FrameType::Headers,
+            http2::FLAG_END_HEADERS,
+            1,
+            &[0x82, 0x86, 0x84],
+        ));
+        input.extend(builder.frame(FrameType::Data, 0, 1, &[0u8; 100]));
+        let config = ConnectionConfig {
+            max_request_size: 64,
+            ..ConnectionConfig::default()
+        };
+        let mut conn = Connection::new(MockStream::new(&input), test_addr(), config);
+        conn.read_available().unwrap();
+        conn.process().unwrap();
+
+        let rst = builder.rst_stream(1, crate::streams::ENHANCE_YOUR_CALM);
+        assert!(conn.stream.written.ends_with(&rst));
+        // A stream-level overrun does not take the connection down.
+        match conn.state() {
+            ConnectionState::Http2(http2) => assert_eq!(http2.streams.active_count(), 0),
+            other => panic!("expected an HTTP/2 connection, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn oversized_data_frame_is_a_stream_error() {
+        let builder = Http2FrameBuilder::new();
+        let mut input = HTTP2_PREFACE.to_vec();
+        input.extend(builder.settings_frame(&[]));
+        input.extend(builder.frame(
+            FrameType::Headers,
+            http2::FLAG_END_HEADERS,
+            1,
+            &[0x82, 0x86, 0x84],
+        ));
+        // 20 000 bytes of DATA against the default 16 384 frame cap.
+        input.extend(builder.frame(FrameType::Data, 0, 1, &[0u8; 20_000]));
+        // A PING afterwards proves the connection outlived the reset.
+        input.extend(builder.frame(FrameType::Ping, 0, 0, &[7u8; 8]));
+
+        let mut conn = connection(&input);
+        loop {
+            match conn.process().unwrap() {
+                ConnectionAction::NeedMore => {
+                    if conn.read_available().unwrap() == 0 {
+                        break;
+                    }
+                }
+                other => panic!("expected NeedMore, got {other:?}"),
+            }
+        }
+
+        let rst = builder.rst_stream(1, crate::streams::FRAME_SIZE_ERROR);
+        let written = &conn.stream.written;
+        assert!(
+            written.windows(rst.len()).any(|w| w == rst),
+            "RST_STREAM(FRAME_SIZE_ERROR) not written"
+        );
+        assert!(written.ends_with(&builder.ping_ack(&[7u8; 8])));
+    }
+
+    #[test]
+    fn oversized_settings_frame_is_a_connection_error() {
+        let mut input = HTTP2_PREFACE.to_vec();
+        // A SETTINGS frame declaring 20 000 bytes of payload.
+        input.extend([0x00, 0x4e, 0x20, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        let mut conn = connection(&input);
+        conn.read_available().unwrap();
+        match conn.process() {
+            Err(Error::Http2(Http2ParseError::InvalidFrameSize)) => {}
+            other => panic!("expected InvalidFrameSize, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn settings_ack_clears_the_outstanding_settings() {
+        let mut input = HTTP2_PREFACE.to_vec();
+        input.extend(Http2FrameBuilder::new().settings_ack());
+        let mut conn = connection(&input);
+        // Prior-knowledge detection runs on the first read; only then is
+        // there HTTP/2 state to mark.
+        conn.read_available().unwrap();
+        if let ConnectionState::Http2(http2) = &mut conn.state {
+            http2.note_settings_sent();
+        }
+        assert!(matches!(conn.process().unwrap(), ConnectionAction::NeedMore));
+        match conn.state() {
+            ConnectionState::Http2(http2) => {
+                http2.check_settings_ack(Duration::ZERO).unwrap();
+            }
+            other => panic!("expected Http2 state, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn settings_ack_with_a_payload_is_a_frame_size_error() {
+        let mut input = HTTP2_PREFACE.to_vec();
+        input.extend(Http2FrameBuilder::new().frame(
+            FrameType::Settings,
+            http2::FLAG_ACK,
+            0,
+            &[0x00],
+        ));
+        let mut conn = connection(&input);
+        conn.read_available().unwrap();
+        match conn.process() {
+            Err(Error::Http2(Http2ParseError::InvalidFrameSize)) => {}
+            other => panic!("expected InvalidFrameSize, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn unacknowledged_settings_time_out() {
+        let mut http2 = Http2State::default();
+        let sent = Instant::now();
+        http2.note_settings_sent_at(sent);
+
+        let timeout = Duration::from_secs(5);
+        http2
+            .check_settings_ack_at(timeout, sent + Duration::from_secs(4))
+            .unwrap();
+        assert_eq!(
+            http2
+                .check_settings_ack_at(timeout, sent + Duration::from_secs(6))
+                .unwrap_err(),
+            Http2ParseError::SettingsTimeout
+        );
+
+        // With nothing outstanding, the check never fires.
+        http2.acknowledge_settings();
+        http2
+            .check_settings_ack_at(timeout, sent + Duration::from_secs(60))
+            .unwrap();
+    }
+
+    #[test]
+    fn continuation_completes_a_split_header_block() {
+        // A minimal GET block split across HEADERS and two CONTINUATIONs:
+        // :method GET, :scheme http, :path /.
+        let mut input = HTTP2_PREFACE.to_vec();
+        let builder = Http2FrameBuilder::new();
+        input.extend(builder.frame(FrameType::Headers, 0, 1, &[0x82]));
+        input.extend(builder.frame(FrameType::Continuation, 0, 1, &[0x86]));
+        input.extend(builder.frame(
+            FrameType::Continuation,
+            http2::FLAG_END_HEADERS,
+            1,
+            &[0x84],
+        ));
+        let mut conn = connection(&input);
+        conn.read_available().unwrap();
+        assert!(matches!(conn.process().unwrap(), ConnectionAction::NeedMore));
+        match conn.state() {
+            ConnectionState::Http2(http2) => {
+                assert!(http2.streams.get(1).is_some(), "the stream opened");
+                assert!(!http2.header_block.in_progress());
+            }
+            other => panic!("expected Http2 state, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn continuation_flood_is_terminated_with_enhance_your_calm() {
+        let mut input = HTTP2_PREFACE.to_vec();
+        let builder = Http2FrameBuilder::new();
+        input.extend(builder.frame(FrameType::Headers, 0, 1, &[0x82]));
+        // Far more CONTINUATION frames than the cap allows; none of them
+        // ever carries END_HEADERS.
+        for _ in 0..100 {
+            input.extend(builder.frame(FrameType::Continuation, 0, 1, &[0x86]));
+        }
+        let mut conn = connection(&input);
+        conn.read_available().unwrap();
+        match conn.process() {
+            Err(Error::Http2(Http2ParseError::HeaderBlockFlood)) => {}
+            other => panic!("expected HeaderBlockFlood, got {other:?}"),
+        }
+        let goaway = builder.goaway(1, ENHANCE_YOUR_CALM);
+        assert!(conn.stream.written.ends_with(&goaway));
+    }
+
+    #[test]
+    fn request_larger_than_the_initial_buffer_completes() {
+        // A 20 KiB body cannot fit the initial 8 KiB read buffer; the
+        // buffer must grow until the request is whole.
+        let mut input = b"POST /upload HTTP/1.1\r\nHost: x\r\nContent-Length: 20480\r\n\r\n"
+            .to_vec();
+        input.extend(std::iter::repeat_n(b'x', 20_480));
+        let mut conn = connection(&input);
+        loop {
+            match conn.process().unwrap() {
+                ConnectionAction::NeedMore => {
+                    assert!(conn.read_available().unwrap() > 0, "input must not run dry");
+                }
+                ConnectionAction::Requests(reqs) => {
+                    assert_eq!(reqs[0].body.len(), 20_480);
+                    break;
+                }
+                other => panic!("expected a request, got {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn buffer_growth_stops_at_the_request_size_cap() {
+        let config = ConnectionConfig {
+            read_buffer_size: 64,
+            max_request_size: 256,
+            ..ConnectionConfig::default()
+        };
+        // An endless header line: never parseable, never complete.
+        let mut input = b"GET / HTTP/1.1\r\nX-Filler: ".to_vec();
+        input.extend(std::iter::repeat_n(b'a', 1024));
+        let mut conn = Connection::new(MockStream::new(&input), test_addr(), config);
+        let result = loop {
+            match conn.process() {
+                Ok(ConnectionAction::NeedMore) => match conn.read_available() {
+                    Ok(n) if n > 0 => {}
+                    other => break other.map(|_| ()),
+                },
+                Ok(other) => panic!("expected NeedMore, got {other:?}"),
+                Err(e) => break Err(e),
+            }
+        };
+        match result {
+            Err(Error::Http1(Http1ParseError::RequestTooLarge)) => {}
+            other => panic!("expected RequestTooLarge, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn response_fragments_flush_as_a_single_write() {
+        let mut conn = connection(b"");
+        conn.write_all(b"HTTP/1.1 200 OK\r\n").unwrap();
+        conn.write_all(b"Content-Type: text/plain\r\n").unwrap();
+        conn.write_all(b"Content-Length: 5\r\n\r\n").unwrap();
+        conn.write_all(b"hello").unwrap();
+        assert_eq!(conn.stream.write_calls, 0, "fragments must be buffered");
+
+        conn.flush().unwrap();
+        assert_eq!(conn.stream.write_calls, 1);
+        assert!(conn.stream.written.starts_with(b"HTTP/1.1 200 OK\r\n"));
+        assert!(conn.stream.written.ends_with(b"hello"));
+        assert_eq!(conn.metrics().bytes_written, conn.stream.written.len() as u64);
+
+        // Flushing an empty buffer touches the stream not at all.
+        conn.flush().unwrap();
+        assert_eq!(conn.stream.write_calls, 1);
+    }
+
+    #[test]
+    fn write_buffer_flushes_once_past_the_threshold() {
+        let config = ConnectionConfig {
+            write_buffer_size: 16,
+            ..ConnectionConfig::default()
+        };
+        let mut conn = Connection::new(MockStream::new(b""), test_addr(), config);
+        conn.write_all(b"0123456789abcdef and then some").unwrap();
+        assert_eq!(conn.stream.write_calls, 1);
+        assert_eq!(conn.stream.written, b"0123456789abcdef and then some");
+    }
+
+    #[test]
+    fn dripped_bytes_trip_the_throughput_guard() {
+        let config = ConnectionConfig {
+            min_bytes_per_sec: 1_000_000,
+            throughput_grace: Duration::from_millis(20),
+            ..ConnectionConfig::default()
+        };
+        let mut conn = Connection::new(MockStream::new(b""), test_addr(), config);
+        // One byte per read keeps `last_activity` fresh, but the rate over
+        // the window is hopeless.
+        let result = loop {
+            conn.stream.input.push_back(b'G');
+            match conn.read_available() {
+                Ok(_) => std::thread::sleep(Duration::from_millis(5)),
+                Err(e) => break e,
+            }
+        };
+        assert!(matches!(result, Error::Timeout));
+    }
+
+    #[test]
+    fn adequate_throughput_passes_the_guard() {
+        let config = ConnectionConfig {
+            min_bytes_per_sec: 100,
+            throughput_grace: Duration::from_millis(10),
+            ..ConnectionConfig::default()
+        };
+        let mut conn = Connection::new(MockStream::new(b""), test_addr(), config);
+        // Well above 100 B/s: a handful of bytes every few milliseconds.
+        for _ in 0..8 {
+            conn.stream.input.extend(b"GET / HTT");
+            conn.read_available().unwrap();
+            std::thread::sleep(Duration::from_millis(3));
+        }
+    }
+
+    #[test]
+    fn stalled_read_surfaces_as_timeout() {
+        let mut conn = connection(b"");
+        conn.stream.stall_reads = true;
+        match conn.read_available() {
+            Err(Error::Timeout) => {}
+            other => panic!("expected Error::Timeout, got {other:?}"),
+        }
+        // The configured timeout reached the socket before the read.
+        assert_eq!(
+            conn.stream.read_timeout.get(),
+            Some(ConnectionConfig::default().read_timeout)
+        );
+    }
+
+    #[test]
+    fn stalled_write_surfaces_as_timeout() {
+        let mut conn = connection(b"");
+        conn.stream.stall_writes = true;
+        conn.write_all(b"HTTP/1.1 200 OK\r\n\r\n").unwrap();
+        match conn.flush() {
+            Err(Error::Timeout) => {}
+            other => panic!("expected Error::Timeout, got {other:?}"),
+        }
+        assert_eq!(
+            conn.stream.write_timeout.get(),
+            Some(ConnectionConfig::default().write_timeout)
+        );
+    }
+
+    /// Encodes a literal-without-indexing HPACK field; a `name_index` of 0
+    /// takes the name from `name` instead of the static table.
+    fn hpack_literal(name_index: u8, name: &[u8], value: &[u8]) -> Vec<u8> {
+        let mut out = vec![name_index];
+        if name_index == 0 {
+            out.push(name.len() as u8);
+            out.extend_from_slice(name);
+        }
+        out.push(value.len() as u8);
+        out.extend_from_slice(value);
+        out
+    }
+
+    #[test]
+    fn plain_connect_surfaces_a_tunnel_request() {
+        let builder = Http2FrameBuilder::new();
+        let mut block = hpack_literal(0x02, b"", b"CONNECT"); // :method
+        block.extend(hpack_literal(0x01, b"", b"proxy.example:443")); // :authority
+        let mut input = HTTP2_PREFACE.to_vec();
+        input.extend(builder.settings_frame(&[]));
+        input.extend(builder.frame(FrameType::Headers, http2::FLAG_END_HEADERS, 1, &block));
+        let mut conn = connection(&input);
+        conn.read_available().unwrap();
+        match conn.process().unwrap() {
+            ConnectionAction::Connect {
+                stream_id,
+                authority,
+                protocol,
+            } => {
+                assert_eq!(stream_id, 1);
+                assert_eq!(authority, "proxy.example:443");
+                assert_eq!(protocol, None);
+            }
+            other => panic!("expected a CONNECT, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn extended_connect_carries_its_protocol() {
+        let builder = Http2FrameBuilder::new();
+        let mut block = hpack_literal(0x02, b"", b"CONNECT");
+        block.extend(hpack_literal(0x01, b"", b"chat.example"));
+        block.extend(hpack_literal(0x00, b":protocol", b"websocket"));
+        let mut input = HTTP2_PREFACE.to_vec();
+        input.extend(builder.settings_frame(&[]));
+        input.extend(builder.frame(FrameType::Headers, http2::FLAG_END_HEADERS, 1, &block));
+        let mut conn = connection(&input);
+        conn.read_available().unwrap();
+        match conn.process().unwrap() {
+            ConnectionAction::Connect {
+                authority, protocol, ..
+            } => {
+                assert_eq!(authority, "chat.example");
+                assert_eq!(protocol.as_deref(), Some("websocket"));
+            }
+            other => panic!("expected a CONNECT, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn connect_with_a_path_is_reset() {
+        let builder = Http2FrameBuilder::new();
+        let mut block = hpack_literal(0x02, b"", b"CONNECT");
+        block.extend(hpack_literal(0x04, b"", b"/tunnel")); // :path is forbidden
+        let mut input = HTTP2_PREFACE.to_vec();
+        input.extend(builder.settings_frame(&[]));
+        input.extend(builder.frame(FrameType::Headers, http2::FLAG_END_HEADERS, 1, &block));
+        let mut conn = connection(&input);
+        conn.read_available().unwrap();
+        assert!(matches!(conn.process().unwrap(), ConnectionAction::NeedMore));
+        let rst = builder.rst_stream(1, crate::streams::PROTOCOL_ERROR);
+        assert!(conn.stream.written.ends_with(&rst));
+    }
+
+    #[test]
+    fn split_frame_reassembles_across_reads() {
+        let builder = Http2FrameBuilder::new();
+        let mut input = HTTP2_PREFACE.to_vec();
+        input.extend(builder.settings_frame(&[]));
+        let mut conn = connection(&input);
+        conn.read_available().unwrap();
+        conn.process().unwrap();
+
+        // A PING delivered in three fragments: part of the header, the
+        // rest of the header plus some payload, then the remainder.
+        let ping = builder.frame(FrameType::Ping, 0, 0, &[1, 2, 3, 4, 5, 6, 7, 8]);
+        for fragment in [&ping[..5], &ping[5..12], &ping[12..]] {
+            assert!(matches!(conn.process().unwrap(), ConnectionAction::NeedMore));
+            conn.stream.input.extend(fragment);
+            conn.read_available().unwrap();
+        }
+        conn.process().unwrap();
+        let pong = builder.ping_ack(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        assert!(conn.stream.written.ends_with(&pong));
+        match conn.state() {
+            ConnectionState::Http2(http2) => assert!(http2.frame_buffer.is_empty()),
+            other => panic!("expected Http2 state, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn alpn_h2_skips_detection_and_expects_the_preface() {
+        let mut input = HTTP2_PREFACE.to_vec();
+        input.extend(Http2FrameBuilder::new().settings_frame(&[]));
+        let mut conn = Connection::new_with_protocol(
+            MockStream::new(&input),
+            test_addr(),
+            ConnectionConfig::default(),
+            Protocol::from_alpn(b"h2"),
+        );
+        match conn.state() {
+            ConnectionState::Http2(http2) => assert!(!http2.preface_received),
+            other => panic!("expected Http2 state, got {other:?}"),
+        }
+        conn.read_available().unwrap();
+        assert!(matches!(conn.process().unwrap(), ConnectionAction::NeedMore));
+        let ack = Http2FrameBuilder::new().settings_ack();
+        assert!(conn.stream.written.ends_with(&ack));
+    }
+
+    #[test]
+    fn alpn_http11_skips_detection() {
+        let mut conn = Connection::new_with_protocol(
+            MockStream::new(b"GET / HTTP/1.1\r\nHost: x\r\n\r\n"),
+            test_addr(),
+            ConnectionConfig::default(),
+            Protocol::from_alpn(b"http/1.1"),
+        );
+        assert!(matches!(conn.state(), ConnectionState::Http1(_)));
+        conn.read_available().unwrap();
+        match conn.process().unwrap() {
+            ConnectionAction::Requests(reqs) => assert_eq!(reqs[0].path(), "/"),
+            other => panic!("expected a request, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn unrecognized_alpn_falls_back_to_detection() {
+        assert_eq!(Protocol::from_alpn(b"spdy/3"), None);
+        let conn = Connection::new_with_protocol(
+            MockStream::new(b""),
+            test_addr(),
+            ConnectionConfig::default(),
+            Protocol::from_alpn(b"spdy/3"),
+        );
+        assert!(matches!(conn.state(), ConnectionState::Detecting));
+    }
+
+    #[test]
+    fn detection_waits_for_ambiguous_prefixes() {
+        assert!(detect_protocol(b"").is_none());
+        assert!(detect_protocol(b"GET").is_none());
+        assert!(detect_protocol(b"PRI * HTTP/2").is_none());
+
+        let full = detect_protocol(b"GET / HTTP/1.1\r\nHost: x\r\n\r\n").unwrap();
+        assert_eq!(full.protocol, Protocol::Http1);
+        assert_eq!(full.consumed, 0);
+
+        let preface = detect_protocol(HTTP2_PREFACE).unwrap();
+        assert_eq!(preface.protocol, Protocol::Http2);
+        assert_eq!(preface.consumed, HTTP2_PREFACE.len());
+
+        assert_eq!(
+            detect_protocol(b"NONSENSE / HTTP/1.1\r\n").unwrap().protocol,
+            Protocol::Unknown
+        );
+    }
+
+    #[test]
+    fn tls_client_hello_is_classified_as_tls() {
+        // TLS 1.2 record header followed by the start of a ClientHello.
+        let hello = [0x16, 0x03, 0x01, 0x00, 0x5a, 0x01, 0x00, 0x00, 0x56];
+        let detection = detect_protocol(&hello).unwrap();
+        assert_eq!(detection.protocol, Protocol::Tls);
+        assert_eq!(detection.consumed, 0);
+
+        // A lone record byte is ambiguous; plaintext HTTP is unaffected.
+        assert!(detect_protocol(&[0x16]).is_none());
+        assert_eq!(
+            detect_protocol(b"GET / HTTP/1.1\r\n").unwrap().protocol,
+            Protocol::Http1
+        );
+        // 0x16 with a non-TLS version byte is not TLS.
+        assert_eq!(
+            detect_protocol(&[0x16, 0x99, 0x01]).unwrap().protocol,
+            Protocol::Unknown
+        );
+    }
+
+    #[test]
+    fn unknown_protocol_is_rejected() {
+        let mut conn = connection(b"\x00\x01\x02garbage everywhere");
+        conn.read_available().unwrap();
+        assert!(conn.process().is_err());
+    }
+
+    #[test]
+    fn sweep_reaps_only_connections_past_the_timeout() {
+        let registry = ConnectionRegistry::new(Duration::from_secs(5));
+        let now = Instant::now();
+        let stale = registry.register_at(now - Duration::from_secs(10));
+        let _fresh = registry.register_at(now - Duration::from_secs(3));
+        let _newest = registry.register_at(now);
+
+        assert_eq!(registry.reap_at(now), vec![stale]);
+        assert_eq!(registry.len(), 2);
+        // Nothing else has expired; a second sweep finds nothing.
+        assert!(registry.reap_at(now).is_empty());
+    }
+
+    #[test]
+    fn touch_rescues_a_connection_from_the_sweep() {
+        let registry = ConnectionRegistry::new(Duration::from_secs(5));
+        let now = Instant::now();
+        let id = registry.register_at(now - Duration::from_secs(10));
+        registry.touch_at(id, now - Duration::from_secs(1));
+
+        assert!(registry.reap_at(now).is_empty());
+        // Once the refreshed stamp ages out, the connection goes too.
+        assert_eq!(registry.reap_at(now + Duration::from_secs(7)), vec![id]);
+        assert!(registry.is_empty());
+    }
+
+    #[test]
+    fn deregistered_connections_are_never_reaped() {
+        let registry = ConnectionRegistry::new(Duration::from_secs(5));
+        let now = Instant::now();
+        let id = registry.register_at(now - Duration::from_secs(10));
+        registry.deregister(id);
+        assert!(registry.reap_at(now).is_empty());
+        assert!(registry.is_empty());
+    }
+}
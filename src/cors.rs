@@ -0,0 +1,244 @@
+//! Cross-Origin Resource Sharing: preflight answering and the
+//! `Access-Control-Allow-*` headers on actual responses.
+
+use crate::http1::{Http1ResponseBuilder, Method, Request};
+
+/// The cross-origin policy a server advertises.
+#[derive(Debug, Clone, Default)]
+pub struct CorsConfig {
+    /// Origins allowed to make cross-origin requests, matched exactly.
+    allowed_origins: Vec<String>,
+    /// When set, any origin is allowed and `*` is advertised (unless
+    /// credentials are allowed, which forces echoing the origin).
+    allow_any_origin: bool,
+    allowed_methods: Vec<Method>,
+    allowed_headers: Vec<String>,
+    allow_credentials: bool,
+    /// How long, in seconds, browsers may cache a preflight answer.
+    max_age: Option<u64>,
+}
+
+impl CorsConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allows cross-origin requests from `origin` (scheme, host, port).
+    pub fn allow_origin(mut self, origin: &str) -> Self {
+        self.allowed_origins.push(origin.to_owned());
+        self
+    }
+
+    /// Allows cross-origin requests from any origin.
+    pub fn allow_any_origin(mut self) -> Self {
+        self.allow_any_origin = true;
+        self
+    }
+
+    pub fn allow_method(mut self, method: Method) -> Self {
+        self.allowed_methods.push(method);
+        self
+    }
+
+    pub fn allow_header(mut self, name: &str) -> Self {
+        self.allowed_headers.push(name.to_owned());
+        self
+    }
+
+    /// Allows requests to carry credentials (cookies, Authorization).
+    pub fn allow_credentials(mut self) -> Self {
+        self.allow_credentials = true;
+        self
+    }
+
+    /// Lets browsers cache the preflight answer for `seconds`.
+    pub fn max_age(mut self, seconds: u64) -> Self {
+        self.max_age = Some(seconds);
+        self
+    }
+
+    fn origin_allowed(&self, origin: &str) -> bool {
+        self.allow_any_origin || self.allowed_origins.iter().any(|o| o == origin)
+    }
+
+    /// The `Access-Control-Allow-Origin` value for an allowed `origin`.
+    /// Credentialed responses must echo the origin; `*` is forbidden there.
+    fn allow_origin_value<'a>(&self, origin: &'a str) -> &'a str {
+        if self.allow_any_origin && !self.allow_credentials {
+            "*"
+        } else {
+            origin
+        }
+    }
+}
+
+/// Whether the request is a CORS preflight: an `OPTIONS` request carrying
+/// both `Origin` and `Access-Control-Request-Method`.
+pub fn is_preflight(request: &Request<'_>) -> bool {
+    request.method == Method::Options
+        && request.header("Origin").is_some()
+        && request.header("Access-Control-Request-Method").is_some()
+}
+
+/// Answers a preflight: a complete `204 No Content` response advertising
+/// the configured policy, or `None` when the origin or requested method
+/// is not allowed (the caller should fall through to its normal 403/404
+/// handling).
+pub fn preflight_response(request: &Request<'_>, config: &CorsConfig) -> Option<Vec<u8>> {
+    let origin = request.header("Origin")?;
+    if !config.origin_allowed(origin) {
+        return None;
+    }
+    let requested = request.header("Access-Control-Request-Method")?;
+    if !config
+        .allowed_methods
+        .iter()
+        .any(|m| m.as_str() == requested.trim())
+    {
+        return None;
+    }
+
+    let methods = config
+        .allowed_methods
+        .iter()
+        .map(Method::as_str)
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut builder = Http1ResponseBuilder::new(204)
+        .header("Access-Control-Allow-Origin", config.allow_origin_value(origin))
+        .header("Access-Control-Allow-Methods", &methods)
+        .header("Vary", "Origin");
+    if !config.allowed_headers.is_empty() {
+        builder = builder.header(
+            "Access-Control-Allow-Headers",
+            &config.allowed_headers.join(", "),
+        );
+    }
+    if config.allow_credentials {
+        builder = builder.header("Access-Control-Allow-Credentials", "true");
+    }
+    if let Some(seconds) = config.max_age {
+        builder = builder.header("Access-Control-Max-Age", &seconds.to_string());
+    }
+    Some(builder.build())
+}
+
+/// Adds the CORS headers an actual (non-preflight) response needs for an
+/// allowed `origin`; a disallowed origin leaves the builder untouched.
+pub fn apply_cors_headers(
+    builder: Http1ResponseBuilder,
+    origin: &str,
+    config: &CorsConfig,
+) -> Http1ResponseBuilder {
+    if !config.origin_allowed(origin) {
+        return builder;
+    }
+    let mut builder = builder
+        .header("Access-Control-Allow-Origin", config.allow_origin_value(origin))
+        .header("Vary", "Origin");
+    if config.allow_credentials {
+        builder = builder.header("Access-Control-Allow-Credentials", "true");
+    }
+    builder
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http1::{Header, Version};
+    use std::borrow::Cow;
+
+    fn preflight<'a>(origin: &'a str, method: &'a str) -> Request<'a> {
+        Request {
+            method: Method::Options,
+            target: "/api/items",
+            version: Version::Http11,
+            headers: vec![
+                Header {
+                    name: "Origin",
+                    value: origin,
+                },
+                Header {
+                    name: "Access-Control-Request-Method",
+                    value: method,
+                },
+            ],
+            body: Cow::Borrowed(&[]),
+            trailers: Vec::new(),
+        }
+    }
+
+    fn config() -> CorsConfig {
+        CorsConfig::new()
+            .allow_origin("https://app.example")
+            .allow_method(Method::Get)
+            .allow_method(Method::Post)
+            .allow_header("X-Requested-With")
+            .max_age(600)
+    }
+
+    #[test]
+    fn preflight_is_recognized() {
+        assert!(is_preflight(&preflight("https://app.example", "POST")));
+
+        let mut plain_options = preflight("https://app.example", "POST");
+        plain_options.headers.pop();
+        assert!(!is_preflight(&plain_options), "no requested method");
+
+        let mut get = preflight("https://app.example", "POST");
+        get.method = Method::Get;
+        assert!(!is_preflight(&get));
+    }
+
+    #[test]
+    fn allowed_origin_preflight_advertises_the_policy() {
+        let request = preflight("https://app.example", "POST");
+        let response = preflight_response(&request, &config()).unwrap();
+        let text = String::from_utf8(response).unwrap();
+        assert!(text.starts_with("HTTP/1.1 204 No Content\r\n"));
+        assert!(text.contains("Access-Control-Allow-Origin: https://app.example\r\n"));
+        assert!(text.contains("Access-Control-Allow-Methods: GET, POST\r\n"));
+        assert!(text.contains("Access-Control-Allow-Headers: X-Requested-With\r\n"));
+        assert!(text.contains("Access-Control-Max-Age: 600\r\n"));
+        assert!(text.contains("Vary: Origin\r\n"));
+    }
+
+    #[test]
+    fn rejected_origin_and_method_yield_none() {
+        let request = preflight("https://evil.example", "POST");
+        assert!(preflight_response(&request, &config()).is_none());
+
+        let request = preflight("https://app.example", "DELETE");
+        assert!(
+            preflight_response(&request, &config()).is_none(),
+            "method outside the policy"
+        );
+    }
+
+    #[test]
+    fn any_origin_advertises_star_unless_credentialed() {
+        let request = preflight("https://anywhere.example", "GET");
+        let open = CorsConfig::new().allow_any_origin().allow_method(Method::Get);
+        let text = String::from_utf8(preflight_response(&request, &open).unwrap()).unwrap();
+        assert!(text.contains("Access-Control-Allow-Origin: *\r\n"));
+
+        let credentialed = open.allow_credentials();
+        let text =
+            String::from_utf8(preflight_response(&request, &credentialed).unwrap()).unwrap();
+        assert!(text.contains("Access-Control-Allow-Origin: https://anywhere.example\r\n"));
+        assert!(text.contains("Access-Control-Allow-Credentials: true\r\n"));
+    }
+
+    #[test]
+    fn actual_responses_get_cors_headers_for_allowed_origins() {
+        let builder = Http1ResponseBuilder::new(200);
+        let response = apply_cors_headers(builder, "https://app.example", &config()).build();
+        let text = String::from_utf8(response).unwrap();
+        assert!(text.contains("Access-Control-Allow-Origin: https://app.example\r\n"));
+
+        let builder = Http1ResponseBuilder::new(200);
+        let response = apply_cors_headers(builder, "https://evil.example", &config()).build();
+        let text = String::from_utf8(response).unwrap();
+        assert!(!text.contains("Access-Control-Allow-Origin"));
+    }
+}
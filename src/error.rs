@@ -0,0 +1,194 @@
+//! The core error type shared across the crate.
+
+use std::fmt;
+
+use crate::http1::Http1ParseError;
+use crate::http2::Http2ParseError;
+
+/// A coarse classification of an [`Error`], used to pick a response status
+/// without matching on every underlying parser variant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    /// The request was syntactically invalid.
+    BadRequest,
+    /// The request exceeded a configured size limit.
+    PayloadTooLarge,
+    /// The request target exceeded the configured request-line limit.
+    UriTooLong,
+    /// The request used a feature the server does not implement, such as
+    /// a transfer coding it cannot decode.
+    NotImplemented,
+    /// A protocol rule was violated (preface, SETTINGS, framing order).
+    ProtocolError,
+    /// A frame exceeded the negotiated maximum size.
+    FrameSizeError,
+    /// A flow-control window was violated.
+    FlowControlError,
+    /// A read or write stalled beyond its configured timeout.
+    Timeout,
+    /// TLS configuration or handshake failure.
+    Tls,
+    /// I/O failure on the underlying stream.
+    Io,
+}
+
+/// Errors surfaced by the parsing and connection layers.
+#[derive(Debug)]
+pub enum Error {
+    /// The request could not be parsed or violated protocol requirements.
+    ParseError(String),
+    /// HTTP/1.1 request parsing failed.
+    Http1(Http1ParseError),
+    /// HTTP/2 frame parsing failed.
+    Http2(Http2ParseError),
+    /// TLS configuration or handshake failure.
+    TlsError(String),
+    /// A read or write stalled beyond its configured timeout.
+    Timeout,
+    /// I/O failure on the underlying stream.
+    Io(std::io::Error),
+}
+
+impl Error {
+    /// The HTTP status code this error maps to, derived from its
+    /// [`ErrorCode`].
+    pub fn status_code(&self) -> u16 {
+        match self.code() {
+            ErrorCode::BadRequest
+            | ErrorCode::ProtocolError
+            | ErrorCode::FrameSizeError
+            | ErrorCode::FlowControlError => 400,
+            ErrorCode::PayloadTooLarge => 413,
+            ErrorCode::UriTooLong => 414,
+            ErrorCode::NotImplemented => 501,
+            ErrorCode::Timeout => 408,
+            ErrorCode::Tls | ErrorCode::Io => 500,
+        }
+    }
+
+    /// Builds a minimal HTTP/1.1 error response for this error: plain-text
+    /// display message, `Connection: close`.
+    pub fn to_response(&self) -> Vec<u8> {
+        crate::http1::Http1ResponseBuilder::new(self.status_code())
+            .header("Content-Type", "text/plain")
+            .header("Connection", "close")
+            .body(self.to_string().as_bytes())
+            .build()
+    }
+
+    /// Classifies the error for status mapping.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Error::ParseError(_) => ErrorCode::BadRequest,
+            Error::Http1(e) => match e {
+                Http1ParseError::RequestTooLarge => ErrorCode::PayloadTooLarge,
+                Http1ParseError::UriTooLong => ErrorCode::UriTooLong,
+                Http1ParseError::UnsupportedTransferEncoding => ErrorCode::NotImplemented,
+                _ => ErrorCode::BadRequest,
+            },
+            Error::Http2(e) => e.to_error_code(),
+            Error::TlsError(_) => ErrorCode::Tls,
+            Error::Timeout => ErrorCode::Timeout,
+            Error::Io(_) => ErrorCode::Io,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::ParseError(msg) => write!(f, "parse error: {msg}"),
+            Error::Http1(err) => write!(f, "HTTP/1.1 parse failed: {err}"),
+            Error::Http2(err) => write!(f, "HTTP/2 parse failed: {err}"),
+            Error::TlsError(msg) => write!(f, "TLS error: {msg}"),
+            Error::Timeout => write!(f, "operation timed out"),
+            Error::Io(err) => write!(f, "I/O error: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Http1(err) => Some(err),
+            Error::Http2(err) => Some(err),
+            Error::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<Http1ParseError> for Error {
+    fn from(err: Http1ParseError) -> Self {
+        Error::Http1(err)
+    }
+}
+
+impl From<Http2ParseError> for Error {
+    fn from(err: Http2ParseError) -> Self {
+        Error::Http2(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parser_errors_map_to_codes() {
+        assert_eq!(
+            Error::from(Http1ParseError::TooManyHeaders).code(),
+            ErrorCode::BadRequest
+        );
+        assert_eq!(
+            Error::from(Http1ParseError::RequestTooLarge).code(),
+            ErrorCode::PayloadTooLarge
+        );
+        assert_eq!(
+            Error::from(Http2ParseError::InvalidFrameSize).code(),
+            ErrorCode::FrameSizeError
+        );
+        assert_eq!(
+            Error::from(Http2ParseError::InvalidPreface).code(),
+            ErrorCode::ProtocolError
+        );
+        assert_eq!(
+            Error::from(std::io::Error::other("boom")).code(),
+            ErrorCode::Io
+        );
+    }
+
+    #[test]
+    fn request_too_large_maps_to_413() {
+        let err = Error::from(Http1ParseError::RequestTooLarge);
+        assert_eq!(err.status_code(), 413);
+        let response = err.to_response();
+        let text = std::str::from_utf8(&response).unwrap();
+        assert!(text.starts_with("HTTP/1.1 413 Payload Too Large\r\n"));
+        assert!(text.contains("Content-Type: text/plain\r\n"));
+        assert!(text.contains("Connection: close\r\n"));
+        let body = text.split("\r\n\r\n").nth(1).unwrap();
+        assert!(text.contains(&format!("Content-Length: {}\r\n", body.len())));
+        assert!(body.contains("request too large"));
+    }
+
+    #[test]
+    fn timeout_maps_to_408() {
+        assert_eq!(Error::Timeout.code(), ErrorCode::Timeout);
+        assert_eq!(Error::Timeout.status_code(), 408);
+        assert_eq!(Error::Timeout.to_string(), "operation timed out");
+    }
+
+    #[test]
+    fn conversions_preserve_the_source() {
+        let err = Error::from(Http1ParseError::InvalidMethod);
+        assert!(std::error::Error::source(&err).is_some());
+        assert_eq!(err.to_string(), "HTTP/1.1 parse failed: invalid method");
+    }
+}
@@ -0,0 +1,107 @@
+//! `application/x-www-form-urlencoded` body parsing: the format of HTML
+//! form POSTs, where `+` encodes a space (unlike percent-encoded paths).
+
+use std::borrow::Cow;
+
+/// Parses an urlencoded body into decoded key/value pairs, in document
+/// order.
+///
+/// Pairs are separated by `&` and split at the first `=`; a key without
+/// `=` yields an empty value, and empty segments (from `&&` or a
+/// trailing `&`) are skipped. Both sides are percent-decoded with `+`
+/// treated as a space. Undecodable input is not an error: malformed
+/// percent escapes pass through literally and invalid UTF-8 is replaced.
+pub fn parse_urlencoded(body: &[u8]) -> impl Iterator<Item = (Cow<'_, str>, Cow<'_, str>)> {
+    body.split(|&b| b == b'&')
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| {
+            let (key, value) = match segment.iter().position(|&b| b == b'=') {
+                Some(idx) => (&segment[..idx], &segment[idx + 1..]),
+                None => (segment, &segment[segment.len()..]),
+            };
+            (decode_component(key), decode_component(value))
+        })
+}
+
+/// Decodes one urlencoded component, borrowing when nothing needs
+/// rewriting.
+fn decode_component(input: &[u8]) -> Cow<'_, str> {
+    if !input.iter().any(|&b| b == b'%' || b == b'+') {
+        return String::from_utf8_lossy(input);
+    }
+    let mut out = Vec::with_capacity(input.len());
+    let mut i = 0;
+    while i < input.len() {
+        match input[i] {
+            b'+' => {
+                out.push(b' ');
+                i += 1;
+            }
+            b'%' if i + 2 < input.len() => match (hex_value(input[i + 1]), hex_value(input[i + 2])) {
+                (Some(hi), Some(lo)) => {
+                    out.push((hi << 4) | lo);
+                    i += 3;
+                }
+                _ => {
+                    out.push(b'%');
+                    i += 1;
+                }
+            },
+            byte => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    Cow::Owned(String::from_utf8_lossy(&out).into_owned())
+}
+
+fn hex_value(byte: u8) -> Option<u8> {
+    match byte {
+        b'0'..=b'9' => Some(byte - b'0'),
+        b'a'..=b'f' => Some(byte - b'a' + 10),
+        b'A'..=b'F' => Some(byte - b'A' + 10),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_escapes_plus_and_valueless_keys() {
+        let pairs: Vec<_> = parse_urlencoded(b"a=1&b=hello+world&c=%26&flag").collect();
+        assert_eq!(
+            pairs,
+            vec![
+                (Cow::Borrowed("a"), Cow::Borrowed("1")),
+                (Cow::Borrowed("b"), Cow::Owned("hello world".to_owned())),
+                (Cow::Borrowed("c"), Cow::Owned("&".to_owned())),
+                (Cow::Borrowed("flag"), Cow::Borrowed("")),
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_segments_are_skipped() {
+        let pairs: Vec<_> = parse_urlencoded(b"&a=1&&b=2&").collect();
+        assert_eq!(pairs.len(), 2);
+        assert_eq!(pairs[0], (Cow::Borrowed("a"), Cow::Borrowed("1")));
+        assert_eq!(pairs[1], (Cow::Borrowed("b"), Cow::Borrowed("2")));
+    }
+
+    #[test]
+    fn plain_components_borrow_from_the_body() {
+        let (key, value) = parse_urlencoded(b"name=angelax").next().unwrap();
+        assert!(matches!(key, Cow::Borrowed("name")));
+        assert!(matches!(value, Cow::Borrowed("angelax")));
+    }
+
+    #[test]
+    fn malformed_escapes_pass_through() {
+        let pairs: Vec<_> = parse_urlencoded(b"a=%zz&b=%2").collect();
+        assert_eq!(pairs[0].1, "%zz");
+        assert_eq!(pairs[1].1, "%2");
+    }
+}
@@ -0,0 +1,760 @@
+//! HPACK header compression (RFC 7541): the dynamic table shared by a
+//! connection's header blocks, and the block decoder that walks it.
+
+use crate::http2::Http2ParseError;
+use crate::huffman::HuffmanDecoder;
+use std::collections::VecDeque;
+
+/// Per-entry overhead added to the name/value lengths when accounting
+/// table size (RFC 7541 §4.1) and header-list size (RFC 7540 §6.5.2).
+const ENTRY_OVERHEAD: usize = 32;
+
+/// A decoded header list: owned name/value pairs in block order.
+pub type HeaderList = Vec<(Vec<u8>, Vec<u8>)>;
+
+/// The static header table (RFC 7541 Appendix A), indexed from 1.
+const STATIC_TABLE: [(&str, &str); 61] = [
+    (":authority", ""),
+    (":method", "GET"),
+    (":method", "POST"),
+    (":path", "/"),
+    (":path", "/index.html"),
+    (":scheme", "http"),
+    (":scheme", "https"),
+    (":status", "200"),
+    (":status", "204"),
+    (":status", "206"),
+    (":status", "304"),
+    (":status", "400"),
+    (":status", "404"),
+    (":status", "500"),
+    ("accept-charset", ""),
+    ("accept-encoding", "gzip, deflate"),
+    ("accept-language", ""),
+    ("accept-ranges", ""),
+    ("accept", ""),
+    ("access-control-allow-origin", ""),
+    ("age", ""),
+    ("allow", ""),
+    ("authorization", ""),
+    ("cache-control", ""),
+    ("content-disposition", ""),
+    ("content-encoding", ""),
+    ("content-language", ""),
+    ("content-length", ""),
+    ("content-location", ""),
+    ("content-range", ""),
+    ("content-type", ""),
+    ("cookie", ""),
+    ("date", ""),
+    ("etag", ""),
+    ("expect", ""),
+    ("expires", ""),
+    ("from", ""),
+    ("host", ""),
+    ("if-match", ""),
+    ("if-modified-since", ""),
+    ("if-none-match", ""),
+    ("if-range", ""),
+    ("if-unmodified-since", ""),
+    ("last-modified", ""),
+    ("link", ""),
+    ("location", ""),
+    ("max-forwards", ""),
+    ("proxy-authenticate", ""),
+    ("proxy-authorization", ""),
+    ("range", ""),
+    ("referer", ""),
+    ("refresh", ""),
+    ("retry-after", ""),
+    ("server", ""),
+    ("set-cookie", ""),
+    ("strict-transport-security", ""),
+    ("transfer-encoding", ""),
+    ("user-agent", ""),
+    ("vary", ""),
+    ("via", ""),
+    ("www-authenticate", ""),
+];
+
+/// Decoder-side HPACK state: the dynamic table, newest entry first.
+///
+/// The table must survive for the whole connection — entries indexed by
+/// one header block are inserted by earlier ones — so capacity changes
+/// evict from the oldest end rather than rebuilding the table.
+#[derive(Debug, Clone)]
+pub struct HpackDecoder {
+    entries: VecDeque<(Vec<u8>, Vec<u8>)>,
+    /// Current size per §4.1: name + value + 32 octets per entry.
+    size: usize,
+    max_size: usize,
+    /// The ceiling this endpoint advertised in its own
+    /// `SETTINGS_HEADER_TABLE_SIZE`. In-block size updates may set any
+    /// capacity up to this bound but no further (RFC 7541 §6.3).
+    settings_max_size: usize,
+    /// Set when this endpoint's advertised setting reduced `max_size`:
+    /// the peer must open its next header block with a dynamic table size
+    /// update no larger than this (RFC 7541 §4.2).
+    pending_reduction: Option<usize>,
+    /// Scratch storage reused across blocks: every decoded name and value
+    /// lands here back to back, so steady-state decoding allocates
+    /// nothing.
+    scratch: Vec<u8>,
+    /// Where each header of the current block sits in `scratch`.
+    spans: Vec<HeaderSpan>,
+}
+
+/// One header's position in the decoder's scratch buffer: the name runs
+/// `name_start..name_end`, the value `name_end..value_end`.
+#[derive(Debug, Clone, Copy)]
+struct HeaderSpan {
+    name_start: usize,
+    name_end: usize,
+    value_end: usize,
+}
+
+/// A decoded header block whose names and values borrow the decoder's
+/// scratch buffer; see [`HpackDecoder::decode_into`].
+#[derive(Debug, Clone, Copy)]
+pub struct HeaderListView<'d> {
+    scratch: &'d [u8],
+    spans: &'d [HeaderSpan],
+}
+
+impl<'d> HeaderListView<'d> {
+    /// Number of headers in the block.
+    pub fn len(&self) -> usize {
+        self.spans.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+
+    /// The header at `index`, in block order.
+    pub fn get(&self, index: usize) -> Option<(&'d [u8], &'d [u8])> {
+        self.spans.get(index).map(|&span| self.resolve(span))
+    }
+
+    /// Iterates the headers in block order.
+    pub fn iter(&self) -> impl Iterator<Item = (&'d [u8], &'d [u8])> + '_ {
+        self.spans.iter().map(|&span| self.resolve(span))
+    }
+
+    fn resolve(&self, span: HeaderSpan) -> (&'d [u8], &'d [u8]) {
+        (
+            &self.scratch[span.name_start..span.name_end],
+            &self.scratch[span.name_end..span.value_end],
+        )
+    }
+}
+
+impl Default for HpackDecoder {
+    fn default() -> Self {
+        // 4096 is the SETTINGS_HEADER_TABLE_SIZE default (RFC 7540 §11.3).
+        Self::new(4096)
+    }
+}
+
+impl HpackDecoder {
+    pub fn new(max_size: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            size: 0,
+            max_size,
+            settings_max_size: max_size,
+            pending_reduction: None,
+            scratch: Vec::new(),
+            spans: Vec::new(),
+        }
+    }
+
+    /// The accounted size of the dynamic table in octets.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// The maximum accounted size the table may reach.
+    pub fn max_size(&self) -> usize {
+        self.max_size
+    }
+
+    /// Number of entries currently in the table.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The entry at `index`, zero-based from the newest insertion.
+    pub fn get(&self, index: usize) -> Option<(&[u8], &[u8])> {
+        self.entries
+            .get(index)
+            .map(|(name, value)| (name.as_slice(), value.as_slice()))
+    }
+
+    /// Inserts an entry at the newest end, evicting from the oldest end
+    /// until the table fits. An entry larger than the whole table empties
+    /// it and is not inserted (RFC 7541 §4.4).
+    pub fn insert(&mut self, name: &[u8], value: &[u8]) {
+        let entry_size = name.len() + value.len() + ENTRY_OVERHEAD;
+        if entry_size > self.max_size {
+            self.entries.clear();
+            self.size = 0;
+            return;
+        }
+        self.evict_until(self.max_size - entry_size);
+        self.entries.push_front((name.to_vec(), value.to_vec()));
+        self.size += entry_size;
+    }
+
+    /// Changes the table capacity in place, evicting the oldest entries
+    /// until the current contents fit the new bound.
+    ///
+    /// This reflects a change to *this endpoint's own* advertised
+    /// `SETTINGS_HEADER_TABLE_SIZE` — the bound the peer's encoder must
+    /// honor. The peer's setting constrains the encoder it faces, never
+    /// this decoder. A reduction arms an acknowledgement requirement: the
+    /// peer's next header block must begin with a dynamic table size
+    /// update no larger than the reduced capacity, or
+    /// [`HpackDecoder::decode_block`] fails with a compression error
+    /// (RFC 7541 §4.2).
+    pub fn set_max_table_size(&mut self, max_size: usize) {
+        if max_size < self.max_size {
+            self.pending_reduction = Some(max_size);
+        }
+        self.settings_max_size = max_size;
+        self.apply_max_table_size(max_size);
+    }
+
+    /// The capacity change itself, shared with in-block size updates —
+    /// which acknowledge a reduction rather than demanding one.
+    fn apply_max_table_size(&mut self, max_size: usize) {
+        self.max_size = max_size;
+        self.evict_until(max_size);
+    }
+
+    /// Decodes one complete header block into owned name/value pairs.
+    ///
+    /// `max_header_list_size` bounds the decompressed size of the list —
+    /// name length + value length + 32 per entry (RFC 7540 §6.5.2). The
+    /// budget is charged as each header is produced, so a compact block
+    /// referencing huge table entries (an HPACK bomb) aborts with
+    /// [`Http2ParseError::CompressionError`] long before full expansion.
+    pub fn decode_block(
+        &mut self,
+        block: &[u8],
+        max_header_list_size: Option<u32>,
+    ) -> Result<HeaderList, Http2ParseError> {
+        let view = self.decode_into(block, max_header_list_size)?;
+        Ok(view
+            .iter()
+            .map(|(name, value)| (name.to_vec(), value.to_vec()))
+            .collect())
+    }
+
+    /// Decodes one complete header block into the decoder's reusable
+    /// scratch buffer, returning a view of slices into it.
+    ///
+    /// This is the allocation-free form of [`HpackDecoder::decode_block`]:
+    /// after the first few blocks have sized the scratch, decoding copies
+    /// bytes without touching the allocator (Huffman-coded literals and
+    /// incremental-indexing inserts excepted). The view — and the previous
+    /// block's contents — are invalidated by the next decode.
+    pub fn decode_into(
+        &mut self,
+        block: &[u8],
+        max_header_list_size: Option<u32>,
+    ) -> Result<HeaderListView<'_>, Http2ParseError> {
+        self.scratch.clear();
+        self.spans.clear();
+        let mut list_size = 0usize;
+        let mut cursor = 0;
+        let mut pending_reduction = self.pending_reduction.take();
+        while cursor < block.len() {
+            let byte = block[cursor];
+            let is_size_update = byte & 0xe0 == 0x20;
+            if pending_reduction.is_some() && !is_size_update {
+                // The reduced capacity was never acknowledged (§4.2).
+                return Err(Http2ParseError::CompressionError);
+            }
+            if is_size_update {
+                // Dynamic table size update (§6.3). The new capacity must
+                // stay within the ceiling this endpoint advertised; an
+                // encoder cannot grant itself a larger table.
+                let (size, next) = decode_integer(block, cursor, 5)?;
+                cursor = next;
+                if size > self.settings_max_size {
+                    return Err(Http2ParseError::CompressionError);
+                }
+                if let Some(limit) = pending_reduction {
+                    if size > limit {
+                        return Err(Http2ParseError::CompressionError);
+                    }
+                    pending_reduction = None;
+                }
+                self.apply_max_table_size(size);
+                continue;
+            }
+            let name_start = self.scratch.len();
+            let (name_end, value_end) = if byte & 0x80 != 0 {
+                // Indexed header field (§6.1).
+                let (index, next) = decode_integer(block, cursor, 7)?;
+                cursor = next;
+                self.copy_indexed(index)?
+            } else {
+                // Literal header field (§6.2): with incremental indexing
+                // (01), without indexing (0000), or never indexed (0001).
+                let indexed = byte & 0xc0 == 0x40;
+                let prefix_bits = if indexed { 6 } else { 4 };
+                let (index, next) = decode_integer(block, cursor, prefix_bits)?;
+                cursor = next;
+                if index == 0 {
+                    cursor = decode_string_into(block, cursor, &mut self.scratch)?;
+                } else {
+                    self.copy_indexed_name(index)?;
+                }
+                let name_end = self.scratch.len();
+                cursor = decode_string_into(block, cursor, &mut self.scratch)?;
+                let value_end = self.scratch.len();
+                if indexed {
+                    // The table must own its entries; this copy is the
+                    // price of the insertion, not of the decode.
+                    let name = self.scratch[name_start..name_end].to_vec();
+                    let value = self.scratch[name_end..value_end].to_vec();
+                    self.insert(&name, &value);
+                }
+                (name_end, value_end)
+            };
+
+            list_size += value_end - name_start + ENTRY_OVERHEAD;
+            if max_header_list_size.is_some_and(|limit| list_size > limit as usize) {
+                return Err(Http2ParseError::CompressionError);
+            }
+            self.spans.push(HeaderSpan {
+                name_start,
+                name_end,
+                value_end,
+            });
+        }
+        if pending_reduction.is_some() {
+            // An empty block cannot acknowledge the reduction either.
+            return Err(Http2ParseError::CompressionError);
+        }
+        Ok(HeaderListView {
+            scratch: &self.scratch,
+            spans: &self.spans,
+        })
+    }
+
+    /// Copies the entry at `index` into the scratch buffer, returning the
+    /// offsets where its name and value end.
+    fn copy_indexed(&mut self, index: usize) -> Result<(usize, usize), Http2ParseError> {
+        if index == 0 {
+            return Err(Http2ParseError::CompressionError);
+        }
+        if index <= STATIC_TABLE.len() {
+            let (name, value) = STATIC_TABLE[index - 1];
+            self.scratch.extend_from_slice(name.as_bytes());
+            let name_end = self.scratch.len();
+            self.scratch.extend_from_slice(value.as_bytes());
+            return Ok((name_end, self.scratch.len()));
+        }
+        let (name, value) = self
+            .entries
+            .get(index - STATIC_TABLE.len() - 1)
+            .ok_or(Http2ParseError::CompressionError)?;
+        self.scratch.extend_from_slice(name);
+        let name_end = self.scratch.len();
+        self.scratch.extend_from_slice(value);
+        Ok((name_end, self.scratch.len()))
+    }
+
+    /// Copies only the name of the entry at `index` into the scratch
+    /// buffer, for literals that reference an indexed name.
+    fn copy_indexed_name(&mut self, index: usize) -> Result<(), Http2ParseError> {
+        if index == 0 {
+            return Err(Http2ParseError::CompressionError);
+        }
+        if index <= STATIC_TABLE.len() {
+            self.scratch
+                .extend_from_slice(STATIC_TABLE[index - 1].0.as_bytes());
+            return Ok(());
+        }
+        let (name, _) = self
+            .entries
+            .get(index - STATIC_TABLE.len() - 1)
+            .ok_or(Http2ParseError::CompressionError)?;
+        self.scratch.extend_from_slice(name);
+        Ok(())
+    }
+
+    fn evict_until(&mut self, bound: usize) {
+        while self.size > bound {
+            let (name, value) = self
+                .entries
+                .pop_back()
+                .expect("a non-zero size implies entries");
+            self.size -= name.len() + value.len() + ENTRY_OVERHEAD;
+        }
+    }
+}
+
+/// Encodes header lists into HPACK blocks (RFC 7541).
+///
+/// Every field is emitted as a literal without indexing (§6.2.2): the
+/// encoder keeps no dynamic-table state to synchronize with the peer, at
+/// the cost of forgoing compression beyond the fixed field layout. Any
+/// conformant decoder — including [`HpackDecoder`] — reads such blocks
+/// without touching its own table.
+#[derive(Debug, Default)]
+pub struct HpackEncoder;
+
+impl HpackEncoder {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Encodes `headers`, in order, as one header block.
+    pub fn encode_block<'a, I>(&mut self, headers: I) -> Vec<u8>
+    where
+        I: IntoIterator<Item = (&'a [u8], &'a [u8])>,
+    {
+        let mut block = Vec::new();
+        for (name, value) in headers {
+            // Literal without indexing, literal name: a zero 4-bit prefix.
+            block.push(0x00);
+            encode_string(&mut block, name);
+            encode_string(&mut block, value);
+        }
+        block
+    }
+}
+
+/// Encodes an HPACK prefixed integer (RFC 7541 §5.1) whose prefix byte —
+/// pattern bits included — has already been started in `first`.
+fn encode_integer(out: &mut Vec<u8>, first: u8, value: usize, prefix_bits: u32) {
+    let mask = (1usize << prefix_bits) - 1;
+    if value < mask {
+        out.push(first | value as u8);
+        return;
+    }
+    out.push(first | mask as u8);
+    let mut rest = value - mask;
+    while rest >= 0x80 {
+        out.push((rest & 0x7f) as u8 | 0x80);
+        rest >>= 7;
+    }
+    out.push(rest as u8);
+}
+
+/// Encodes an HPACK string literal (RFC 7541 §5.2), always raw (H bit
+/// clear).
+fn encode_string(out: &mut Vec<u8>, bytes: &[u8]) {
+    encode_integer(out, 0x00, bytes.len(), 7);
+    out.extend_from_slice(bytes);
+}
+
+/// Decodes an HPACK prefixed integer (RFC 7541 §5.1), returning the value
+/// and the offset one past it.
+fn decode_integer(
+    block: &[u8],
+    cursor: usize,
+    prefix_bits: u32,
+) -> Result<(usize, usize), Http2ParseError> {
+    let mask = (1usize << prefix_bits) - 1;
+    let first = *block.get(cursor).ok_or(Http2ParseError::CompressionError)?;
+    let mut value = first as usize & mask;
+    let mut cursor = cursor + 1;
+    if value < mask {
+        return Ok((value, cursor));
+    }
+    let mut shift = 0u32;
+    loop {
+        let byte = *block.get(cursor).ok_or(Http2ParseError::CompressionError)?;
+        cursor += 1;
+        value = value
+            .checked_add(((byte & 0x7f) as usize) << shift)
+            .ok_or(Http2ParseError::CompressionError)?;
+        if byte & 0x80 == 0 {
+            return Ok((value, cursor));
+        }
+        shift += 7;
+        if shift > 28 {
+            return Err(Http2ParseError::CompressionError);
+        }
+    }
+}
+
+/// Decodes an HPACK string literal (RFC 7541 §5.2) onto the end of `out`,
+/// returning the offset one past it. A plain literal is copied straight
+/// in; a Huffman-coded one passes through the decoder's temporary.
+fn decode_string_into(
+    block: &[u8],
+    cursor: usize,
+    out: &mut Vec<u8>,
+) -> Result<usize, Http2ParseError> {
+    let huffman = block
+        .get(cursor)
+        .is_some_and(|&b| b & 0x80 != 0);
+    let (len, cursor) = decode_integer(block, cursor, 7)?;
+    let end = cursor
+        .checked_add(len)
+        .filter(|&end| end <= block.len())
+        .ok_or(Http2ParseError::CompressionError)?;
+    let raw = &block[cursor..end];
+    if huffman {
+        let decoded =
+            HuffmanDecoder::decode(raw).map_err(|_| Http2ParseError::CompressionError)?;
+        out.extend_from_slice(&decoded);
+    } else {
+        out.extend_from_slice(raw);
+    }
+    Ok(end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_accounts_entry_overhead() {
+        let mut decoder = HpackDecoder::new(4096);
+        decoder.insert(b"custom-key", b"custom-value");
+        assert_eq!(decoder.size(), 10 + 12 + 32);
+        assert_eq!(decoder.get(0), Some((b"custom-key".as_slice(), b"custom-value".as_slice())));
+    }
+
+    #[test]
+    fn insertion_evicts_from_the_oldest_end() {
+        // Room for exactly two 42-octet entries.
+        let mut decoder = HpackDecoder::new(84);
+        decoder.insert(b"aaaaa", b"11111");
+        decoder.insert(b"bbbbb", b"22222");
+        decoder.insert(b"ccccc", b"33333");
+        assert_eq!(decoder.len(), 2);
+        assert_eq!(decoder.get(0).unwrap().0, b"ccccc");
+        assert_eq!(decoder.get(1).unwrap().0, b"bbbbb");
+    }
+
+    #[test]
+    fn oversized_entry_empties_the_table() {
+        let mut decoder = HpackDecoder::new(64);
+        decoder.insert(b"name", b"value");
+        decoder.insert(b"n", &[b'v'; 64]);
+        assert!(decoder.is_empty());
+        assert_eq!(decoder.size(), 0);
+    }
+
+    #[test]
+    fn decodes_the_rfc7541_example_request_sequence() {
+        // RFC 7541 C.3.1: indexed static fields plus a literal
+        // `:authority` that enters the dynamic table.
+        let mut decoder = HpackDecoder::default();
+        let first = [
+            0x82, 0x86, 0x84, 0x41, 0x0f, b'w', b'w', b'w', b'.', b'e', b'x', b'a', b'm', b'p',
+            b'l', b'e', b'.', b'c', b'o', b'm',
+        ];
+        let headers = decoder.decode_block(&first, None).unwrap();
+        assert_eq!(headers.len(), 4);
+        assert_eq!(headers[0], (b":method".to_vec(), b"GET".to_vec()));
+        assert_eq!(headers[3], (b":authority".to_vec(), b"www.example.com".to_vec()));
+        assert_eq!(decoder.size(), 57);
+
+        // C.3.2: the second request references the dynamic entry (index
+        // 62) and adds a literal cache-control.
+        let second = [
+            0x82, 0x86, 0x84, 0xbe, 0x58, 0x08, b'n', b'o', b'-', b'c', b'a', b'c', b'h', b'e',
+        ];
+        let headers = decoder.decode_block(&second, None).unwrap();
+        assert_eq!(headers[3], (b":authority".to_vec(), b"www.example.com".to_vec()));
+        assert_eq!(headers[4], (b"cache-control".to_vec(), b"no-cache".to_vec()));
+    }
+
+    #[test]
+    fn decodes_huffman_coded_literals() {
+        // RFC 7541 C.4.1: the same first request with the authority
+        // Huffman-coded.
+        let mut decoder = HpackDecoder::default();
+        let block = [
+            0x82, 0x86, 0x84, 0x41, 0x8c, 0xf1, 0xe3, 0xc2, 0xe5, 0xf2, 0x3a, 0x6b, 0xa0, 0xab,
+            0x90, 0xf4, 0xff,
+        ];
+        let headers = decoder.decode_block(&block, None).unwrap();
+        assert_eq!(headers[3], (b":authority".to_vec(), b"www.example.com".to_vec()));
+    }
+
+    #[test]
+    fn header_list_size_limit_aborts_expansion() {
+        // Plant one large dynamic entry, then reference it over and over:
+        // a few bytes of input expanding to kilobytes of headers.
+        let mut block = vec![0x40, 0x06];
+        block.extend_from_slice(b"x-bomb");
+        block.push(0x7f);
+        block.push(0x35); // 127 + 53 = 180-octet value
+        block.extend(std::iter::repeat_n(b'a', 180));
+        block.extend(std::iter::repeat_n(0xbe, 50));
+
+        // Unbounded, the block expands to 51 copies of the entry.
+        let mut decoder = HpackDecoder::default();
+        let headers = decoder.decode_block(&block, None).unwrap();
+        assert_eq!(headers.len(), 51);
+
+        // With a budget, the decode aborts after the first reference.
+        let mut decoder = HpackDecoder::default();
+        assert_eq!(
+            decoder.decode_block(&block, Some(500)).unwrap_err(),
+            Http2ParseError::CompressionError
+        );
+    }
+
+    #[test]
+    fn truncated_and_invalid_blocks_are_compression_errors() {
+        let mut decoder = HpackDecoder::default();
+        // Index 0 is never a valid reference.
+        assert_eq!(
+            decoder.decode_block(&[0x80], None).unwrap_err(),
+            Http2ParseError::CompressionError
+        );
+        // A string literal whose declared length runs past the block.
+        assert_eq!(
+            decoder.decode_block(&[0x40, 0x02, b'a'], None).unwrap_err(),
+            Http2ParseError::CompressionError
+        );
+        // A dynamic index beyond the (empty) table.
+        assert_eq!(
+            decoder.decode_block(&[0xbe], None).unwrap_err(),
+            Http2ParseError::CompressionError
+        );
+    }
+
+    #[test]
+    fn reduced_table_size_accepts_a_compliant_size_update() {
+        let mut decoder = HpackDecoder::default();
+        decoder.set_max_table_size(64);
+        // 0x20 | 30: a size update to 30 octets, then `:method: GET`.
+        let headers = decoder.decode_block(&[0x3e, 0x82], None).unwrap();
+        assert_eq!(headers, vec![(b":method".to_vec(), b"GET".to_vec())]);
+        assert_eq!(decoder.max_size(), 30);
+        // The acknowledgement is consumed; later blocks need none.
+        assert!(decoder.decode_block(&[0x82], None).is_ok());
+    }
+
+    #[test]
+    fn unacknowledged_table_size_reduction_is_rejected() {
+        let mut decoder = HpackDecoder::default();
+        decoder.set_max_table_size(64);
+        // The next block opens with a header field, not a size update.
+        assert_eq!(
+            decoder.decode_block(&[0x82], None).unwrap_err(),
+            Http2ParseError::CompressionError
+        );
+
+        // A size update above the reduced capacity is no acknowledgement.
+        let mut decoder = HpackDecoder::default();
+        decoder.set_max_table_size(64);
+        // 0x3f 0x61: a size update to 31 + 97 = 128 octets.
+        assert_eq!(
+            decoder.decode_block(&[0x3f, 0x61, 0x82], None).unwrap_err(),
+            Http2ParseError::CompressionError
+        );
+
+        // Growing the capacity demands nothing.
+        let mut decoder = HpackDecoder::new(64);
+        decoder.set_max_table_size(4096);
+        assert!(decoder.decode_block(&[0x82], None).is_ok());
+    }
+
+    #[test]
+    fn size_update_past_the_advertised_ceiling_is_rejected() {
+        // The encoder may shrink its table at will but can never grow it
+        // past the ceiling this endpoint advertised (§6.3).
+        let mut decoder = HpackDecoder::default();
+        // 0x3f 0xe2 0x1f: a size update to 4097, one octet past the
+        // default 4096 ceiling…
+        assert_eq!(
+            decoder.decode_block(&[0x3f, 0xe2, 0x1f, 0x82], None).unwrap_err(),
+            Http2ParseError::CompressionError
+        );
+        // …while the ceiling itself is fine.
+        let mut decoder = HpackDecoder::default();
+        assert!(decoder.decode_block(&[0x3f, 0xe1, 0x1f, 0x82], None).is_ok());
+        assert_eq!(decoder.max_size(), 4096);
+    }
+
+    #[test]
+    fn encoded_blocks_round_trip_through_the_decoder() {
+        let headers: [(&[u8], &[u8]); 3] = [
+            (b":status", b"200"),
+            (b"content-type", b"text/plain"),
+            // Long enough to exercise integer continuation bytes.
+            (b"x-long", &[b'v'; 300]),
+        ];
+        let mut encoder = HpackEncoder::new();
+        let block = encoder.encode_block(headers);
+
+        let mut decoder = HpackDecoder::default();
+        let decoded = decoder.decode_block(&block, None).unwrap();
+        assert_eq!(decoded.len(), headers.len());
+        for ((name, value), (expected_name, expected_value)) in decoded.iter().zip(headers) {
+            assert_eq!(name, expected_name);
+            assert_eq!(value, expected_value);
+        }
+        // Literals without indexing leave the dynamic table untouched.
+        assert!(decoder.is_empty());
+    }
+
+    #[test]
+    fn decode_into_borrows_the_scratch_buffer() {
+        // The RFC 7541 C.2.1 literal plus an indexed static entry: both
+        // shapes must land in the same scratch buffer.
+        let mut block = vec![0x40, 0x0a];
+        block.extend_from_slice(b"custom-key");
+        block.push(0x0d);
+        block.extend_from_slice(b"custom-header");
+        block.push(0x82);
+
+        let mut decoder = HpackDecoder::default();
+        let view = decoder.decode_into(&block, None).unwrap();
+        assert_eq!(view.len(), 2);
+        assert_eq!(
+            view.get(0),
+            Some((b"custom-key".as_slice(), b"custom-header".as_slice()))
+        );
+        assert_eq!(view.get(1), Some((b":method".as_slice(), b"GET".as_slice())));
+        assert_eq!(view.iter().count(), 2);
+        // The incremental-indexing literal still reached the table.
+        assert_eq!(decoder.len(), 1);
+    }
+
+    #[test]
+    fn repeat_decodes_reuse_the_scratch_allocation() {
+        let headers: Vec<(Vec<u8>, Vec<u8>)> = (0..20)
+            .map(|i| {
+                (
+                    format!("x-header-{i}").into_bytes(),
+                    format!("value-number-{i}").into_bytes(),
+                )
+            })
+            .collect();
+        let mut encoder = HpackEncoder::new();
+        let block = encoder
+            .encode_block(headers.iter().map(|(n, v)| (n.as_slice(), v.as_slice())));
+
+        // The first decode sizes the scratch; every later one must fit in
+        // the same allocation — zero allocator traffic in steady state.
+        let mut decoder = HpackDecoder::default();
+        decoder.decode_into(&block, None).unwrap();
+        let scratch_capacity = decoder.scratch.capacity();
+        let span_capacity = decoder.spans.capacity();
+        for _ in 0..3 {
+            let view = decoder.decode_into(&block, None).unwrap();
+            assert_eq!(view.len(), 20);
+            assert_eq!(view.get(7), Some((b"x-header-7".as_slice(), b"value-number-7".as_slice())));
+        }
+        assert_eq!(decoder.scratch.capacity(), scratch_capacity);
+        assert_eq!(decoder.spans.capacity(), span_capacity);
+    }
+}
@@ -0,0 +1,2145 @@
+//! HTTP/1.x request parsing.
+
+use crate::simd::{
+    SimdCrlfFinder, SimdDelimiterFinder, SimdHexParser, SimdTokenValidator, SimdWhitespaceSkipper,
+};
+use std::borrow::Cow;
+use std::fmt;
+
+/// An HTTP request method.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Method {
+    Get,
+    Head,
+    Post,
+    Put,
+    Delete,
+    Connect,
+    Options,
+    Trace,
+    Patch,
+}
+
+impl Method {
+    /// Parses a method from its uppercase wire form.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        match bytes {
+            b"GET" => Some(Method::Get),
+            b"HEAD" => Some(Method::Head),
+            b"POST" => Some(Method::Post),
+            b"PUT" => Some(Method::Put),
+            b"DELETE" => Some(Method::Delete),
+            b"CONNECT" => Some(Method::Connect),
+            b"OPTIONS" => Some(Method::Options),
+            b"TRACE" => Some(Method::Trace),
+            b"PATCH" => Some(Method::Patch),
+            _ => None,
+        }
+    }
+
+    /// Returns the uppercase wire form of the method.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Method::Get => "GET",
+            Method::Head => "HEAD",
+            Method::Post => "POST",
+            Method::Put => "PUT",
+            Method::Delete => "DELETE",
+            Method::Connect => "CONNECT",
+            Method::Options => "OPTIONS",
+            Method::Trace => "TRACE",
+            Method::Patch => "PATCH",
+        }
+    }
+}
+
+/// An HTTP protocol version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Version {
+    Http10,
+    Http11,
+}
+
+impl Version {
+    /// Returns the wire form of the version.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Version::Http10 => "HTTP/1.0",
+            Version::Http11 => "HTTP/1.1",
+        }
+    }
+}
+
+/// A decoded message body, the trailer fields that followed it, and the
+/// total bytes the message consumed.
+type BodyAndTrailers<'a> = (Cow<'a, [u8]>, Vec<Header<'a>>, usize);
+
+/// A single header field, borrowed from the request buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Header<'a> {
+    pub name: &'a str,
+    pub value: &'a str,
+}
+
+/// A parsed `Authorization` header credential (RFC 7235).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthScheme {
+    /// `Basic` credentials, base64-decoded and split on the first `:`.
+    Basic { username: String, password: String },
+    /// A `Bearer` token, carried verbatim.
+    Bearer { token: String },
+    /// Any other scheme, with its parameters unparsed.
+    Other { scheme: String, params: String },
+}
+
+/// A parsed HTTP/1.x request, borrowing from the input buffer where possible.
+#[derive(Debug)]
+pub struct Request<'a> {
+    pub method: Method,
+    /// The request target exactly as it appeared on the request line.
+    pub target: &'a str,
+    pub version: Version,
+    pub headers: Vec<Header<'a>>,
+    pub body: Cow<'a, [u8]>,
+    /// Trailer fields from the chunked body's trailer section (RFC 7230
+    /// §4.1.2); empty for any other framing.
+    pub trailers: Vec<Header<'a>>,
+}
+
+impl<'a> Request<'a> {
+    /// Returns the value of the first header with the given name,
+    /// compared case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&'a str> {
+        self.headers
+            .iter()
+            .find(|h| h.name.eq_ignore_ascii_case(name))
+            .map(|h| h.value)
+    }
+
+    /// Returns the value of the first trailer field with the given name,
+    /// compared case-insensitively.
+    pub fn trailer(&self, name: &str) -> Option<&'a str> {
+        self.trailers
+            .iter()
+            .find(|h| h.name.eq_ignore_ascii_case(name))
+            .map(|h| h.value)
+    }
+
+    /// Returns the path portion of the request target, excluding any query.
+    pub fn path(&self) -> &'a str {
+        match self.target.find('?') {
+            Some(idx) => &self.target[..idx],
+            None => self.target,
+        }
+    }
+
+    /// Parses the `Authorization` header into its scheme and credentials,
+    /// matching the scheme case-insensitively. Returns `None` when the
+    /// header is absent or its `Basic` credentials are malformed (bad
+    /// base64, non-UTF-8, or no `:` separator).
+    pub fn authorization(&self) -> Option<AuthScheme> {
+        let value = self.header("Authorization")?.trim();
+        let (scheme, params) = value.split_once(' ').unwrap_or((value, ""));
+        let params = params.trim_start();
+        if scheme.eq_ignore_ascii_case("Basic") {
+            let decoded = crate::base64::decode(params.as_bytes()).ok()?;
+            let credentials = String::from_utf8(decoded).ok()?;
+            let (username, password) = credentials.split_once(':')?;
+            Some(AuthScheme::Basic {
+                username: username.to_owned(),
+                password: password.to_owned(),
+            })
+        } else if scheme.eq_ignore_ascii_case("Bearer") {
+            Some(AuthScheme::Bearer {
+                token: params.to_owned(),
+            })
+        } else {
+            Some(AuthScheme::Other {
+                scheme: scheme.to_owned(),
+                params: params.to_owned(),
+            })
+        }
+    }
+
+    /// Returns the value of the `Host` header, if the request carries one.
+    pub fn host(&self) -> Option<&'a str> {
+        self.header("Host")
+    }
+
+    /// Validates the request's `Host` header per RFC 7230 §5.4: HTTP/1.1
+    /// requires exactly one, and an absolute-form target's authority must
+    /// agree with it (compared case-insensitively). Violations are
+    /// [`Http1ParseError::MalformedRequest`].
+    pub fn validate_host(&self) -> Result<(), Http1ParseError> {
+        let hosts = self
+            .headers
+            .iter()
+            .filter(|h| h.name.eq_ignore_ascii_case("Host"))
+            .count();
+        if hosts > 1 || (hosts == 0 && self.version == Version::Http11) {
+            return Err(Http1ParseError::MalformedRequest);
+        }
+        let authority = ["http://", "https://"]
+            .iter()
+            .find_map(|scheme| self.target.strip_prefix(scheme))
+            .map(|rest| rest.split(['/', '?', '#']).next().unwrap_or(rest));
+        if let Some(authority) = authority {
+            match self.host() {
+                Some(host) if host.trim().eq_ignore_ascii_case(authority) => {}
+                _ => return Err(Http1ParseError::MalformedRequest),
+            }
+        }
+        Ok(())
+    }
+
+    /// Iterates the name/value pairs of the `Cookie` header (RFC 6265
+    /// §5.4), or nothing when the request carries none.
+    ///
+    /// Values are yielded as they appear on the wire — cookie values are
+    /// opaque octets, so no decoding is applied. A pair without an `=` is
+    /// skipped.
+    pub fn cookies(&self) -> impl Iterator<Item = (&'a str, &'a str)> {
+        parse_cookies(self.header("Cookie"))
+    }
+}
+
+/// Builds the absolute-form target a proxy forwards upstream (RFC 7230
+/// §5.3.2): the scheme, the request's `Host`, and its origin-form target.
+///
+/// The asterisk form and a target that is already absolute pass through
+/// unchanged, and the scheme's default port is dropped from the
+/// authority, so equivalent requests produce one canonical target.
+pub fn to_absolute_form(request: &Request<'_>, scheme: &str) -> String {
+    if request.target == "*" || request.target.contains("://") {
+        return request.target.to_owned();
+    }
+    let authority = strip_default_port(request.header("Host").unwrap_or_default(), scheme);
+    format!("{scheme}://{authority}{}", request.target)
+}
+
+/// Splits an absolute-form target into its authority and origin-form
+/// path, the shape a request line takes once it reaches the origin
+/// server (RFC 7230 §5.3.1).
+///
+/// The scheme's default port is dropped from the authority and an absent
+/// path becomes `/`. The asterisk form yields an empty authority, and a
+/// target that is already origin-form passes through with one.
+pub fn to_origin_form(uri: &str) -> (&str, &str) {
+    let Some(scheme_end) = uri.find("://") else {
+        return ("", uri);
+    };
+    let (scheme, rest) = (&uri[..scheme_end], &uri[scheme_end + 3..]);
+    let (authority, path) = match rest.find('/') {
+        Some(slash) => (&rest[..slash], &rest[slash..]),
+        None => (rest, "/"),
+    };
+    (strip_default_port(authority, scheme), path)
+}
+
+/// Drops the scheme's default port from an authority, leaving any other
+/// port in place.
+fn strip_default_port<'a>(authority: &'a str, scheme: &str) -> &'a str {
+    let default = match scheme {
+        "http" => ":80",
+        "https" => ":443",
+        _ => return authority,
+    };
+    authority.strip_suffix(default).unwrap_or(authority)
+}
+
+/// Splits a `Cookie` header value into trimmed name/value pairs.
+fn parse_cookies(header: Option<&str>) -> impl Iterator<Item = (&str, &str)> {
+    header
+        .unwrap_or_default()
+        .split(';')
+        .filter_map(|pair| pair.trim().split_once('='))
+        .filter(|(name, _)| !name.is_empty())
+}
+
+/// A lazy view over a request's header block.
+///
+/// Nothing is materialized up front: each lookup or iteration step scans
+/// the borrowed header region line by line, so a handler that reads one
+/// or two headers never pays for a `Vec` of all of them. Line grammar is
+/// validated as lines are visited, which is why iteration yields
+/// `Result`.
+#[derive(Debug, Clone, Copy)]
+pub struct HeadersView<'p, 'a> {
+    parser: &'p Http1Parser,
+    /// The header lines, each with its CRLF, excluding the empty line.
+    block: &'a [u8],
+}
+
+impl<'p, 'a> HeadersView<'p, 'a> {
+    /// Returns the value of the first header with the given name, compared
+    /// case-insensitively. Scanning stops at a malformed line, so a name
+    /// behind one is reported as absent.
+    pub fn get(&self, name: &str) -> Option<&'a str> {
+        self.iter()
+            .map_while(Result::ok)
+            .find(|h| h.name.eq_ignore_ascii_case(name))
+            .map(|h| h.value)
+    }
+
+    /// Iterates the header fields in order, parsing each line on demand.
+    pub fn iter(&self) -> HeadersViewIter<'p, 'a> {
+        HeadersViewIter {
+            parser: self.parser,
+            block: self.block,
+            cursor: 0,
+        }
+    }
+}
+
+/// The iterator over a [`HeadersView`]; see [`HeadersView::iter`].
+#[derive(Debug)]
+pub struct HeadersViewIter<'p, 'a> {
+    parser: &'p Http1Parser,
+    block: &'a [u8],
+    cursor: usize,
+}
+
+impl<'a> Iterator for HeadersViewIter<'_, 'a> {
+    type Item = Result<Header<'a>, Http1ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.cursor >= self.block.len() {
+            return None;
+        }
+        // The block was delimited by a full scan, so every line has its
+        // CRLF.
+        let line_len = self
+            .parser
+            .crlf_finder
+            .find_crlf(&self.block[self.cursor..])
+            .expect("scanned block contains whole lines");
+        let line = &self.block[self.cursor..self.cursor + line_len];
+        self.cursor += line_len + 2;
+        Some(self.parser.parse_header(line))
+    }
+}
+
+/// A parsed request whose headers stay unmaterialized; see
+/// [`Http1Parser::parse_request_borrowed`].
+#[derive(Debug)]
+pub struct RequestView<'p, 'a> {
+    pub method: Method,
+    /// The request target exactly as it appeared on the request line.
+    pub target: &'a str,
+    pub version: Version,
+    pub headers: HeadersView<'p, 'a>,
+    pub body: Cow<'a, [u8]>,
+    /// Trailer fields from the chunked body's trailer section; empty for
+    /// any other framing.
+    pub trailers: Vec<Header<'a>>,
+}
+
+impl<'a> RequestView<'_, 'a> {
+    /// Returns the value of the first header with the given name,
+    /// compared case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&'a str> {
+        self.headers.get(name)
+    }
+
+    /// Returns the path portion of the request target, excluding any query.
+    pub fn path(&self) -> &'a str {
+        match self.target.find('?') {
+            Some(idx) => &self.target[..idx],
+            None => self.target,
+        }
+    }
+}
+
+/// Errors produced while parsing an HTTP/1.x request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Http1ParseError {
+    /// The buffer does not yet hold a complete request; read more and retry.
+    IncompleteRequest,
+    /// The request violates the HTTP/1.x grammar.
+    MalformedRequest,
+    InvalidMethod,
+    InvalidUri,
+    /// The request line outgrew the configured limit before its CRLF.
+    UriTooLong,
+    InvalidVersion,
+    InvalidHeaderName,
+    InvalidHeaderValue,
+    TooManyHeaders,
+    RequestTooLarge,
+    TooManyChunks,
+    InvalidContentLength,
+    InvalidChunkSize,
+    /// A trailer carried a field that can only appear in the header
+    /// section (`Transfer-Encoding`, `Content-Length`, `Host`).
+    ForbiddenTrailer,
+    /// `Transfer-Encoding` named a coding the server cannot decode.
+    UnsupportedTransferEncoding,
+}
+
+impl fmt::Display for Http1ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match self {
+            Http1ParseError::IncompleteRequest => "incomplete request",
+            Http1ParseError::MalformedRequest => "malformed request",
+            Http1ParseError::InvalidMethod => "invalid method",
+            Http1ParseError::InvalidUri => "invalid request target",
+            Http1ParseError::UriTooLong => "request target too long",
+            Http1ParseError::InvalidVersion => "invalid HTTP version",
+            Http1ParseError::InvalidHeaderName => "invalid header name",
+            Http1ParseError::InvalidHeaderValue => "invalid header value",
+            Http1ParseError::TooManyHeaders => "too many headers",
+            Http1ParseError::RequestTooLarge => "request too large",
+            Http1ParseError::TooManyChunks => "too many chunks",
+            Http1ParseError::InvalidContentLength => "invalid Content-Length",
+            Http1ParseError::InvalidChunkSize => "invalid chunk size",
+            Http1ParseError::ForbiddenTrailer => "forbidden field in trailer section",
+            Http1ParseError::UnsupportedTransferEncoding => "unsupported transfer coding",
+        };
+        f.write_str(msg)
+    }
+}
+
+impl std::error::Error for Http1ParseError {}
+
+/// A reusable HTTP/1.x request parser.
+///
+/// The parser holds no per-request state; it borrows all output from the
+/// input buffer, copying only when chunked bodies must be reassembled.
+/// Every parse method takes `&self`, so one parser — whose construction
+/// builds several SIMD lookup tables — can be shared across threads
+/// behind an [`std::sync::Arc`] instead of being rebuilt per connection.
+#[derive(Debug, Clone)]
+pub struct Http1Parser {
+    max_headers: usize,
+    max_header_size: usize,
+    max_request_line: usize,
+    max_request_size: usize,
+    max_chunk_size: usize,
+    max_chunk_count: usize,
+    allow_obs_text: bool,
+    crlf_finder: SimdCrlfFinder,
+    space_finder: SimdDelimiterFinder,
+    colon_finder: SimdDelimiterFinder,
+    whitespace_skipper: SimdWhitespaceSkipper,
+    token_validator: SimdTokenValidator,
+    hex_parser: SimdHexParser,
+}
+
+/// Configures the limits of an [`Http1Parser`].
+///
+/// Every limit starts at the parser's default and must be non-zero; an
+/// edge proxy can raise them for large uploads, a constrained gateway can
+/// lower them.
+#[derive(Debug, Clone)]
+pub struct Http1ParserBuilder {
+    max_headers: usize,
+    max_header_size: usize,
+    max_request_line: usize,
+    max_request_size: usize,
+    max_chunk_size: usize,
+    max_chunk_count: usize,
+    allow_obs_text: bool,
+}
+
+impl Default for Http1ParserBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Http1ParserBuilder {
+    pub fn new() -> Self {
+        Self {
+            max_headers: 100,
+            max_header_size: 8192,
+            max_request_line: 8192,
+            max_request_size: 1024 * 1024,
+            max_chunk_size: 1024 * 1024,
+            max_chunk_count: 16 * 1024,
+            allow_obs_text: false,
+        }
+    }
+
+    /// Caps the number of header fields per request.
+    ///
+    /// # Panics
+    ///
+    /// Panics when `value` is zero.
+    pub fn max_headers(mut self, value: usize) -> Self {
+        assert!(value > 0, "max_headers must be non-zero");
+        self.max_headers = value;
+        self
+    }
+
+    /// Caps the cumulative size of the header block in bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics when `value` is zero.
+    pub fn max_header_size(mut self, value: usize) -> Self {
+        assert!(value > 0, "max_header_size must be non-zero");
+        self.max_header_size = value;
+        self
+    }
+
+    /// Caps the request line — method, target and version — in bytes,
+    /// excluding its CRLF. Since the target dominates the line, this is
+    /// effectively the URI length limit.
+    ///
+    /// # Panics
+    ///
+    /// Panics when `value` is zero.
+    pub fn max_request_line(mut self, value: usize) -> Self {
+        assert!(value > 0, "max_request_line must be non-zero");
+        self.max_request_line = value;
+        self
+    }
+
+    /// Caps the body size in bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics when `value` is zero.
+    pub fn max_request_size(mut self, value: usize) -> Self {
+        assert!(value > 0, "max_request_size must be non-zero");
+        self.max_request_size = value;
+        self
+    }
+
+    /// Caps the declared size of a single chunk in a chunked body.
+    ///
+    /// # Panics
+    ///
+    /// Panics when `value` is zero.
+    pub fn max_chunk_size(mut self, value: usize) -> Self {
+        assert!(value > 0, "max_chunk_size must be non-zero");
+        self.max_chunk_size = value;
+        self
+    }
+
+    /// Caps the number of chunks in a chunked body.
+    ///
+    /// # Panics
+    ///
+    /// Panics when `value` is zero.
+    pub fn max_chunk_count(mut self, value: usize) -> Self {
+        assert!(value > 0, "max_chunk_count must be non-zero");
+        self.max_chunk_count = value;
+        self
+    }
+
+    /// Accepts obs-text bytes (0x80–0xFF) in header values, which RFC 7230
+    /// §3.2.6 permits only as a historical concession. Off by default.
+    pub fn allow_obs_text(mut self) -> Self {
+        self.allow_obs_text = true;
+        self
+    }
+
+    pub fn build(self) -> Http1Parser {
+        Http1Parser {
+            max_headers: self.max_headers,
+            max_header_size: self.max_header_size,
+            max_request_line: self.max_request_line,
+            max_request_size: self.max_request_size,
+            max_chunk_size: self.max_chunk_size,
+            max_chunk_count: self.max_chunk_count,
+            allow_obs_text: self.allow_obs_text,
+            crlf_finder: SimdCrlfFinder::new(),
+            space_finder: SimdDelimiterFinder::new(b' '),
+            colon_finder: SimdDelimiterFinder::new(b':'),
+            whitespace_skipper: SimdWhitespaceSkipper::new(),
+            token_validator: SimdTokenValidator::new(),
+            hex_parser: SimdHexParser::new(),
+        }
+    }
+}
+
+impl Default for Http1Parser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Http1Parser {
+    /// A parser with the default limits; see [`Http1Parser::builder`] to
+    /// change them.
+    pub fn new() -> Self {
+        Http1ParserBuilder::new().build()
+    }
+
+    /// Starts configuring a parser with non-default limits.
+    pub fn builder() -> Http1ParserBuilder {
+        Http1ParserBuilder::new()
+    }
+
+    /// The maximum accepted number of header fields.
+    pub fn max_headers(&self) -> usize {
+        self.max_headers
+    }
+
+    /// The maximum accepted size of the header section in bytes.
+    pub fn max_header_size(&self) -> usize {
+        self.max_header_size
+    }
+
+    /// The maximum accepted request-line length in bytes.
+    pub fn max_request_line(&self) -> usize {
+        self.max_request_line
+    }
+
+    /// The maximum accepted body size in bytes.
+    pub fn max_request_size(&self) -> usize {
+        self.max_request_size
+    }
+
+    /// Parses one complete request from the front of `input`, returning the
+    /// request and the number of bytes it consumed.
+    ///
+    /// Returns [`Http1ParseError::IncompleteRequest`] when the buffer does
+    /// not yet hold the whole request.
+    pub fn parse_request<'a>(
+        &self,
+        input: &'a [u8],
+    ) -> Result<(Request<'a>, usize), Http1ParseError> {
+        let (mut request, header_end) = self.parse_head(input)?;
+        let (body, trailers, consumed) = self.extract_body(input, header_end, &request)?;
+        request.body = body;
+        request.trailers = trailers;
+        Ok((request, consumed))
+    }
+
+    /// Parses one complete request without materializing its headers: the
+    /// returned view scans the header region lazily, so handlers that
+    /// read only a header or two skip the `Vec` the eager
+    /// [`Http1Parser::parse_request`] builds.
+    ///
+    /// The header block is still delimited and bounded up front; only the
+    /// per-line grammar is deferred to the moment a line is visited.
+    pub fn parse_request_borrowed<'a>(
+        &self,
+        input: &'a [u8],
+    ) -> Result<(RequestView<'_, 'a>, usize), Http1ParseError> {
+        let (method, target, version, line_end) = self.parse_request_line(input)?;
+        let header_end = self.scan_header_block(input, line_end)?;
+        let headers = HeadersView {
+            parser: self,
+            block: &input[line_end..header_end - 2],
+        };
+        let (body, trailers, consumed) = if let Some(te) = headers.get("Transfer-Encoding") {
+            Self::validate_transfer_encoding(te)?;
+            self.extract_chunked_body(input, header_end)?
+        } else if let Some(value) = headers.get("Content-Length") {
+            let length = parse_content_length(value)?;
+            if length > self.max_request_size {
+                return Err(Http1ParseError::RequestTooLarge);
+            }
+            if input.len() < header_end + length {
+                return Err(Http1ParseError::IncompleteRequest);
+            }
+            (
+                Cow::Borrowed(&input[header_end..header_end + length]),
+                Vec::new(),
+                header_end + length,
+            )
+        } else {
+            (Cow::Borrowed(&[][..]), Vec::new(), header_end)
+        };
+        Ok((
+            RequestView {
+                method,
+                target,
+                version,
+                headers,
+                body,
+                trailers,
+            },
+            consumed,
+        ))
+    }
+
+    /// Delimits the header block starting at `offset` without parsing its
+    /// lines, returning the offset one past the empty line. The same
+    /// size and count limits apply as in [`Http1Parser::parse_headers`].
+    fn scan_header_block(&self, input: &[u8], offset: usize) -> Result<usize, Http1ParseError> {
+        let mut cursor = offset;
+        let mut count = 0;
+        loop {
+            let line_len = match self.crlf_finder.find_crlf(&input[cursor..]) {
+                Some(len) => len,
+                None => {
+                    if input.len() - cursor > self.max_header_size {
+                        return Err(Http1ParseError::RequestTooLarge);
+                    }
+                    return Err(Http1ParseError::IncompleteRequest);
+                }
+            };
+            if cursor + line_len + 2 - offset > self.max_header_size {
+                return Err(Http1ParseError::RequestTooLarge);
+            }
+            if line_len == 0 {
+                return Ok(cursor + 2);
+            }
+            if count >= self.max_headers {
+                return Err(Http1ParseError::TooManyHeaders);
+            }
+            count += 1;
+            cursor += line_len + 2;
+        }
+    }
+
+    /// Parses the request line and headers only, returning a request with an
+    /// empty body and the offset one past the terminating empty line.
+    ///
+    /// This lets the connection layer inspect headers (e.g. `Expect`) before
+    /// the body has arrived.
+    pub fn parse_head<'a>(
+        &self,
+        input: &'a [u8],
+    ) -> Result<(Request<'a>, usize), Http1ParseError> {
+        let (method, target, version, line_end) = self.parse_request_line(input)?;
+        let (headers, header_end) = self.parse_headers(input, line_end)?;
+        Ok((
+            Request {
+                method,
+                target,
+                version,
+                headers,
+                body: Cow::Borrowed(&[]),
+                trailers: Vec::new(),
+            },
+            header_end,
+        ))
+    }
+
+    /// Parses `METHOD SP target SP HTTP-version CRLF`, returning the offset
+    /// one past the CRLF.
+    fn parse_request_line<'a>(
+        &self,
+        input: &'a [u8],
+    ) -> Result<(Method, &'a str, Version, usize), Http1ParseError> {
+        // Bound the CRLF search so an endless request target is rejected
+        // after `max_request_line` bytes instead of being scanned whole.
+        let window = &input[..input.len().min(self.max_request_line + 2)];
+        let line_len = match self.crlf_finder.find_crlf(window) {
+            Some(len) => len,
+            None if input.len() > self.max_request_line + 1 => {
+                return Err(Http1ParseError::UriTooLong);
+            }
+            None => return Err(Http1ParseError::IncompleteRequest),
+        };
+        let line = &input[..line_len];
+
+        let method_end = self
+            .space_finder
+            .find_in(line)
+            .ok_or(Http1ParseError::MalformedRequest)?;
+        let method =
+            Method::from_bytes(&line[..method_end]).ok_or(Http1ParseError::InvalidMethod)?;
+
+        let rest = &line[method_end + 1..];
+        let target_end = self
+            .space_finder
+            .find_in(rest)
+            .ok_or(Http1ParseError::MalformedRequest)?;
+        if target_end == 0 {
+            return Err(Http1ParseError::InvalidUri);
+        }
+        let target =
+            std::str::from_utf8(&rest[..target_end]).map_err(|_| Http1ParseError::InvalidUri)?;
+
+        let version = match &rest[target_end + 1..] {
+            b"HTTP/1.1" => Version::Http11,
+            b"HTTP/1.0" => Version::Http10,
+            _ => return Err(Http1ParseError::InvalidVersion),
+        };
+
+        Ok((method, target, version, line_len + 2))
+    }
+
+    /// Parses header lines starting at `offset` until the empty line,
+    /// returning the headers and the offset one past the empty line.
+    ///
+    /// The cumulative size of the header block — and therefore also any
+    /// single line — is bounded by `max_header_size`, so a client cannot
+    /// grow memory with many individually modest headers or one endless
+    /// line that never reaches its CRLF.
+    fn parse_headers<'a>(
+        &self,
+        input: &'a [u8],
+        offset: usize,
+    ) -> Result<(Vec<Header<'a>>, usize), Http1ParseError> {
+        let mut headers = Vec::with_capacity(32);
+        let mut cursor = offset;
+        loop {
+            let line_len = match self.crlf_finder.find_crlf(&input[cursor..]) {
+                Some(len) => len,
+                None => {
+                    // No terminator yet; reject rather than buffer forever
+                    // once the unterminated tail alone exceeds the limit.
+                    if input.len() - cursor > self.max_header_size {
+                        return Err(Http1ParseError::RequestTooLarge);
+                    }
+                    return Err(Http1ParseError::IncompleteRequest);
+                }
+            };
+            if cursor + line_len + 2 - offset > self.max_header_size {
+                return Err(Http1ParseError::RequestTooLarge);
+            }
+            if line_len == 0 {
+                return Ok((headers, cursor + 2));
+            }
+            if headers.len() >= self.max_headers {
+                return Err(Http1ParseError::TooManyHeaders);
+            }
+            headers.push(self.parse_header(&input[cursor..cursor + line_len])?);
+            cursor += line_len + 2;
+        }
+    }
+
+    /// Parses a single `name: value` header line, trimming optional
+    /// whitespace around the value.
+    fn parse_header<'a>(&self, line: &'a [u8]) -> Result<Header<'a>, Http1ParseError> {
+        let colon = self
+            .colon_finder
+            .find_in(line)
+            .ok_or(Http1ParseError::MalformedRequest)?;
+        let name_bytes = &line[..colon];
+        if !self.token_validator.is_valid_token(name_bytes) {
+            return Err(Http1ParseError::InvalidHeaderName);
+        }
+        // SAFETY-adjacent: every tchar is ASCII, so the name is valid UTF-8.
+        let name = std::str::from_utf8(name_bytes).map_err(|_| Http1ParseError::InvalidHeaderName)?;
+
+        let raw_value = &line[colon + 1..];
+        let start = self.whitespace_skipper.skip(raw_value);
+        let value_bytes = self.whitespace_skipper.trim_end(&raw_value[start..]);
+        // Only field-vchar plus internal SP/HTAB may appear in a value
+        // (RFC 7230 §3.2); an embedded NUL, CR, or other control byte is a
+        // header-injection vector, not data.
+        if !value_bytes.iter().all(|&b| self.is_field_byte(b)) {
+            return Err(Http1ParseError::InvalidHeaderValue);
+        }
+        let value = std::str::from_utf8(value_bytes)
+            .map_err(|_| Http1ParseError::InvalidHeaderValue)?;
+
+        Ok(Header { name, value })
+    }
+
+    /// Whether a byte may appear inside a header value: visible ASCII,
+    /// SP, HTAB, and — when the parser was built with
+    /// [`Http1ParserBuilder::allow_obs_text`] — obs-text (0x80–0xFF).
+    fn is_field_byte(&self, b: u8) -> bool {
+        matches!(b, 0x21..=0x7e | b' ' | b'\t') || (self.allow_obs_text && b >= 0x80)
+    }
+
+    /// Validates a `Transfer-Encoding` list per RFC 7230 §3.3.1: `chunked`
+    /// must appear exactly once, as the final coding, and no coding the
+    /// server cannot decode may precede it.
+    fn validate_transfer_encoding(value: &str) -> Result<(), Http1ParseError> {
+        let codings: Vec<&str> = value.split(',').map(str::trim).collect();
+        let last = codings.len() - 1;
+        for (index, coding) in codings.iter().enumerate() {
+            if coding.eq_ignore_ascii_case("chunked") {
+                // Repeated, or followed by another coding: the message
+                // length would be ambiguous.
+                if index != last {
+                    return Err(Http1ParseError::MalformedRequest);
+                }
+            } else if coding.is_empty() {
+                return Err(Http1ParseError::MalformedRequest);
+            } else {
+                return Err(Http1ParseError::UnsupportedTransferEncoding);
+            }
+        }
+        Ok(())
+    }
+
+    /// Extracts the message body according to `Transfer-Encoding` and
+    /// `Content-Length`, returning the body and the total bytes consumed.
+    fn extract_body<'a>(
+        &self,
+        input: &'a [u8],
+        header_end: usize,
+        request: &Request<'a>,
+    ) -> Result<BodyAndTrailers<'a>, Http1ParseError> {
+        if let Some(te) = request.header("Transfer-Encoding") {
+            Self::validate_transfer_encoding(te)?;
+            return self.extract_chunked_body(input, header_end);
+        }
+        if let Some(value) = request.header("Content-Length") {
+            let length = parse_content_length(value)?;
+            if length > self.max_request_size {
+                return Err(Http1ParseError::RequestTooLarge);
+            }
+            if input.len() < header_end + length {
+                return Err(Http1ParseError::IncompleteRequest);
+            }
+            let body = &input[header_end..header_end + length];
+            return Ok((Cow::Borrowed(body), Vec::new(), header_end + length));
+        }
+        Ok((Cow::Borrowed(&[]), Vec::new(), header_end))
+    }
+
+    /// Decodes a chunked body (RFC 7230 §4.1) and parses the trailer
+    /// section into headers. A body of exactly one chunk — the common case
+    /// for clients that know the length but frame with chunked anyway — is
+    /// borrowed straight out of the input; only multiple chunks are
+    /// reassembled into an owned buffer.
+    fn extract_chunked_body<'a>(
+        &self,
+        input: &'a [u8],
+        header_end: usize,
+    ) -> Result<BodyAndTrailers<'a>, Http1ParseError> {
+        // First pass: locate every chunk and compute the total size.
+        let mut chunks = Vec::new();
+        let trailers;
+        let mut total = 0usize;
+        let mut cursor = header_end;
+        loop {
+            let line_len = self
+                .crlf_finder
+                .find_crlf(&input[cursor..])
+                .ok_or(Http1ParseError::IncompleteRequest)?;
+            let (size, _ext) = self.parse_chunk_size(&input[cursor..cursor + line_len])?;
+            // Reject the declared size before waiting for (or buffering)
+            // the data, so an absurd chunk fails immediately.
+            if size > self.max_chunk_size {
+                return Err(Http1ParseError::RequestTooLarge);
+            }
+            if chunks.len() >= self.max_chunk_count {
+                return Err(Http1ParseError::TooManyChunks);
+            }
+            cursor += line_len + 2;
+            if size == 0 {
+                let (parsed_trailers, end) = self.parse_trailer_headers(input, cursor)?;
+                trailers = parsed_trailers;
+                cursor = end;
+                break;
+            }
+            if input.len() < cursor + size + 2 {
+                return Err(Http1ParseError::IncompleteRequest);
+            }
+            if &input[cursor + size..cursor + size + 2] != b"\r\n" {
+                return Err(Http1ParseError::MalformedRequest);
+            }
+            total = total
+                .checked_add(size)
+                .ok_or(Http1ParseError::RequestTooLarge)?;
+            if total > self.max_request_size {
+                return Err(Http1ParseError::RequestTooLarge);
+            }
+            chunks.push((cursor, size));
+            cursor += size + 2;
+        }
+
+        let body = match chunks.as_slice() {
+            // Single chunk: the data is already contiguous in the input.
+            &[(start, size)] => Cow::Borrowed(&input[start..start + size]),
+            // Second pass: copy the chunk data into one contiguous buffer.
+            _ => {
+                let mut body = Vec::with_capacity(total);
+                for (start, size) in chunks {
+                    body.extend_from_slice(&input[start..start + size]);
+                }
+                Cow::Owned(body)
+            }
+        };
+        Ok((body, trailers, cursor))
+    }
+
+    /// Parses a chunk-size line, returning the size and any chunk extension.
+    fn parse_chunk_size<'a>(
+        &self,
+        line: &'a [u8],
+    ) -> Result<(usize, Option<&'a [u8]>), Http1ParseError> {
+        let (value, consumed) = self
+            .hex_parser
+            .parse_hex(line)
+            .ok_or(Http1ParseError::InvalidChunkSize)?;
+        let size = usize::try_from(value).map_err(|_| Http1ParseError::InvalidChunkSize)?;
+        let rest = &line[consumed..];
+        let rest = &rest[self.whitespace_skipper.skip(rest)..];
+        match rest.first() {
+            None => Ok((size, None)),
+            Some(b';') => Ok((size, Some(&rest[1..]))),
+            Some(_) => Err(Http1ParseError::InvalidChunkSize),
+        }
+    }
+
+    /// Parses the trailer section after the last chunk, returning the
+    /// trailer fields and the offset one past the final empty line.
+    ///
+    /// Fields that govern message framing or routing — and so must not be
+    /// deferred to a trailer (RFC 7230 §4.1.2) — are rejected.
+    fn parse_trailer_headers<'a>(
+        &self,
+        input: &'a [u8],
+        mut cursor: usize,
+    ) -> Result<(Vec<Header<'a>>, usize), Http1ParseError> {
+        const FORBIDDEN: [&str; 3] = ["Transfer-Encoding", "Content-Length", "Host"];
+        let mut trailers = Vec::new();
+        loop {
+            let line_len = self
+                .crlf_finder
+                .find_crlf(&input[cursor..])
+                .ok_or(Http1ParseError::IncompleteRequest)?;
+            if line_len == 0 {
+                return Ok((trailers, cursor + 2));
+            }
+            if trailers.len() >= self.max_headers {
+                return Err(Http1ParseError::TooManyHeaders);
+            }
+            let trailer = self.parse_header(&input[cursor..cursor + line_len])?;
+            if FORBIDDEN.iter().any(|f| trailer.name.eq_ignore_ascii_case(f)) {
+                return Err(Http1ParseError::ForbiddenTrailer);
+            }
+            trailers.push(trailer);
+            cursor += line_len + 2;
+        }
+    }
+}
+
+/// Parses a `Content-Length` value as `1*DIGIT` (RFC 9110 §8.6): ASCII
+/// decimal digits only. Signs, hex prefixes, and embedded whitespace are
+/// rejected — `str::parse` tolerates a leading `+`, which request-smuggling
+/// payloads exploit when a front proxy reads the length differently.
+fn parse_content_length(value: &str) -> Result<usize, Http1ParseError> {
+    if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(Http1ParseError::InvalidContentLength);
+    }
+    value
+        .parse()
+        .map_err(|_| Http1ParseError::InvalidContentLength)
+}
+
+/// Errors produced while parsing a `Range` header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeError {
+    /// The value violates the `bytes=` range grammar; the header should
+    /// be ignored and the full resource served (RFC 9110 §14.2).
+    Malformed,
+    /// A requested range lies entirely beyond the resource; answer 416.
+    Unsatisfiable,
+}
+
+impl fmt::Display for RangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match self {
+            RangeError::Malformed => "malformed Range header",
+            RangeError::Unsatisfiable => "unsatisfiable range",
+        };
+        f.write_str(msg)
+    }
+}
+
+impl std::error::Error for RangeError {}
+
+/// Parses a `Range: bytes=...` header against a resource of
+/// `resource_len` bytes, returning inclusive `(start, end)` offsets
+/// (RFC 9110 §14.1.2).
+///
+/// `0-499`, open-ended `500-`, and suffix `-500` forms are accepted, in
+/// any comma-separated combination; ends are clamped to the last byte. A
+/// range starting beyond the resource is unsatisfiable, letting the
+/// caller answer 416. Ranges are returned in request order, uncoalesced —
+/// overlap policy is the caller's.
+pub fn parse_range_header(value: &str, resource_len: u64) -> Result<Vec<(u64, u64)>, RangeError> {
+    let specs = value.strip_prefix("bytes=").ok_or(RangeError::Malformed)?;
+    let parse_offset = |s: &str| -> Result<u64, RangeError> {
+        if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(RangeError::Malformed);
+        }
+        s.parse().map_err(|_| RangeError::Malformed)
+    };
+
+    let mut ranges = Vec::new();
+    for spec in specs.split(',') {
+        let spec = spec.trim();
+        let (first, last) = spec.split_once('-').ok_or(RangeError::Malformed)?;
+        let range = if first.is_empty() {
+            // Suffix form: the final `last` bytes of the resource.
+            let suffix = parse_offset(last)?;
+            if suffix == 0 || resource_len == 0 {
+                return Err(RangeError::Unsatisfiable);
+            }
+            (resource_len.saturating_sub(suffix), resource_len - 1)
+        } else {
+            let start = parse_offset(first)?;
+            if start >= resource_len {
+                return Err(RangeError::Unsatisfiable);
+            }
+            let end = if last.is_empty() {
+                resource_len - 1
+            } else {
+                let end = parse_offset(last)?;
+                if end < start {
+                    return Err(RangeError::Malformed);
+                }
+                end.min(resource_len - 1)
+            };
+            (start, end)
+        };
+        ranges.push(range);
+    }
+    if ranges.is_empty() {
+        return Err(RangeError::Malformed);
+    }
+    Ok(ranges)
+}
+
+/// Returns the standard reason phrase for a status code, defaulting to an
+/// empty phrase for codes without a registered one.
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        100 => "Continue",
+        101 => "Switching Protocols",
+        200 => "OK",
+        204 => "No Content",
+        301 => "Moved Permanently",
+        304 => "Not Modified",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        411 => "Length Required",
+        413 => "Payload Too Large",
+        417 => "Expectation Failed",
+        431 => "Request Header Fields Too Large",
+        500 => "Internal Server Error",
+        501 => "Not Implemented",
+        503 => "Service Unavailable",
+        _ => "",
+    }
+}
+
+/// Appends the decimal digits of `n` to `out` without going through a
+/// formatter: digits are produced into a fixed stack buffer from the least
+/// significant end and copied in one `extend_from_slice`. `Content-Length`
+/// is written on every response, so this keeps the hot path free of
+/// `fmt::Display` machinery.
+fn write_u64(out: &mut Vec<u8>, mut n: u64) {
+    // u64::MAX has 20 decimal digits.
+    let mut digits = [0u8; 20];
+    let mut index = digits.len();
+    loop {
+        index -= 1;
+        digits[index] = b'0' + (n % 10) as u8;
+        n /= 10;
+        if n == 0 {
+            break;
+        }
+    }
+    out.extend_from_slice(&digits[index..]);
+}
+
+/// Formats a Unix timestamp as an IMF-fixdate (RFC 9110 §5.6.7), the only
+/// `Date` form a server may generate: `Sun, 06 Nov 1994 08:49:37 GMT`.
+fn imf_fixdate(unix_secs: u64) -> String {
+    const DAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let days = unix_secs / 86_400;
+    let secs_of_day = unix_secs % 86_400;
+    // 1970-01-01 was a Thursday.
+    let weekday = DAYS[((days + 4) % 7) as usize];
+
+    // Civil-from-days: shift the epoch to 0000-03-01 so leap days land at
+    // the end of the year, then decompose into 400-year eras.
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_march + 2) / 5 + 1;
+    let month = if month_march < 10 { month_march + 3 } else { month_march - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{weekday}, {day:02} {} {year} {:02}:{:02}:{:02} GMT",
+        MONTHS[(month - 1) as usize],
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60,
+    )
+}
+
+/// The `SameSite` attribute of a cookie (RFC 6265bis §5.4.7).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+impl SameSite {
+    fn as_str(&self) -> &'static str {
+        match self {
+            SameSite::Strict => "Strict",
+            SameSite::Lax => "Lax",
+            SameSite::None => "None",
+        }
+    }
+}
+
+/// A `Set-Cookie` header under construction (RFC 6265 §4.1).
+#[derive(Debug, Clone)]
+pub struct Cookie {
+    name: String,
+    value: String,
+    max_age: Option<i64>,
+    domain: Option<String>,
+    path: Option<String>,
+    secure: bool,
+    http_only: bool,
+    same_site: Option<SameSite>,
+}
+
+impl Cookie {
+    /// Starts a cookie with the given name and value.
+    ///
+    /// # Panics
+    ///
+    /// Panics when `name` is not a valid token (RFC 6265 §4.1.1): a
+    /// non-token name would let attribute delimiters leak into the header.
+    pub fn new(name: &str, value: &str) -> Self {
+        assert!(
+            SimdTokenValidator::new().is_valid_token(name.as_bytes()),
+            "cookie name must be a valid token"
+        );
+        Self {
+            name: name.to_owned(),
+            value: value.to_owned(),
+            max_age: None,
+            domain: None,
+            path: None,
+            secure: false,
+            http_only: false,
+            same_site: None,
+        }
+    }
+
+    /// Sets `Max-Age` in seconds; zero or negative expires the cookie.
+    pub fn max_age(mut self, seconds: i64) -> Self {
+        self.max_age = Some(seconds);
+        self
+    }
+
+    pub fn domain(mut self, domain: &str) -> Self {
+        self.domain = Some(domain.to_owned());
+        self
+    }
+
+    pub fn path(mut self, path: &str) -> Self {
+        self.path = Some(path.to_owned());
+        self
+    }
+
+    /// Restricts the cookie to secure transports.
+    pub fn secure(mut self) -> Self {
+        self.secure = true;
+        self
+    }
+
+    /// Hides the cookie from client-side scripts.
+    pub fn http_only(mut self) -> Self {
+        self.http_only = true;
+        self
+    }
+
+    pub fn same_site(mut self, same_site: SameSite) -> Self {
+        self.same_site = Some(same_site);
+        self
+    }
+
+    /// Serializes the cookie as a `Set-Cookie` header value, attributes in
+    /// the conventional order: `Max-Age`, `Domain`, `Path`, `Secure`,
+    /// `HttpOnly`, `SameSite`.
+    pub fn to_header_value(&self) -> String {
+        use std::fmt::Write as _;
+        let mut out = String::with_capacity(self.name.len() + self.value.len() + 32);
+        out.push_str(&self.name);
+        out.push('=');
+        out.push_str(&self.value);
+        if let Some(seconds) = self.max_age {
+            let _ = write!(out, "; Max-Age={seconds}");
+        }
+        if let Some(domain) = &self.domain {
+            let _ = write!(out, "; Domain={domain}");
+        }
+        if let Some(path) = &self.path {
+            let _ = write!(out, "; Path={path}");
+        }
+        if self.secure {
+            out.push_str("; Secure");
+        }
+        if self.http_only {
+            out.push_str("; HttpOnly");
+        }
+        if let Some(same_site) = self.same_site {
+            let _ = write!(out, "; SameSite={}", same_site.as_str());
+        }
+        out
+    }
+}
+
+/// Serializes an HTTP/1.1 response: status line, headers, then the body
+/// with a computed `Content-Length`.
+#[derive(Debug)]
+pub struct Http1ResponseBuilder {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+    /// `Content-Length` is still computed from the body, but the body
+    /// bytes themselves are omitted (HEAD responses, RFC 9110 §9.3.2).
+    omit_body: bool,
+}
+
+impl Http1ResponseBuilder {
+    pub fn new(status: u16) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: Vec::new(),
+            omit_body: false,
+        }
+    }
+
+    /// Appends a header field.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_owned(), value.to_owned()));
+        self
+    }
+
+    /// Appends a `Set-Cookie` header for the given cookie; each call adds
+    /// another cookie.
+    pub fn set_cookie(mut self, cookie: &Cookie) -> Self {
+        self.headers
+            .push(("Set-Cookie".to_owned(), cookie.to_header_value()));
+        self
+    }
+
+    /// Sets the response body; `Content-Length` is emitted automatically.
+    pub fn body(mut self, body: &[u8]) -> Self {
+        self.body = body.to_vec();
+        self
+    }
+
+    /// Adds the `Date` header every response must carry (RFC 9110
+    /// §6.6.1) and, when `server` is given, a `Server` header. Headers the
+    /// caller already set by either name are left untouched.
+    pub fn auto_headers(mut self, server: Option<&str>) -> Self {
+        let has = |headers: &[(String, String)], name: &str| {
+            headers.iter().any(|(n, _)| n.eq_ignore_ascii_case(name))
+        };
+        if !has(&self.headers, "Date") {
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            self.headers.push(("Date".to_owned(), imf_fixdate(now)));
+        }
+        if let Some(server) = server {
+            if !has(&self.headers, "Server") {
+                self.headers.push(("Server".to_owned(), server.to_owned()));
+            }
+        }
+        self
+    }
+
+    /// Sets the body of the response to the given request method.
+    ///
+    /// For `HEAD` the headers — including the `Content-Length` the
+    /// equivalent GET would carry — are serialized unchanged, but the body
+    /// bytes are withheld so the response cannot corrupt framing.
+    pub fn body_for_method(mut self, method: Method, body: Option<&[u8]>) -> Self {
+        self.body = body.unwrap_or_default().to_vec();
+        self.omit_body = method == Method::Head;
+        self
+    }
+
+    /// Serializes the response to wire bytes.
+    pub fn build(self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(64 + self.body.len());
+        self.build_into(&mut out);
+        out
+    }
+
+    /// Serializes the response into an existing buffer — typically one
+    /// checked out of a [`crate::pool::BufferPool`] — without allocating
+    /// a fresh one per response.
+    pub fn build_into(self, out: &mut Vec<u8>) {
+        use std::io::Write as _;
+        let reason = reason_phrase(self.status);
+        let _ = write!(out, "HTTP/1.1 {} {reason}\r\n", self.status);
+        for (name, value) in &self.headers {
+            let _ = write!(out, "{name}: {value}\r\n");
+        }
+        out.extend_from_slice(b"Content-Length: ");
+        write_u64(out, self.body.len() as u64);
+        out.extend_from_slice(b"\r\n\r\n");
+        if !self.omit_body {
+            out.extend_from_slice(&self.body);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Counts allocations per thread so a test can assert a code path
+    /// stayed allocation-free without interference from parallel tests.
+    struct CountingAllocator;
+
+    std::thread_local! {
+        static ALLOCATIONS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+    }
+
+    unsafe impl std::alloc::GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+            unsafe { std::alloc::System.alloc(layout) }
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+            unsafe { std::alloc::System.dealloc(ptr, layout) }
+        }
+    }
+
+    #[global_allocator]
+    static GLOBAL: CountingAllocator = CountingAllocator;
+
+    fn request_with_headers(headers: Vec<Header<'_>>) -> Request<'_> {
+        Request {
+            method: Method::Get,
+            target: "/index.html?q=1",
+            version: Version::Http11,
+            headers,
+            body: Cow::Borrowed(&[]),
+            trailers: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn header_lookup_is_case_insensitive() {
+        let req = request_with_headers(vec![Header {
+            name: "Content-Type",
+            value: "text/plain",
+        }]);
+        assert_eq!(req.header("content-type"), Some("text/plain"));
+        assert_eq!(req.header("CONTENT-TYPE"), Some("text/plain"));
+        assert_eq!(req.header("accept"), None);
+    }
+
+    #[test]
+    fn path_strips_query() {
+        let req = request_with_headers(Vec::new());
+        assert_eq!(req.path(), "/index.html");
+    }
+
+    #[test]
+    fn parses_simple_get() {
+        let parser = Http1Parser::new();
+        let input = b"GET /index.html HTTP/1.1\r\nHost: example.com\r\n\r\n";
+        let (req, consumed) = parser.parse_request(input).unwrap();
+        assert_eq!(req.method, Method::Get);
+        assert_eq!(req.target, "/index.html");
+        assert_eq!(req.version, Version::Http11);
+        assert_eq!(req.header("Host"), Some("example.com"));
+        assert_eq!(consumed, input.len());
+    }
+
+    #[test]
+    fn parses_content_length_body() {
+        let parser = Http1Parser::new();
+        let input = b"POST /submit HTTP/1.1\r\nContent-Length: 5\r\n\r\nhello";
+        let (req, consumed) = parser.parse_request(input).unwrap();
+        assert_eq!(&*req.body, b"hello");
+        assert_eq!(consumed, input.len());
+    }
+
+    #[test]
+    fn parses_chunked_body() {
+        let parser = Http1Parser::new();
+        let input = b"POST /u HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n\
+                      4\r\nWiki\r\n5\r\npedia\r\n0\r\n\r\n";
+        let (req, consumed) = parser.parse_request(input).unwrap();
+        assert_eq!(&*req.body, b"Wikipedia");
+        assert_eq!(consumed, input.len());
+    }
+
+    #[test]
+    fn single_chunk_body_borrows_from_the_input() {
+        let parser = Http1Parser::new();
+        let input = b"POST /u HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n\
+                      9\r\nWikipedia\r\n0\r\n\r\n";
+        let (req, consumed) = parser.parse_request(input).unwrap();
+        assert!(matches!(req.body, Cow::Borrowed(_)), "body was copied");
+        assert_eq!(&*req.body, b"Wikipedia");
+        assert_eq!(consumed, input.len());
+    }
+
+    #[test]
+    fn multi_chunk_body_is_reassembled_into_the_same_bytes() {
+        let parser = Http1Parser::new();
+        let input = b"POST /u HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n\
+                      4\r\nWiki\r\n5\r\npedia\r\n0\r\n\r\n";
+        let (req, _) = parser.parse_request(input).unwrap();
+        assert!(matches!(req.body, Cow::Owned(_)), "body was not reassembled");
+        assert_eq!(&*req.body, b"Wikipedia");
+    }
+
+    #[test]
+    fn unsupported_transfer_coding_is_refused() {
+        let parser = Http1Parser::new();
+        let input = b"POST /u HTTP/1.1\r\nTransfer-Encoding: gzip, chunked\r\n\r\n\
+                      4\r\nWiki\r\n0\r\n\r\n";
+        assert_eq!(
+            parser.parse_request(input).unwrap_err(),
+            Http1ParseError::UnsupportedTransferEncoding
+        );
+    }
+
+    #[test]
+    fn chunked_must_be_the_final_coding() {
+        let parser = Http1Parser::new();
+        let input = b"POST /u HTTP/1.1\r\nTransfer-Encoding: chunked, gzip\r\n\r\n\
+                      4\r\nWiki\r\n0\r\n\r\n";
+        assert_eq!(
+            parser.parse_request(input).unwrap_err(),
+            Http1ParseError::MalformedRequest
+        );
+
+        // A repeated `chunked` leaves the message length ambiguous.
+        let input = b"POST /u HTTP/1.1\r\nTransfer-Encoding: chunked, chunked\r\n\r\n\
+                      4\r\nWiki\r\n0\r\n\r\n";
+        assert_eq!(
+            parser.parse_request(input).unwrap_err(),
+            Http1ParseError::MalformedRequest
+        );
+    }
+
+    #[test]
+    fn chunked_trailers_are_parsed() {
+        let parser = Http1Parser::new();
+        let input = b"POST /u HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n\
+                      4\r\nWiki\r\n5\r\npedia\r\n0\r\nX-Checksum: abc\r\n\r\n";
+        let (req, consumed) = parser.parse_request(input).unwrap();
+        assert_eq!(&*req.body, b"Wikipedia");
+        assert_eq!(req.trailer("x-checksum"), Some("abc"));
+        assert_eq!(consumed, input.len());
+
+        // A request with no trailer section has no trailers.
+        let input = b"POST /u HTTP/1.1\r\nContent-Length: 5\r\n\r\nhello";
+        let (req, _) = parser.parse_request(input).unwrap();
+        assert!(req.trailers.is_empty());
+    }
+
+    #[test]
+    fn framing_fields_are_forbidden_in_trailers() {
+        let parser = Http1Parser::new();
+        for field in ["Transfer-Encoding: gzip", "Content-Length: 9", "Host: x"] {
+            let mut input = b"POST /u HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n\
+                              4\r\nWiki\r\n0\r\n"
+                .to_vec();
+            input.extend_from_slice(field.as_bytes());
+            input.extend_from_slice(b"\r\n\r\n");
+            assert_eq!(
+                parser.parse_request(&input).unwrap_err(),
+                Http1ParseError::ForbiddenTrailer,
+                "{field} must be rejected"
+            );
+        }
+
+        // A malformed trailer line is rejected like any malformed header.
+        let input = b"POST /u HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n\
+                      4\r\nWiki\r\n0\r\nnot a header\r\n\r\n";
+        assert!(parser.parse_request(input).is_err());
+    }
+
+    #[test]
+    fn incomplete_request_is_reported() {
+        let parser = Http1Parser::new();
+        assert_eq!(
+            parser.parse_request(b"GET / HTTP/1.1\r\nHos").unwrap_err(),
+            Http1ParseError::IncompleteRequest
+        );
+        assert_eq!(
+            parser
+                .parse_request(b"POST / HTTP/1.1\r\nContent-Length: 10\r\n\r\nabc")
+                .unwrap_err(),
+            Http1ParseError::IncompleteRequest
+        );
+    }
+
+    #[test]
+    fn builder_limits_are_enforced() {
+        let parser = Http1Parser::builder().max_headers(2).build();
+        let input = b"GET / HTTP/1.1\r\nHost: x\r\nAccept: */*\r\nUser-Agent: t\r\n\r\n";
+        assert_eq!(
+            parser.parse_request(input).unwrap_err(),
+            Http1ParseError::TooManyHeaders
+        );
+
+        let parser = Http1Parser::builder().max_request_size(4).build();
+        assert_eq!(
+            parser
+                .parse_request(b"POST / HTTP/1.1\r\nContent-Length: 5\r\n\r\nhello")
+                .unwrap_err(),
+            Http1ParseError::RequestTooLarge
+        );
+    }
+
+    #[test]
+    fn request_line_at_the_limit_is_accepted() {
+        // "GET /xx...x HTTP/1.1" padded to exactly 64 bytes.
+        let target = format!("/{}", "x".repeat(64 - "GET / HTTP/1.1".len()));
+        let input = format!("GET {target} HTTP/1.1\r\nHost: x\r\n\r\n");
+        let parser = Http1Parser::builder().max_request_line(64).build();
+        let (request, _) = parser.parse_request(input.as_bytes()).unwrap();
+        assert_eq!(request.target, target);
+    }
+
+    #[test]
+    fn request_line_over_the_limit_is_a_uri_too_long() {
+        let target = format!("/{}", "x".repeat(65 - "GET / HTTP/1.1".len()));
+        let input = format!("GET {target} HTTP/1.1\r\nHost: x\r\n\r\n");
+        let parser = Http1Parser::builder().max_request_line(64).build();
+        assert_eq!(
+            parser.parse_request(input.as_bytes()).unwrap_err(),
+            Http1ParseError::UriTooLong
+        );
+        // The mapped response status is 414, not the generic 400 or 413.
+        assert_eq!(
+            crate::error::Error::from(Http1ParseError::UriTooLong).status_code(),
+            414
+        );
+    }
+
+    #[test]
+    fn endless_request_line_is_rejected_without_a_crlf() {
+        // No CRLF at all: once the buffered line alone exceeds the limit,
+        // the parser rejects instead of asking for more input forever.
+        let parser = Http1Parser::builder().max_request_line(64).build();
+        let input = vec![b'a'; 200];
+        assert_eq!(
+            parser.parse_request(&input).unwrap_err(),
+            Http1ParseError::UriTooLong
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "max_headers must be non-zero")]
+    fn builder_rejects_zero_limits() {
+        let _ = Http1Parser::builder().max_headers(0);
+    }
+
+    #[test]
+    fn oversized_header_block_is_rejected() {
+        let parser = Http1Parser::new();
+        // 100 headers of ~90 bytes each: every line is modest, but the
+        // block totals well past max_header_size (8192).
+        let mut input = b"GET / HTTP/1.1\r\n".to_vec();
+        for i in 0..100 {
+            input.extend_from_slice(format!("X-Padding-{i}: {}\r\n", "v".repeat(75)).as_bytes());
+        }
+        input.extend_from_slice(b"\r\n");
+        assert_eq!(
+            parser.parse_request(&input).unwrap_err(),
+            Http1ParseError::RequestTooLarge
+        );
+    }
+
+    #[test]
+    fn unterminated_overlong_header_line_is_rejected() {
+        let parser = Http1Parser::new();
+        // A single header line that never reaches its CRLF must fail once
+        // it exceeds the limit instead of asking for more data forever.
+        let mut input = b"GET / HTTP/1.1\r\nX-Huge: ".to_vec();
+        input.extend(std::iter::repeat_n(b'a', parser.max_header_size() + 1));
+        assert_eq!(
+            parser.parse_request(&input).unwrap_err(),
+            Http1ParseError::RequestTooLarge
+        );
+    }
+
+    #[test]
+    fn rejects_bad_request_lines() {
+        let parser = Http1Parser::new();
+        assert_eq!(
+            parser.parse_request(b"BREW / HTTP/1.1\r\n\r\n").unwrap_err(),
+            Http1ParseError::InvalidMethod
+        );
+        assert_eq!(
+            parser.parse_request(b"GET / HTTP/2.5\r\n\r\n").unwrap_err(),
+            Http1ParseError::InvalidVersion
+        );
+    }
+
+    #[test]
+    fn header_value_whitespace_is_trimmed() {
+        let parser = Http1Parser::new();
+        let input = b"GET / HTTP/1.1\r\nX-Pad:   padded value  \r\n\r\n";
+        let (req, _) = parser.parse_request(input).unwrap();
+        assert_eq!(req.header("X-Pad"), Some("padded value"));
+    }
+
+    #[test]
+    fn oversized_chunk_is_rejected_before_buffering() {
+        let parser = Http1Parser::builder().max_chunk_size(16).build();
+        // The declared size alone trips the limit; the data never arrives.
+        let input = b"POST /u HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n11\r\n";
+        assert_eq!(
+            parser.parse_request(input).unwrap_err(),
+            Http1ParseError::RequestTooLarge
+        );
+    }
+
+    #[test]
+    fn chunk_count_is_bounded() {
+        let parser = Http1Parser::builder().max_chunk_count(4).build();
+        let mut input = b"POST /u HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n".to_vec();
+        for _ in 0..5 {
+            input.extend_from_slice(b"1\r\na\r\n");
+        }
+        input.extend_from_slice(b"0\r\n\r\n");
+        assert_eq!(
+            parser.parse_request(&input).unwrap_err(),
+            Http1ParseError::TooManyChunks
+        );
+    }
+
+    #[test]
+    fn content_length_is_strictly_decimal() {
+        let parser = Http1Parser::new();
+        let request = |value: &str| {
+            format!("POST / HTTP/1.1\r\nContent-Length: {value}\r\n\r\n0123456789012345678901")
+        };
+        for smuggled in ["+10", "0x10", "1 0", "10abc", ""] {
+            assert_eq!(
+                parser.parse_request(request(smuggled).as_bytes()).unwrap_err(),
+                Http1ParseError::InvalidContentLength,
+                "{smuggled:?} must be rejected"
+            );
+        }
+        // Surrounding whitespace was already trimmed by header parsing.
+        for plain in [" 10 ", "10"] {
+            let input = request(plain);
+            let (req, _) = parser.parse_request(input.as_bytes()).unwrap();
+            assert_eq!(req.body.len(), 10);
+        }
+    }
+
+    #[test]
+    fn borrowed_parse_matches_the_eager_headers() {
+        let parser = Http1Parser::new();
+        let input =
+            b"POST /submit?v=1 HTTP/1.1\r\nHost: example.com\r\nAccept: */*\r\nContent-Length: 5\r\n\r\nhello";
+        let (eager, eager_consumed) = parser.parse_request(input).unwrap();
+
+        let (view, consumed) = parser.parse_request_borrowed(input).unwrap();
+        assert_eq!(view.method, eager.method);
+        assert_eq!(view.target, eager.target);
+        assert_eq!(view.path(), "/submit");
+        assert_eq!(consumed, eager_consumed);
+        assert_eq!(&*view.body, b"hello");
+
+        let lazy: Vec<Header<'_>> = view.headers.iter().map(Result::unwrap).collect();
+        assert_eq!(lazy, eager.headers);
+        assert_eq!(view.header("HOST"), Some("example.com"));
+    }
+
+    #[test]
+    fn borrowed_parse_does_not_allocate() {
+        let parser = Http1Parser::new();
+        let input = b"GET /metrics HTTP/1.1\r\nHost: example.com\r\nAccept: */*\r\n\r\n";
+
+        let before = ALLOCATIONS.with(std::cell::Cell::get);
+        let (view, consumed) = parser.parse_request_borrowed(input).unwrap();
+        let host = view.header("host");
+        let mut fields = 0;
+        for header in view.headers.iter() {
+            assert!(header.is_ok());
+            fields += 1;
+        }
+        let after = ALLOCATIONS.with(std::cell::Cell::get);
+
+        assert_eq!(host, Some("example.com"));
+        assert_eq!(fields, 2);
+        assert_eq!(consumed, input.len());
+        assert_eq!(after - before, 0, "borrowed parse path allocated");
+    }
+
+    #[test]
+    fn cookies_split_and_trim_pairs() {
+        let request = request_with_headers(vec![Header {
+            name: "Cookie",
+            value: "a=1; b=2; c=",
+        }]);
+        let cookies: Vec<(&str, &str)> = request.cookies().collect();
+        assert_eq!(cookies, vec![("a", "1"), ("b", "2"), ("c", "")]);
+    }
+
+    #[test]
+    fn cookies_skip_malformed_pairs_and_absent_headers() {
+        let request = request_with_headers(vec![Header {
+            name: "Cookie",
+            value: "bare; session=abc=def",
+        }]);
+        // A pair without `=` is skipped; a value keeps embedded `=` intact.
+        let cookies: Vec<(&str, &str)> = request.cookies().collect();
+        assert_eq!(cookies, vec![("session", "abc=def")]);
+
+        let request = request_with_headers(Vec::new());
+        assert_eq!(request.cookies().count(), 0);
+    }
+
+    #[test]
+    fn origin_form_plus_host_converts_to_absolute_form_and_back() {
+        let parser = Http1Parser::new();
+        let (request, _) = parser
+            .parse_request(b"GET /p HTTP/1.1\r\nHost: h\r\n\r\n")
+            .unwrap();
+        let absolute = to_absolute_form(&request, "http");
+        assert_eq!(absolute, "http://h/p");
+        assert_eq!(to_origin_form(&absolute), ("h", "/p"));
+    }
+
+    #[test]
+    fn default_ports_are_dropped_from_the_authority() {
+        let parser = Http1Parser::new();
+        let (request, _) = parser
+            .parse_request(b"GET /p HTTP/1.1\r\nHost: h:80\r\n\r\n")
+            .unwrap();
+        assert_eq!(to_absolute_form(&request, "http"), "http://h/p");
+        assert_eq!(to_origin_form("https://h:443"), ("h", "/"));
+        // A non-default port is meaningful and survives both directions.
+        assert_eq!(to_origin_form("http://h:8080/p"), ("h:8080", "/p"));
+    }
+
+    #[test]
+    fn asterisk_and_absolute_targets_pass_through() {
+        let parser = Http1Parser::new();
+        let (request, _) = parser
+            .parse_request(b"OPTIONS * HTTP/1.1\r\nHost: h\r\n\r\n")
+            .unwrap();
+        assert_eq!(to_absolute_form(&request, "http"), "*");
+        assert_eq!(to_origin_form("*"), ("", "*"));
+
+        let (request, _) = parser
+            .parse_request(b"GET http://h/p HTTP/1.1\r\nHost: h\r\n\r\n")
+            .unwrap();
+        assert_eq!(to_absolute_form(&request, "http"), "http://h/p");
+    }
+
+    #[test]
+    fn write_u64_matches_the_formatter() {
+        for n in [0, 7, 10, 99, 1_000_000, u64::MAX - 1, u64::MAX] {
+            let mut out = Vec::new();
+            write_u64(&mut out, n);
+            assert_eq!(out, n.to_string().as_bytes(), "mismatch for {n}");
+        }
+    }
+
+    #[test]
+    fn imf_fixdate_formats_known_instants() {
+        // The RFC 9110 example date.
+        assert_eq!(imf_fixdate(784_111_777), "Sun, 06 Nov 1994 08:49:37 GMT");
+        assert_eq!(imf_fixdate(0), "Thu, 01 Jan 1970 00:00:00 GMT");
+        // 2100 skips the century leap day, so February ends on the 28th.
+        assert_eq!(imf_fixdate(4_107_542_399), "Sun, 28 Feb 2100 23:59:59 GMT");
+    }
+
+    #[test]
+    fn auto_headers_emit_a_well_formed_date() {
+        let response = Http1ResponseBuilder::new(200)
+            .auto_headers(Some("angelax"))
+            .build();
+        let text = String::from_utf8(response).unwrap();
+        let date = text
+            .lines()
+            .find_map(|line| line.strip_prefix("Date: "))
+            .expect("Date header present");
+
+        // `Day, DD Mon YYYY HH:MM:SS GMT`, field by field.
+        let mut parts = date.split(' ');
+        let day_name = parts.next().unwrap();
+        assert!(["Sun,", "Mon,", "Tue,", "Wed,", "Thu,", "Fri,", "Sat,"].contains(&day_name));
+        let day = parts.next().unwrap();
+        assert!(day.len() == 2 && day.bytes().all(|b| b.is_ascii_digit()));
+        let month = parts.next().unwrap();
+        assert!([
+            "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"
+        ]
+        .contains(&month));
+        let year = parts.next().unwrap();
+        assert!(year.len() == 4 && year.bytes().all(|b| b.is_ascii_digit()));
+        let time = parts.next().unwrap();
+        let time = time.as_bytes();
+        assert_eq!(time.len(), 8);
+        assert!(time[2] == b':' && time[5] == b':');
+        assert_eq!(parts.next(), Some("GMT"));
+        assert_eq!(parts.next(), None);
+
+        assert!(text.contains("Server: angelax\r\n"));
+    }
+
+    #[test]
+    fn auto_headers_defer_to_caller_supplied_values() {
+        let response = Http1ResponseBuilder::new(200)
+            .header("date", "Thu, 01 Jan 1970 00:00:00 GMT")
+            .header("Server", "custom/1.0")
+            .auto_headers(Some("angelax"))
+            .build();
+        let text = String::from_utf8(response).unwrap();
+        assert_eq!(text.matches("ate: ").count(), 1, "no duplicate Date");
+        assert!(text.contains("Server: custom/1.0\r\n"));
+        assert!(!text.contains("angelax"));
+    }
+
+    #[test]
+    fn range_forms_parse_and_clamp() {
+        // Closed, open-ended, and suffix forms against a 1000-byte file.
+        assert_eq!(parse_range_header("bytes=0-499", 1000).unwrap(), vec![(0, 499)]);
+        assert_eq!(parse_range_header("bytes=500-", 1000).unwrap(), vec![(500, 999)]);
+        assert_eq!(parse_range_header("bytes=-500", 1000).unwrap(), vec![(500, 999)]);
+        // An end past EOF clamps to the last byte.
+        assert_eq!(parse_range_header("bytes=900-2000", 1000).unwrap(), vec![(900, 999)]);
+        // A suffix longer than the file covers the whole file.
+        assert_eq!(parse_range_header("bytes=-5000", 1000).unwrap(), vec![(0, 999)]);
+    }
+
+    #[test]
+    fn multiple_ranges_keep_request_order() {
+        assert_eq!(
+            parse_range_header("bytes=500-599, 0-99, 50-149", 1000).unwrap(),
+            vec![(500, 599), (0, 99), (50, 149)]
+        );
+    }
+
+    #[test]
+    fn unsatisfiable_and_malformed_ranges_are_rejected() {
+        assert_eq!(
+            parse_range_header("bytes=1000-", 1000).unwrap_err(),
+            RangeError::Unsatisfiable
+        );
+        assert_eq!(
+            parse_range_header("bytes=-0", 1000).unwrap_err(),
+            RangeError::Unsatisfiable
+        );
+        for malformed in ["items=0-499", "bytes=499-0", "bytes=a-b", "bytes=5"] {
+            assert_eq!(
+                parse_range_header(malformed, 1000).unwrap_err(),
+                RangeError::Malformed,
+                "{malformed:?} should be malformed"
+            );
+        }
+    }
+
+    #[test]
+    fn cookie_attributes_serialize_in_order() {
+        let cookie = Cookie::new("id", "abc")
+            .max_age(3600)
+            .domain("example.com")
+            .path("/")
+            .secure()
+            .http_only()
+            .same_site(SameSite::Lax);
+        assert_eq!(
+            cookie.to_header_value(),
+            "id=abc; Max-Age=3600; Domain=example.com; Path=/; Secure; HttpOnly; SameSite=Lax"
+        );
+
+        let bare = Cookie::new("session", "");
+        assert_eq!(bare.to_header_value(), "session=");
+
+        let response = Http1ResponseBuilder::new(200)
+            .set_cookie(&Cookie::new("id", "abc").http_only())
+            .build();
+        let text = String::from_utf8(response).unwrap();
+        assert!(text.contains("Set-Cookie: id=abc; HttpOnly\r\n"));
+    }
+
+    #[test]
+    #[should_panic(expected = "cookie name must be a valid token")]
+    fn cookie_name_must_be_a_token() {
+        let _ = Cookie::new("bad;name", "value");
+    }
+
+    #[test]
+    fn head_response_keeps_headers_but_drops_the_body() {
+        let payload = b"hello world";
+        let response = |method| {
+            Http1ResponseBuilder::new(200)
+                .header("Content-Type", "text/plain")
+                .body_for_method(method, Some(payload))
+                .build()
+        };
+        let get = String::from_utf8(response(Method::Get)).unwrap();
+        let head = String::from_utf8(response(Method::Head)).unwrap();
+
+        // Identical head section, including the GET body's Content-Length.
+        let get_head = get.split("\r\n\r\n").next().unwrap();
+        let head_head = head.split("\r\n\r\n").next().unwrap();
+        assert_eq!(get_head, head_head);
+        assert!(head_head.contains(&format!("Content-Length: {}", payload.len())));
+
+        assert!(get.ends_with("hello world"));
+        assert!(head.ends_with("\r\n\r\n"), "HEAD must carry no body: {head:?}");
+    }
+
+    #[test]
+    fn basic_authorization_decodes_credentials() {
+        let parser = Http1Parser::new();
+        let (request, _) = parser
+            .parse_request(b"GET / HTTP/1.1\r\nHost: x\r\nAuthorization: basic dXNlcjpwYXNz\r\n\r\n")
+            .unwrap();
+        assert_eq!(
+            request.authorization(),
+            Some(AuthScheme::Basic {
+                username: "user".into(),
+                password: "pass".into(),
+            })
+        );
+
+        // No colon in the decoded credentials: not Basic at all.
+        let (request, _) = parser
+            .parse_request(b"GET / HTTP/1.1\r\nHost: x\r\nAuthorization: Basic dXNlcg==\r\n\r\n")
+            .unwrap();
+        assert_eq!(request.authorization(), None);
+    }
+
+    #[test]
+    fn bearer_and_unknown_schemes_parse() {
+        let parser = Http1Parser::new();
+        let (request, _) = parser
+            .parse_request(b"GET / HTTP/1.1\r\nHost: x\r\nAuthorization: Bearer abc.def.ghi\r\n\r\n")
+            .unwrap();
+        assert_eq!(
+            request.authorization(),
+            Some(AuthScheme::Bearer {
+                token: "abc.def.ghi".into(),
+            })
+        );
+
+        let (request, _) = parser
+            .parse_request(b"GET / HTTP/1.1\r\nHost: x\r\nAuthorization: Digest realm=\"r\"\r\n\r\n")
+            .unwrap();
+        assert_eq!(
+            request.authorization(),
+            Some(AuthScheme::Other {
+                scheme: "Digest".into(),
+                params: "realm=\"r\"".into(),
+            })
+        );
+        let (request, _) = parser.parse_request(b"GET / HTTP/1.1\r\nHost: x\r\n\r\n").unwrap();
+        assert_eq!(request.authorization(), None);
+    }
+
+    #[test]
+    fn http11_without_host_is_malformed() {
+        let parser = Http1Parser::new();
+        let (request, _) = parser
+            .parse_request(b"GET / HTTP/1.1\r\nAccept: */*\r\n\r\n")
+            .unwrap();
+        assert_eq!(request.host(), None);
+        assert_eq!(request.validate_host().unwrap_err(), Http1ParseError::MalformedRequest);
+
+        // HTTP/1.0 predates Host; its absence is fine there.
+        let (request, _) = parser.parse_request(b"GET / HTTP/1.0\r\n\r\n").unwrap();
+        assert!(request.validate_host().is_ok());
+    }
+
+    #[test]
+    fn duplicate_host_headers_are_malformed() {
+        let parser = Http1Parser::new();
+        let (request, _) = parser
+            .parse_request(b"GET / HTTP/1.1\r\nHost: a.example\r\nHost: b.example\r\n\r\n")
+            .unwrap();
+        assert_eq!(request.validate_host().unwrap_err(), Http1ParseError::MalformedRequest);
+    }
+
+    #[test]
+    fn absolute_form_authority_must_agree_with_host() {
+        let parser = Http1Parser::new();
+        let (request, _) = parser
+            .parse_request(b"GET http://a.example/x HTTP/1.1\r\nHost: b.example\r\n\r\n")
+            .unwrap();
+        assert_eq!(request.validate_host().unwrap_err(), Http1ParseError::MalformedRequest);
+
+        let (request, _) = parser
+            .parse_request(b"GET http://a.example/x?q=1 HTTP/1.1\r\nHost: A.Example\r\n\r\n")
+            .unwrap();
+        assert_eq!(request.host(), Some("A.Example"));
+        assert!(request.validate_host().is_ok(), "authority comparison is case-insensitive");
+    }
+
+    #[test]
+    fn control_bytes_in_header_values_are_rejected() {
+        let parser = Http1Parser::new();
+        let with_nul = b"GET / HTTP/1.1\r\nX-Injected: abc\0def\r\n\r\n";
+        assert_eq!(
+            parser.parse_request(with_nul).unwrap_err(),
+            Http1ParseError::InvalidHeaderValue
+        );
+        let with_bare_cr = b"GET / HTTP/1.1\r\nX-Injected: abc\rdef\r\n\r\n";
+        assert_eq!(
+            parser.parse_request(with_bare_cr).unwrap_err(),
+            Http1ParseError::InvalidHeaderValue
+        );
+    }
+
+    #[test]
+    fn internal_spaces_in_header_values_are_legitimate() {
+        let parser = Http1Parser::new();
+        let input = b"GET / HTTP/1.1\r\nUser-Agent: Mozilla/5.0 (X11; Linux)\r\n\r\n";
+        let (request, _) = parser.parse_request(input).unwrap();
+        assert_eq!(request.header("User-Agent"), Some("Mozilla/5.0 (X11; Linux)"));
+    }
+
+    #[test]
+    fn obs_text_is_opt_in() {
+        // "é" in UTF-8: two obs-text bytes.
+        let input = "GET / HTTP/1.1\r\nX-Name: caf\u{e9}\r\n\r\n".as_bytes();
+        assert_eq!(
+            Http1Parser::new().parse_request(input).unwrap_err(),
+            Http1ParseError::InvalidHeaderValue
+        );
+        let lenient = Http1Parser::builder().allow_obs_text().build();
+        let (request, _) = lenient.parse_request(input).unwrap();
+        assert_eq!(request.header("X-Name"), Some("caf\u{e9}"));
+    }
+
+    #[test]
+    fn one_parser_parses_concurrently_across_threads() {
+        let parser = std::sync::Arc::new(Http1Parser::new());
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                let parser = std::sync::Arc::clone(&parser);
+                std::thread::spawn(move || {
+                    let input = format!("GET /thread/{i} HTTP/1.1\r\nHost: x\r\n\r\n");
+                    for _ in 0..100 {
+                        let (request, consumed) = parser.parse_request(input.as_bytes()).unwrap();
+                        assert_eq!(request.target, format!("/thread/{i}"));
+                        assert_eq!(consumed, input.len());
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn method_round_trips() {
+        for m in [b"GET".as_slice(), b"POST", b"DELETE", b"PATCH"] {
+            let parsed = Method::from_bytes(m).unwrap();
+            assert_eq!(parsed.as_str().as_bytes(), m);
+        }
+        assert_eq!(Method::from_bytes(b"BREW"), None);
+    }
+}
@@ -0,0 +1,1289 @@
+//! HTTP/2 framing and connection-level parsing (RFC 7540).
+
+use crate::error::ErrorCode;
+use crate::hpack::HpackDecoder;
+use std::fmt;
+
+/// Length of the fixed frame header (RFC 7540 §4.1).
+pub const FRAME_HEADER_LEN: usize = 9;
+
+/// The frame-size limit in force before SETTINGS change it (RFC 7540
+/// §6.5.2).
+pub const DEFAULT_MAX_FRAME_SIZE: u32 = 16_384;
+
+/// Frame flags.
+pub const FLAG_ACK: u8 = 0x1;
+pub const FLAG_END_STREAM: u8 = 0x1;
+pub const FLAG_END_HEADERS: u8 = 0x4;
+pub const FLAG_PADDED: u8 = 0x8;
+pub const FLAG_PRIORITY: u8 = 0x20;
+
+/// SETTINGS parameter identifiers (RFC 7540 §6.5.2).
+pub const SETTINGS_HEADER_TABLE_SIZE: u16 = 0x1;
+pub const SETTINGS_ENABLE_PUSH: u16 = 0x2;
+pub const SETTINGS_MAX_CONCURRENT_STREAMS: u16 = 0x3;
+pub const SETTINGS_INITIAL_WINDOW_SIZE: u16 = 0x4;
+pub const SETTINGS_MAX_FRAME_SIZE: u16 = 0x5;
+pub const SETTINGS_MAX_HEADER_LIST_SIZE: u16 = 0x6;
+
+/// Errors produced while parsing HTTP/2 input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Http2ParseError {
+    /// The buffer does not yet hold the whole frame; read more and retry.
+    IncompleteFrame,
+    /// A frame declared a length beyond the negotiated maximum.
+    InvalidFrameSize,
+    /// A DATA frame declared a length beyond the negotiated maximum;
+    /// fatal to its stream only, not the connection (RFC 7540 §4.2).
+    StreamFrameSizeError { stream_id: u32 },
+    /// A SETTINGS frame or parameter violated RFC 7540 §6.5.
+    InvalidSettings,
+    /// A SETTINGS the server sent went unacknowledged past the configured
+    /// deadline (RFC 7540 §6.5.3).
+    SettingsTimeout,
+    /// A HEADERS + CONTINUATION sequence outgrew the configured block
+    /// caps — the CONTINUATION-flood pattern; answer with a GOAWAY
+    /// advertising ENHANCE_YOUR_CALM.
+    HeaderBlockFlood,
+    /// A frame arrived on a stream id its type forbids, such as DATA on
+    /// stream 0 (RFC 7540 §6.1).
+    InvalidStreamId,
+    /// The connection preface did not match RFC 7540 §3.5.
+    InvalidPreface,
+    /// A flow-control window bound was violated (RFC 7540 §6.9).
+    FlowControlError,
+    /// A header block could not be decompressed, or its decompressed
+    /// size exceeded `SETTINGS_MAX_HEADER_LIST_SIZE` (RFC 7540 §6.5.2).
+    CompressionError,
+}
+
+/// The type of an HTTP/2 frame (RFC 7540 §6).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameType {
+    Data,
+    Headers,
+    Priority,
+    RstStream,
+    Settings,
+    PushPromise,
+    Ping,
+    Goaway,
+    WindowUpdate,
+    Continuation,
+    /// An extension frame; implementations must ignore these (§4.1).
+    Unknown(u8),
+}
+
+impl From<u8> for FrameType {
+    fn from(byte: u8) -> Self {
+        match byte {
+            0x0 => FrameType::Data,
+            0x1 => FrameType::Headers,
+            0x2 => FrameType::Priority,
+            0x3 => FrameType::RstStream,
+            0x4 => FrameType::Settings,
+            0x5 => FrameType::PushPromise,
+            0x6 => FrameType::Ping,
+            0x7 => FrameType::Goaway,
+            0x8 => FrameType::WindowUpdate,
+            0x9 => FrameType::Continuation,
+            other => FrameType::Unknown(other),
+        }
+    }
+}
+
+impl FrameType {
+    pub fn as_byte(&self) -> u8 {
+        match self {
+            FrameType::Data => 0x0,
+            FrameType::Headers => 0x1,
+            FrameType::Priority => 0x2,
+            FrameType::RstStream => 0x3,
+            FrameType::Settings => 0x4,
+            FrameType::PushPromise => 0x5,
+            FrameType::Ping => 0x6,
+            FrameType::Goaway => 0x7,
+            FrameType::WindowUpdate => 0x8,
+            FrameType::Continuation => 0x9,
+            FrameType::Unknown(byte) => *byte,
+        }
+    }
+}
+
+/// The fixed 9-byte header preceding every frame payload.
+#[derive(Debug, Clone, Copy)]
+pub struct FrameHeader {
+    pub length: u32,
+    pub frame_type: FrameType,
+    pub flags: u8,
+    pub stream_id: u32,
+}
+
+/// A complete frame borrowed from the input buffer.
+#[derive(Debug, Clone, Copy)]
+pub struct Frame<'a> {
+    pub header: FrameHeader,
+    pub payload: &'a [u8],
+}
+
+/// The SETTINGS parameters of one endpoint, with RFC 7540 §11.3 defaults.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Http2Settings {
+    pub header_table_size: u32,
+    pub enable_push: bool,
+    pub max_concurrent_streams: Option<u32>,
+    pub initial_window_size: u32,
+    pub max_frame_size: u32,
+    pub max_header_list_size: Option<u32>,
+}
+
+impl Default for Http2Settings {
+    fn default() -> Self {
+        Self {
+            header_table_size: 4096,
+            enable_push: true,
+            max_concurrent_streams: None,
+            initial_window_size: 65_535,
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            max_header_list_size: None,
+        }
+    }
+}
+
+/// A PRIORITY payload (RFC 7540 §5.3, §6.3): the stream this one depends
+/// on, whether the dependency is exclusive, and the effective weight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Priority {
+    pub exclusive: bool,
+    pub dependency: u32,
+    /// The effective weight, 1–256; the wire carries it minus one.
+    pub weight: u16,
+}
+
+/// Parses the 5-byte PRIORITY payload: one exclusive bit, a 31-bit
+/// dependency stream id, and a weight byte.
+pub fn parse_priority(payload: &[u8]) -> Result<Priority, Http2ParseError> {
+    if payload.len() != 5 {
+        return Err(Http2ParseError::InvalidFrameSize);
+    }
+    let word = u32::from_be_bytes([payload[0], payload[1], payload[2], payload[3]]);
+    Ok(Priority {
+        exclusive: word & 0x8000_0000 != 0,
+        dependency: word & 0x7fff_ffff,
+        weight: u16::from(payload[4]) + 1,
+    })
+}
+
+/// Extracts the header block fragment from a HEADERS payload, stripping
+/// the optional pad-length byte, priority fields, and trailing padding
+/// the flags declare (RFC 7540 §6.2).
+pub fn headers_fragment(flags: u8, payload: &[u8]) -> Result<&[u8], Http2ParseError> {
+    let mut start = 0;
+    let mut end = payload.len();
+    if flags & FLAG_PADDED != 0 {
+        let pad = *payload.first().ok_or(Http2ParseError::InvalidFrameSize)? as usize;
+        start = 1;
+        end = end
+            .checked_sub(pad)
+            .filter(|&e| e >= start)
+            .ok_or(Http2ParseError::InvalidFrameSize)?;
+    }
+    if flags & FLAG_PRIORITY != 0 {
+        start += 5;
+        if start > end {
+            return Err(Http2ParseError::InvalidFrameSize);
+        }
+    }
+    Ok(&payload[start..end])
+}
+
+/// Reassembles one header block from a HEADERS frame and its CONTINUATION
+/// frames (RFC 7540 §4.3). Both the accumulated bytes and the frame count
+/// are capped independently of the frame-size limit, so a flood of small
+/// CONTINUATION frames cannot grow memory without bound.
+#[derive(Debug, Clone, Default)]
+pub struct HeaderBlockAssembler {
+    fragments: Vec<u8>,
+    stream_id: u32,
+    continuations: usize,
+    in_progress: bool,
+}
+
+impl HeaderBlockAssembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether a block is mid-assembly; CONTINUATION for the same stream
+    /// is then the only frame that may advance it (§4.3).
+    pub fn in_progress(&self) -> bool {
+        self.in_progress
+    }
+
+    /// The stream whose block is being assembled.
+    pub fn stream_id(&self) -> u32 {
+        self.stream_id
+    }
+
+    /// Starts assembling from a HEADERS fragment that did not carry
+    /// END_HEADERS.
+    pub fn begin(
+        &mut self,
+        stream_id: u32,
+        fragment: &[u8],
+        max_block_size: usize,
+    ) -> Result<(), Http2ParseError> {
+        if fragment.len() > max_block_size {
+            self.reset();
+            return Err(Http2ParseError::HeaderBlockFlood);
+        }
+        self.fragments.clear();
+        self.fragments.extend_from_slice(fragment);
+        self.stream_id = stream_id;
+        self.continuations = 0;
+        self.in_progress = true;
+        Ok(())
+    }
+
+    /// Appends a CONTINUATION fragment, returning the whole block once
+    /// `end_headers` closes it.
+    pub fn append(
+        &mut self,
+        fragment: &[u8],
+        end_headers: bool,
+        max_block_size: usize,
+        max_continuations: usize,
+    ) -> Result<Option<Vec<u8>>, Http2ParseError> {
+        self.continuations += 1;
+        if self.continuations > max_continuations
+            || self.fragments.len() + fragment.len() > max_block_size
+        {
+            self.reset();
+            return Err(Http2ParseError::HeaderBlockFlood);
+        }
+        self.fragments.extend_from_slice(fragment);
+        if end_headers {
+            self.in_progress = false;
+            Ok(Some(std::mem::take(&mut self.fragments)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Drops the partial block, releasing whatever it had buffered.
+    fn reset(&mut self) {
+        self.fragments = Vec::new();
+        self.in_progress = false;
+        self.continuations = 0;
+    }
+}
+
+/// Validates the pseudo-header section of a decoded request header list
+/// (RFC 7540 §8.1.2.1–8.1.2.3): pseudo-headers must precede every regular
+/// field, appear at most once, come only from the request set, and include
+/// the fields a request requires — `:method`, `:scheme`, and `:path`,
+/// except for CONNECT, which carries `:method` alone (§8.3). A violation
+/// makes the request malformed, i.e. [`ErrorCode::ProtocolError`].
+pub fn validate_request_pseudo_headers(
+    headers: &[(Vec<u8>, Vec<u8>)],
+) -> Result<(), ErrorCode> {
+    const REQUEST_PSEUDO: [&[u8]; 5] = [
+        b":method",
+        b":scheme",
+        b":path",
+        b":authority",
+        b":protocol",
+    ];
+    let mut seen: Vec<&[u8]> = Vec::new();
+    let mut regular_seen = false;
+    for (name, _) in headers {
+        if name.first() == Some(&b':') {
+            if regular_seen
+                || !REQUEST_PSEUDO.contains(&name.as_slice())
+                || seen.contains(&name.as_slice())
+            {
+                return Err(ErrorCode::ProtocolError);
+            }
+            seen.push(name);
+        } else {
+            regular_seen = true;
+        }
+    }
+    let has = |field: &[u8]| seen.contains(&field);
+    if !has(b":method") {
+        return Err(ErrorCode::ProtocolError);
+    }
+    let connect = headers
+        .iter()
+        .any(|(n, v)| n.as_slice() == b":method" && v.as_slice() == b"CONNECT");
+    if !connect && (!has(b":scheme") || !has(b":path")) {
+        return Err(ErrorCode::ProtocolError);
+    }
+    Ok(())
+}
+
+/// Validates that every decoded header field name is lowercase, as HTTP/2
+/// requires (RFC 7540 §8.1.2); a name with an uppercase byte makes the
+/// request malformed, i.e. [`ErrorCode::ProtocolError`].
+pub fn validate_lowercase_header_names(
+    headers: &[(Vec<u8>, Vec<u8>)],
+) -> Result<(), ErrorCode> {
+    let scanner = crate::simd::SimdUppercaseScanner::new();
+    if headers
+        .iter()
+        .any(|(name, _)| scanner.contains_uppercase(name))
+    {
+        return Err(ErrorCode::ProtocolError);
+    }
+    Ok(())
+}
+
+/// Most settings entries accepted in one SETTINGS frame. The protocol
+/// defines six identifiers, so a list past a small multiple of that is a
+/// flooding attempt, not a negotiation.
+pub const MAX_SETTINGS_PER_FRAME: usize = 16;
+
+/// Parses a SETTINGS frame payload into `(identifier, value)` pairs.
+///
+/// The entry count is capped at [`MAX_SETTINGS_PER_FRAME`] and an
+/// identifier may appear at most once per frame; either violation is
+/// [`Http2ParseError::InvalidSettings`].
+pub fn parse_settings(payload: &[u8]) -> Result<Vec<(u16, u32)>, Http2ParseError> {
+    if !payload.len().is_multiple_of(6) || payload.len() / 6 > MAX_SETTINGS_PER_FRAME {
+        return Err(Http2ParseError::InvalidSettings);
+    }
+    let mut pairs = Vec::with_capacity(payload.len() / 6);
+    for entry in payload.chunks_exact(6) {
+        let id = u16::from_be_bytes([entry[0], entry[1]]);
+        let value = u32::from_be_bytes([entry[2], entry[3], entry[4], entry[5]]);
+        if pairs.iter().any(|&(existing, _)| existing == id) {
+            return Err(Http2ParseError::InvalidSettings);
+        }
+        pairs.push((id, value));
+    }
+    Ok(pairs)
+}
+
+/// The default initial flow-control window size (RFC 7540 §6.9.2).
+pub const DEFAULT_WINDOW_SIZE: u32 = 65_535;
+
+/// Connection-level flow-control accounting (RFC 7540 §6.9).
+///
+/// The send window is credit granted by the peer: debited as DATA goes
+/// out, restored by its WINDOW_UPDATEs. The receive window is credit this
+/// endpoint granted: debited as DATA arrives and replenished by the
+/// WINDOW_UPDATEs it owes in return.
+#[derive(Debug, Clone)]
+pub struct FlowController {
+    send_window: i64,
+    recv_window: i64,
+    /// The receive window's full size, which replenishment restores.
+    initial_recv_window: u32,
+    /// Receive-window bytes consumed since the last WINDOW_UPDATE.
+    consumed: u32,
+}
+
+impl Default for FlowController {
+    fn default() -> Self {
+        Self::new(DEFAULT_WINDOW_SIZE)
+    }
+}
+
+impl FlowController {
+    pub fn new(initial_recv_window: u32) -> Self {
+        Self {
+            send_window: i64::from(DEFAULT_WINDOW_SIZE),
+            recv_window: i64::from(initial_recv_window),
+            initial_recv_window,
+            consumed: 0,
+        }
+    }
+
+    /// Remaining credit for outgoing DATA.
+    pub fn send_window(&self) -> i64 {
+        self.send_window
+    }
+
+    /// Remaining credit the peer may spend on incoming DATA.
+    pub fn recv_window(&self) -> i64 {
+        self.recv_window
+    }
+
+    /// Debits the send window for `n` bytes of outgoing DATA.
+    pub fn consume_send_window(&mut self, n: u32) -> Result<(), Http2ParseError> {
+        if i64::from(n) > self.send_window {
+            return Err(Http2ParseError::FlowControlError);
+        }
+        self.send_window -= i64::from(n);
+        Ok(())
+    }
+
+    /// Credits the send window from a peer WINDOW_UPDATE. Growing the
+    /// window past 2^31 - 1 is a flow-control error (§6.9.1).
+    pub fn credit_send_window(&mut self, increment: u32) -> Result<(), Http2ParseError> {
+        let next = self.send_window + i64::from(increment);
+        if next > 0x7fff_ffff {
+            return Err(Http2ParseError::FlowControlError);
+        }
+        self.send_window = next;
+        Ok(())
+    }
+
+    /// Debits the receive window for `n` bytes of incoming DATA. A peer
+    /// sending past the advertised window is a flow-control error.
+    pub fn consume_recv_window(&mut self, n: u32) -> Result<(), Http2ParseError> {
+        if i64::from(n) > self.recv_window {
+            return Err(Http2ParseError::FlowControlError);
+        }
+        self.recv_window -= i64::from(n);
+        self.consumed += n;
+        Ok(())
+    }
+
+    /// The WINDOW_UPDATE increment owed to the peer, once consumed bytes
+    /// reach half the window; batching updates this way keeps a chatty
+    /// per-frame update exchange off the wire.
+    pub fn window_update_needed(&self) -> Option<u32> {
+        if self.consumed != 0 && u64::from(self.consumed) * 2 >= u64::from(self.initial_recv_window)
+        {
+            Some(self.consumed)
+        } else {
+            None
+        }
+    }
+
+    /// Restores receive credit after the WINDOW_UPDATE for `increment`
+    /// bytes has been written.
+    pub fn replenish_recv_window(&mut self, increment: u32) {
+        self.recv_window += i64::from(increment);
+        self.consumed = self.consumed.saturating_sub(increment);
+    }
+}
+
+/// Parses a WINDOW_UPDATE payload into its 31-bit increment (§6.9).
+pub fn parse_window_update(payload: &[u8]) -> Result<u32, Http2ParseError> {
+    if payload.len() != 4 {
+        return Err(Http2ParseError::InvalidFrameSize);
+    }
+    Ok(u32::from_be_bytes([payload[0], payload[1], payload[2], payload[3]]) & 0x7fff_ffff)
+}
+
+/// Connection-level HTTP/2 parser state: the peer's settings and frame
+/// decoding against them.
+#[derive(Debug, Clone, Default)]
+pub struct Http2Parser {
+    /// Settings advertised by the peer.
+    pub settings: Http2Settings,
+    /// HPACK state for the peer's header blocks; sized by
+    /// `SETTINGS_HEADER_TABLE_SIZE`.
+    pub hpack_decoder: HpackDecoder,
+}
+
+impl Http2Parser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses the fixed 9-byte frame header from the front of `buf`.
+    pub fn parse_frame_header(&self, buf: &[u8]) -> Result<FrameHeader, Http2ParseError> {
+        if buf.len() < FRAME_HEADER_LEN {
+            return Err(Http2ParseError::IncompleteFrame);
+        }
+        let length = u32::from_be_bytes([0, buf[0], buf[1], buf[2]]);
+        let frame_type = FrameType::from(buf[3]);
+        let stream_id = u32::from_be_bytes([buf[5], buf[6], buf[7], buf[8]]) & 0x7fff_ffff;
+        if length > self.settings.max_frame_size {
+            // §4.2: a frame that could alter connection state — HEADERS,
+            // SETTINGS, and everything with a fixed payload — is a
+            // connection error when oversized; DATA only kills its stream.
+            if frame_type == FrameType::Data && stream_id != 0 {
+                return Err(Http2ParseError::StreamFrameSizeError { stream_id });
+            }
+            return Err(Http2ParseError::InvalidFrameSize);
+        }
+        Ok(FrameHeader {
+            length,
+            frame_type,
+            flags: buf[4],
+            stream_id,
+        })
+    }
+
+    /// Parses one complete frame from the front of `buf`, returning the
+    /// frame and the number of bytes it consumed.
+    pub fn parse_frame<'a>(&self, buf: &'a [u8]) -> Result<(Frame<'a>, usize), Http2ParseError> {
+        let header = self.parse_frame_header(buf)?;
+        let total = FRAME_HEADER_LEN + header.length as usize;
+        if buf.len() < total {
+            return Err(Http2ParseError::IncompleteFrame);
+        }
+        Ok((
+            Frame {
+                header,
+                payload: &buf[FRAME_HEADER_LEN..total],
+            },
+            total,
+        ))
+    }
+
+    /// Applies a list of SETTINGS parameters from the peer, validating
+    /// each per RFC 7540 §6.5.2.
+    pub fn update_settings(&mut self, pairs: &[(u16, u32)]) -> Result<(), Http2ParseError> {
+        for &(id, value) in pairs {
+            match id {
+                SETTINGS_HEADER_TABLE_SIZE => {
+                    // The peer's table size bounds the encoder *it*
+                    // reads from — an encoder on this side would honor
+                    // it. The inbound decoder answers only to this
+                    // endpoint's own advertised setting
+                    // ([`crate::hpack::HpackDecoder::set_max_table_size`]).
+                    self.settings.header_table_size = value;
+                }
+                SETTINGS_ENABLE_PUSH => {
+                    self.settings.enable_push = match value {
+                        0 => false,
+                        1 => true,
+                        _ => return Err(Http2ParseError::InvalidSettings),
+                    };
+                }
+                SETTINGS_MAX_CONCURRENT_STREAMS => {
+                    self.settings.max_concurrent_streams = Some(value)
+                }
+                SETTINGS_INITIAL_WINDOW_SIZE => {
+                    if value > 0x7fff_ffff {
+                        return Err(Http2ParseError::FlowControlError);
+                    }
+                    self.settings.initial_window_size = value;
+                }
+                SETTINGS_MAX_FRAME_SIZE => {
+                    if !(16_384..=16_777_215).contains(&value) {
+                        return Err(Http2ParseError::InvalidSettings);
+                    }
+                    self.settings.max_frame_size = value;
+                }
+                SETTINGS_MAX_HEADER_LIST_SIZE => {
+                    self.settings.max_header_list_size = Some(value)
+                }
+                // Unknown identifiers must be ignored (§6.5.2).
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Builds outgoing HTTP/2 frames.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Http2FrameBuilder;
+
+impl Http2FrameBuilder {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Builds a raw frame with the given header fields and payload.
+    pub fn frame(
+        &self,
+        frame_type: FrameType,
+        flags: u8,
+        stream_id: u32,
+        payload: &[u8],
+    ) -> Vec<u8> {
+        let mut out = Vec::with_capacity(FRAME_HEADER_LEN + payload.len());
+        let length = (payload.len() as u32).to_be_bytes();
+        out.extend_from_slice(&length[1..4]);
+        out.push(frame_type.as_byte());
+        out.push(flags);
+        out.extend_from_slice(&(stream_id & 0x7fff_ffff).to_be_bytes());
+        out.extend_from_slice(payload);
+        out
+    }
+
+    /// Builds a SETTINGS frame advertising the given parameters.
+    pub fn settings_frame(&self, pairs: &[(u16, u32)]) -> Vec<u8> {
+        let mut payload = Vec::with_capacity(pairs.len() * 6);
+        for &(id, value) in pairs {
+            payload.extend_from_slice(&id.to_be_bytes());
+            payload.extend_from_slice(&value.to_be_bytes());
+        }
+        self.frame(FrameType::Settings, 0, 0, &payload)
+    }
+
+    /// Builds a SETTINGS acknowledgement.
+    pub fn settings_ack(&self) -> Vec<u8> {
+        self.frame(FrameType::Settings, FLAG_ACK, 0, &[])
+    }
+
+    /// Builds a PING acknowledgement echoing the opaque payload.
+    pub fn ping_ack(&self, payload: &[u8; 8]) -> Vec<u8> {
+        self.frame(FrameType::Ping, FLAG_ACK, 0, payload)
+    }
+
+    /// Builds a RST_STREAM frame (RFC 7540 §6.4).
+    pub fn rst_stream(&self, stream_id: u32, error_code: u32) -> Vec<u8> {
+        self.frame(FrameType::RstStream, 0, stream_id, &error_code.to_be_bytes())
+    }
+
+    /// Builds a WINDOW_UPDATE frame (RFC 7540 §6.9) granting the peer
+    /// `increment` more bytes of window.
+    pub fn window_update(&self, stream_id: u32, increment: u32) -> Vec<u8> {
+        self.frame(
+            FrameType::WindowUpdate,
+            0,
+            stream_id,
+            &(increment & 0x7fff_ffff).to_be_bytes(),
+        )
+    }
+
+    /// Builds a HEADERS frame carrying an encoded HPACK block, splitting
+    /// into HEADERS + CONTINUATION frames when the block exceeds
+    /// `max_frame_size` (RFC 7540 §6.2, §6.10).
+    ///
+    /// `END_STREAM` goes on the HEADERS frame; `END_HEADERS` goes on
+    /// whichever frame carries the final fragment. Pass `end_headers:
+    /// false` only when further CONTINUATION frames will follow.
+    pub fn headers_frame(
+        &self,
+        stream_id: u32,
+        block: &[u8],
+        end_stream: bool,
+        end_headers: bool,
+        max_frame_size: u32,
+    ) -> Vec<u8> {
+        let max = (max_frame_size as usize).max(1);
+        let first_len = block.len().min(max);
+        let (first, mut rest) = block.split_at(first_len);
+        let mut flags = if end_stream { FLAG_END_STREAM } else { 0 };
+        if rest.is_empty() && end_headers {
+            flags |= FLAG_END_HEADERS;
+        }
+        let mut out = self.frame(FrameType::Headers, flags, stream_id, first);
+        while !rest.is_empty() {
+            let fragment;
+            (fragment, rest) = rest.split_at(rest.len().min(max));
+            let flags = if rest.is_empty() && end_headers {
+                FLAG_END_HEADERS
+            } else {
+                0
+            };
+            out.extend_from_slice(&self.frame(
+                FrameType::Continuation,
+                flags,
+                stream_id,
+                fragment,
+            ));
+        }
+        out
+    }
+
+    /// Builds a body as DATA frames no larger than `max_frame_size`
+    /// (RFC 7540 §6.1), setting `END_STREAM` only on the final frame. An
+    /// empty body still yields one empty frame so the stream can close.
+    ///
+    /// Flow-control pacing is the caller's concern; a writer that must
+    /// respect the send window should use [`Http2ResponseWriter`] instead.
+    pub fn data_frames(
+        &self,
+        stream_id: u32,
+        body: &[u8],
+        max_frame_size: u32,
+        end_stream: bool,
+    ) -> Vec<Vec<u8>> {
+        let max = (max_frame_size as usize).max(1);
+        if body.is_empty() {
+            let flags = if end_stream { FLAG_END_STREAM } else { 0 };
+            return vec![self.frame(FrameType::Data, flags, stream_id, &[])];
+        }
+        let last = body.len().div_ceil(max) - 1;
+        body.chunks(max)
+            .enumerate()
+            .map(|(index, chunk)| {
+                let flags = if end_stream && index == last {
+                    FLAG_END_STREAM
+                } else {
+                    0
+                };
+                self.frame(FrameType::Data, flags, stream_id, chunk)
+            })
+            .collect()
+    }
+
+    /// Builds a GOAWAY frame (RFC 7540 §6.8) announcing the last stream the
+    /// sender will process and the reason for going away.
+    pub fn goaway(&self, last_stream_id: u32, error_code: u32) -> Vec<u8> {
+        let mut payload = Vec::with_capacity(8);
+        payload.extend_from_slice(&(last_stream_id & 0x7fff_ffff).to_be_bytes());
+        payload.extend_from_slice(&error_code.to_be_bytes());
+        self.frame(FrameType::Goaway, 0, 0, &payload)
+    }
+}
+
+/// Writes a response body as flow-controlled DATA frames (RFC 7540 §6.9).
+///
+/// Each produced frame is capped at `min(max_frame_size, send window)`;
+/// when the window runs dry the unsent remainder stays queued, and frame
+/// production resumes — by calling [`Http2ResponseWriter::next_frame`]
+/// again — once a WINDOW_UPDATE has credited the controller.
+#[derive(Debug, Clone)]
+pub struct Http2ResponseWriter {
+    stream_id: u32,
+    body: Vec<u8>,
+    offset: usize,
+    max_frame_size: u32,
+    end_stream_sent: bool,
+}
+
+impl Http2ResponseWriter {
+    pub fn new(stream_id: u32, body: Vec<u8>, max_frame_size: u32) -> Self {
+        Self {
+            stream_id,
+            body,
+            offset: 0,
+            max_frame_size,
+            end_stream_sent: false,
+        }
+    }
+
+    /// The body bytes not yet emitted as DATA frames.
+    pub fn remaining(&self) -> &[u8] {
+        &self.body[self.offset..]
+    }
+
+    /// Whether the whole body, END_STREAM included, has been emitted.
+    pub fn is_finished(&self) -> bool {
+        self.end_stream_sent
+    }
+
+    /// Produces the next DATA frame the send window allows, debiting the
+    /// controller for its payload. The frame covering the final body byte
+    /// carries END_STREAM (an empty body yields one empty END_STREAM
+    /// frame). Returns `None` when the body is drained or the window is
+    /// exhausted — in the latter case, call again after the peer's
+    /// WINDOW_UPDATE has been credited via
+    /// [`FlowController::credit_send_window`].
+    pub fn next_frame(&mut self, flow: &mut FlowController) -> Option<Vec<u8>> {
+        if self.end_stream_sent {
+            return None;
+        }
+        let pending = self.body.len() - self.offset;
+        let window = flow.send_window().max(0) as usize;
+        let len = pending.min(self.max_frame_size as usize).min(window);
+        if len == 0 && pending > 0 {
+            // Window exhausted; an empty frame here would spin forever.
+            return None;
+        }
+        flow.consume_send_window(len as u32).ok()?;
+        let payload = &self.body[self.offset..self.offset + len];
+        let last = len == pending;
+        let flags = if last { FLAG_END_STREAM } else { 0 };
+        let frame = Http2FrameBuilder::new().frame(FrameType::Data, flags, self.stream_id, payload);
+        self.offset += len;
+        self.end_stream_sent = last;
+        Some(frame)
+    }
+}
+
+/// Decodes the base64url `HTTP2-Settings` header value used by the h2c
+/// upgrade (RFC 7540 §3.2.1).
+pub(crate) fn decode_base64url(input: &[u8]) -> Option<Vec<u8>> {
+    crate::base64::decode_url_safe(input).ok()
+}
+
+impl Http2ParseError {
+    /// The coarse [`ErrorCode`] this parse failure maps to, from which the
+    /// GOAWAY / RST_STREAM code reported to the peer is picked.
+    pub fn to_error_code(&self) -> ErrorCode {
+        match self {
+            Http2ParseError::InvalidFrameSize
+            | Http2ParseError::StreamFrameSizeError { .. } => ErrorCode::FrameSizeError,
+            Http2ParseError::FlowControlError => ErrorCode::FlowControlError,
+            Http2ParseError::SettingsTimeout => ErrorCode::Timeout,
+            Http2ParseError::IncompleteFrame
+            | Http2ParseError::InvalidSettings
+            | Http2ParseError::InvalidStreamId
+            | Http2ParseError::InvalidPreface
+            | Http2ParseError::CompressionError
+            | Http2ParseError::HeaderBlockFlood => ErrorCode::ProtocolError,
+        }
+    }
+}
+
+impl fmt::Display for Http2ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match self {
+            Http2ParseError::IncompleteFrame => "incomplete frame",
+            Http2ParseError::InvalidFrameSize => "invalid frame size",
+            Http2ParseError::StreamFrameSizeError { .. } => "oversized frame on a stream",
+            Http2ParseError::InvalidSettings => "invalid SETTINGS",
+            Http2ParseError::InvalidStreamId => "frame on a forbidden stream id",
+            Http2ParseError::SettingsTimeout => "SETTINGS not acknowledged in time",
+            Http2ParseError::HeaderBlockFlood => "header block exceeds the configured caps",
+            Http2ParseError::InvalidPreface => "invalid connection preface",
+            Http2ParseError::FlowControlError => "flow-control error",
+            Http2ParseError::CompressionError => "header compression error",
+        };
+        f.write_str(msg)
+    }
+}
+
+impl std::error::Error for Http2ParseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_round_trips_through_parser() {
+        let builder = Http2FrameBuilder::new();
+        let bytes = builder.frame(FrameType::Ping, 0, 0, &[1, 2, 3, 4, 5, 6, 7, 8]);
+        let parser = Http2Parser::new();
+        let (frame, consumed) = parser.parse_frame(&bytes).unwrap();
+        assert_eq!(frame.header.frame_type, FrameType::Ping);
+        assert_eq!(frame.header.length, 8);
+        assert_eq!(frame.payload, &[1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(consumed, bytes.len());
+    }
+
+    #[test]
+    fn partial_frame_reports_incomplete() {
+        let builder = Http2FrameBuilder::new();
+        let bytes = builder.settings_frame(&[(SETTINGS_MAX_FRAME_SIZE, 16_384)]);
+        let parser = Http2Parser::new();
+        assert_eq!(
+            parser.parse_frame(&bytes[..bytes.len() - 1]).unwrap_err(),
+            Http2ParseError::IncompleteFrame
+        );
+    }
+
+    #[test]
+    fn priority_payload_parses_all_fields() {
+        // Exclusive dependency on stream 3 with wire weight 15 (effective 16).
+        let payload = [0x80, 0x00, 0x00, 0x03, 0x0f];
+        assert_eq!(
+            parse_priority(&payload).unwrap(),
+            Priority {
+                exclusive: true,
+                dependency: 3,
+                weight: 16,
+            }
+        );
+        assert!(!parse_priority(&[0x00, 0x00, 0x00, 0x05, 0xff]).unwrap().exclusive);
+        assert_eq!(parse_priority(&[0x00, 0x00, 0x00, 0x05, 0xff]).unwrap().weight, 256);
+        assert_eq!(
+            parse_priority(&payload[..4]).unwrap_err(),
+            Http2ParseError::InvalidFrameSize
+        );
+    }
+
+    #[test]
+    fn settings_payload_parses_pairs() {
+        let payload = [0x00, 0x05, 0x00, 0x01, 0x00, 0x00];
+        assert_eq!(
+            parse_settings(&payload).unwrap(),
+            vec![(SETTINGS_MAX_FRAME_SIZE, 65_536)]
+        );
+        assert_eq!(
+            parse_settings(&payload[..5]).unwrap_err(),
+            Http2ParseError::InvalidSettings
+        );
+    }
+
+    #[test]
+    fn oversized_settings_list_is_rejected() {
+        let mut payload = Vec::new();
+        for id in 0..=MAX_SETTINGS_PER_FRAME as u16 {
+            payload.extend_from_slice(&id.to_be_bytes());
+            payload.extend_from_slice(&0u32.to_be_bytes());
+        }
+        assert_eq!(
+            parse_settings(&payload).unwrap_err(),
+            Http2ParseError::InvalidSettings
+        );
+        // One entry fewer is within the cap.
+        assert!(parse_settings(&payload[..payload.len() - 6]).is_ok());
+    }
+
+    #[test]
+    fn duplicate_settings_identifier_is_rejected() {
+        let payload = [
+            0x00, 0x05, 0x00, 0x01, 0x00, 0x00, // MAX_FRAME_SIZE = 65536
+            0x00, 0x05, 0x00, 0x00, 0x40, 0x00, // MAX_FRAME_SIZE again
+        ];
+        assert_eq!(
+            parse_settings(&payload).unwrap_err(),
+            Http2ParseError::InvalidSettings
+        );
+    }
+
+    #[test]
+    fn recv_window_batches_updates_at_half_the_window() {
+        let mut flow = FlowController::new(100);
+        flow.consume_recv_window(30).unwrap();
+        assert_eq!(flow.window_update_needed(), None);
+        flow.consume_recv_window(20).unwrap();
+        assert_eq!(flow.window_update_needed(), Some(50));
+
+        flow.replenish_recv_window(50);
+        assert_eq!(flow.recv_window(), 100);
+        assert_eq!(flow.window_update_needed(), None);
+    }
+
+    #[test]
+    fn overrunning_the_recv_window_is_a_flow_control_error() {
+        let mut flow = FlowController::new(100);
+        flow.consume_recv_window(100).unwrap();
+        assert_eq!(
+            flow.consume_recv_window(1).unwrap_err(),
+            Http2ParseError::FlowControlError
+        );
+    }
+
+    #[test]
+    fn send_window_rejects_overflow_and_overdraft() {
+        let mut flow = FlowController::default();
+        flow.consume_send_window(65_535).unwrap();
+        assert_eq!(
+            flow.consume_send_window(1).unwrap_err(),
+            Http2ParseError::FlowControlError
+        );
+        flow.credit_send_window(65_535).unwrap();
+        assert_eq!(
+            flow.credit_send_window(0x7fff_ffff).unwrap_err(),
+            Http2ParseError::FlowControlError
+        );
+        assert_eq!(parse_window_update(&1024u32.to_be_bytes()).unwrap(), 1024);
+    }
+
+    fn header_list(fields: &[(&str, &str)]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        fields
+            .iter()
+            .map(|(n, v)| (n.as_bytes().to_vec(), v.as_bytes().to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn well_formed_pseudo_header_sections_validate() {
+        let headers = header_list(&[
+            (":method", "GET"),
+            (":scheme", "https"),
+            (":path", "/"),
+            (":authority", "example.com"),
+            ("accept", "*/*"),
+        ]);
+        assert!(validate_request_pseudo_headers(&headers).is_ok());
+
+        let connect = header_list(&[(":method", "CONNECT"), (":authority", "example.com:443")]);
+        assert!(validate_request_pseudo_headers(&connect).is_ok());
+    }
+
+    #[test]
+    fn header_names_must_be_lowercase() {
+        let lowercase = header_list(&[
+            (":method", "GET"),
+            ("content-type", "text/plain"),
+            ("x-request-id", "abc"),
+        ]);
+        assert!(validate_lowercase_header_names(&lowercase).is_ok());
+
+        let uppercased = header_list(&[(":method", "GET"), ("Content-Type", "text/plain")]);
+        assert_eq!(
+            validate_lowercase_header_names(&uppercased),
+            Err(ErrorCode::ProtocolError)
+        );
+    }
+
+    #[test]
+    fn misplaced_duplicate_and_missing_pseudo_headers_are_rejected() {
+        let after_regular = header_list(&[
+            (":method", "GET"),
+            (":scheme", "https"),
+            ("accept", "*/*"),
+            (":path", "/"),
+        ]);
+        assert_eq!(
+            validate_request_pseudo_headers(&after_regular),
+            Err(ErrorCode::ProtocolError)
+        );
+
+        let duplicate = header_list(&[
+            (":method", "GET"),
+            (":scheme", "https"),
+            (":path", "/"),
+            (":path", "/other"),
+        ]);
+        assert_eq!(
+            validate_request_pseudo_headers(&duplicate),
+            Err(ErrorCode::ProtocolError)
+        );
+
+        let missing_method = header_list(&[(":scheme", "https"), (":path", "/")]);
+        assert_eq!(
+            validate_request_pseudo_headers(&missing_method),
+            Err(ErrorCode::ProtocolError)
+        );
+
+        let response_pseudo = header_list(&[(":method", "GET"), (":status", "200")]);
+        assert_eq!(
+            validate_request_pseudo_headers(&response_pseudo),
+            Err(ErrorCode::ProtocolError)
+        );
+    }
+
+    #[test]
+    fn response_writer_caps_data_frames_to_the_send_window() {
+        let mut flow = FlowController::default();
+        // Shrink the send window to 16 KiB.
+        flow.consume_send_window(DEFAULT_WINDOW_SIZE - 16_384).unwrap();
+        let mut writer = Http2ResponseWriter::new(1, vec![0xaa; 100 * 1024], 4096);
+
+        let mut frames = Vec::new();
+        while let Some(frame) = writer.next_frame(&mut flow) {
+            frames.push(frame);
+        }
+        // 16 KiB of window in 4 KiB frames, then the window runs dry.
+        assert_eq!(frames.len(), 4);
+        let parser = Http2Parser::new();
+        for frame in &frames {
+            let (parsed, _) = parser.parse_frame(frame).unwrap();
+            assert_eq!(parsed.header.frame_type, FrameType::Data);
+            assert_eq!(parsed.payload.len(), 4096);
+            assert_eq!(parsed.header.flags & FLAG_END_STREAM, 0);
+        }
+        assert_eq!(writer.remaining().len(), 100 * 1024 - 16_384);
+        assert!(!writer.is_finished());
+        assert_eq!(flow.send_window(), 0);
+    }
+
+    #[test]
+    fn response_writer_resumes_after_window_updates() {
+        let mut flow = FlowController::default();
+        flow.consume_send_window(DEFAULT_WINDOW_SIZE - 16_384).unwrap();
+        let mut writer = Http2ResponseWriter::new(1, vec![0xbb; 100 * 1024], 16_384);
+
+        let mut frames = Vec::new();
+        loop {
+            while let Some(frame) = writer.next_frame(&mut flow) {
+                frames.push(frame);
+            }
+            if writer.is_finished() {
+                break;
+            }
+            // The peer grants more credit.
+            flow.credit_send_window(16_384).unwrap();
+        }
+        assert!(writer.remaining().is_empty());
+        let parser = Http2Parser::new();
+        let total: usize = frames
+            .iter()
+            .map(|f| parser.parse_frame(f).unwrap().0.payload.len())
+            .sum();
+        assert_eq!(total, 100 * 1024);
+        let (last, _) = parser.parse_frame(frames.last().unwrap()).unwrap();
+        assert_ne!(last.header.flags & FLAG_END_STREAM, 0);
+    }
+
+    #[test]
+    fn response_writer_ends_an_empty_body_with_one_frame() {
+        let mut flow = FlowController::default();
+        let mut writer = Http2ResponseWriter::new(3, Vec::new(), 16_384);
+        let frame = writer.next_frame(&mut flow).unwrap();
+        let parser = Http2Parser::new();
+        let (parsed, _) = parser.parse_frame(&frame).unwrap();
+        assert!(parsed.payload.is_empty());
+        assert_ne!(parsed.header.flags & FLAG_END_STREAM, 0);
+        assert!(writer.is_finished());
+        assert!(writer.next_frame(&mut flow).is_none());
+    }
+
+    #[test]
+    fn update_settings_applies_known_parameters() {
+        let mut parser = Http2Parser::new();
+        parser
+            .update_settings(&[
+                (SETTINGS_MAX_FRAME_SIZE, 65_536),
+                (SETTINGS_MAX_CONCURRENT_STREAMS, 7),
+                (0xff, 1), // unknown: ignored
+            ])
+            .unwrap();
+        assert_eq!(parser.settings.max_frame_size, 65_536);
+        assert_eq!(parser.settings.max_concurrent_streams, Some(7));
+    }
+
+    #[test]
+    fn update_settings_rejects_invalid_values() {
+        let mut parser = Http2Parser::new();
+        assert_eq!(
+            parser.update_settings(&[(SETTINGS_ENABLE_PUSH, 2)]).unwrap_err(),
+            Http2ParseError::InvalidSettings
+        );
+        assert_eq!(
+            parser
+                .update_settings(&[(SETTINGS_INITIAL_WINDOW_SIZE, 1 << 31)])
+                .unwrap_err(),
+            Http2ParseError::FlowControlError
+        );
+        assert_eq!(
+            parser.update_settings(&[(SETTINGS_MAX_FRAME_SIZE, 16_383)]).unwrap_err(),
+            Http2ParseError::InvalidSettings
+        );
+        // Valid boundary values are applied.
+        parser
+            .update_settings(&[
+                (SETTINGS_ENABLE_PUSH, 0),
+                (SETTINGS_INITIAL_WINDOW_SIZE, 0x7fff_ffff),
+            ])
+            .unwrap();
+        assert!(!parser.settings.enable_push);
+        assert_eq!(parser.settings.initial_window_size, 0x7fff_ffff);
+    }
+
+    #[test]
+    fn peer_header_table_setting_leaves_the_decoder_alone() {
+        let mut parser = Http2Parser::new();
+        parser.hpack_decoder.insert(b"older-name", b"older-value");
+        parser.hpack_decoder.insert(b"newer-name", b"newer-value");
+
+        // The peer's SETTINGS_HEADER_TABLE_SIZE bounds the encoder it
+        // reads from; the inbound decoder keeps its capacity and its
+        // entries, and the peer's next block needs no size update.
+        parser.update_settings(&[(SETTINGS_HEADER_TABLE_SIZE, 0)]).unwrap();
+        assert_eq!(parser.settings.header_table_size, 0);
+        assert_eq!(parser.hpack_decoder.len(), 2);
+        assert_eq!(parser.hpack_decoder.max_size(), 4096);
+        assert!(parser.hpack_decoder.decode_block(&[0x82], None).is_ok());
+    }
+
+    #[test]
+    fn base64url_decodes_settings_header() {
+        assert_eq!(
+            decode_base64url(b"AAUAAQAA").unwrap(),
+            [0x00, 0x05, 0x00, 0x01, 0x00, 0x00]
+        );
+        assert!(decode_base64url(b"!!invalid").is_none());
+    }
+
+    #[test]
+    fn small_header_block_fits_in_one_headers_frame() {
+        let builder = Http2FrameBuilder::new();
+        let bytes = builder.headers_frame(1, &[0x82, 0x86, 0x84], true, true, 16_384);
+        let parser = Http2Parser::new();
+        let (frame, consumed) = parser.parse_frame(&bytes).unwrap();
+        assert_eq!(frame.header.frame_type, FrameType::Headers);
+        assert_eq!(frame.header.flags, FLAG_END_STREAM | FLAG_END_HEADERS);
+        assert_eq!(frame.header.stream_id, 1);
+        assert_eq!(frame.payload, &[0x82, 0x86, 0x84]);
+        assert_eq!(consumed, bytes.len());
+    }
+
+    #[test]
+    fn large_header_block_splits_into_headers_plus_continuation() {
+        let builder = Http2FrameBuilder::new();
+        let block = vec![0x82; 20];
+        let bytes = builder.headers_frame(3, &block, false, true, 16);
+        let parser = Http2Parser::new();
+
+        let (first, consumed) = parser.parse_frame(&bytes).unwrap();
+        assert_eq!(first.header.frame_type, FrameType::Headers);
+        // END_HEADERS belongs to the continuation, END_STREAM was not asked
+        // for: no flags here.
+        assert_eq!(first.header.flags, 0);
+        assert_eq!(first.payload, &block[..16]);
+
+        let (second, rest) = parser.parse_frame(&bytes[consumed..]).unwrap();
+        assert_eq!(second.header.frame_type, FrameType::Continuation);
+        assert_eq!(second.header.flags, FLAG_END_HEADERS);
+        assert_eq!(second.header.stream_id, 3);
+        assert_eq!(second.payload, &block[16..]);
+        assert_eq!(consumed + rest, bytes.len());
+    }
+
+    #[test]
+    fn assembler_returns_the_block_when_end_headers_arrives() {
+        let mut assembler = HeaderBlockAssembler::new();
+        assembler.begin(1, &[0x82], 64).unwrap();
+        assert!(assembler.in_progress());
+        assert_eq!(assembler.stream_id(), 1);
+        assert_eq!(assembler.append(&[0x86], false, 64, 8).unwrap(), None);
+        assert_eq!(
+            assembler.append(&[0x84], true, 64, 8).unwrap(),
+            Some(vec![0x82, 0x86, 0x84])
+        );
+        assert!(!assembler.in_progress());
+    }
+
+    #[test]
+    fn assembler_caps_bytes_and_continuation_count() {
+        // Byte cap: the second fragment pushes the block past 16 bytes.
+        let mut assembler = HeaderBlockAssembler::new();
+        assembler.begin(1, &[0u8; 10], 16).unwrap();
+        assert_eq!(
+            assembler.append(&[0u8; 10], false, 16, 8).unwrap_err(),
+            Http2ParseError::HeaderBlockFlood
+        );
+        assert!(!assembler.in_progress(), "a flood drops the partial block");
+
+        // Frame-count cap: empty fragments dodge the byte cap but not the
+        // CONTINUATION counter.
+        assembler.begin(1, &[], 16).unwrap();
+        for _ in 0..4 {
+            assert_eq!(assembler.append(&[], false, 16, 4).unwrap(), None);
+        }
+        assert_eq!(
+            assembler.append(&[], false, 16, 4).unwrap_err(),
+            Http2ParseError::HeaderBlockFlood
+        );
+    }
+
+    #[test]
+    fn oversized_body_splits_with_end_stream_on_the_last_data_frame() {
+        let builder = Http2FrameBuilder::new();
+        let body = vec![0xaa; 40];
+        let frames = builder.data_frames(5, &body, 16, true);
+        assert_eq!(frames.len(), 3);
+
+        let parser = Http2Parser::new();
+        let mut reassembled = Vec::new();
+        for (index, bytes) in frames.iter().enumerate() {
+            let (frame, consumed) = parser.parse_frame(bytes).unwrap();
+            assert_eq!(frame.header.frame_type, FrameType::Data);
+            assert_eq!(frame.header.stream_id, 5);
+            let expected = if index == 2 { FLAG_END_STREAM } else { 0 };
+            assert_eq!(frame.header.flags, expected);
+            assert_eq!(consumed, bytes.len());
+            reassembled.extend_from_slice(frame.payload);
+        }
+        assert_eq!(reassembled, body);
+    }
+
+    #[test]
+    fn empty_body_closes_the_stream_with_one_empty_data_frame() {
+        let builder = Http2FrameBuilder::new();
+        let frames = builder.data_frames(5, &[], 16_384, true);
+        assert_eq!(frames.len(), 1);
+        let parser = Http2Parser::new();
+        let (frame, _) = parser.parse_frame(&frames[0]).unwrap();
+        assert_eq!(frame.header.frame_type, FrameType::Data);
+        assert_eq!(frame.header.flags, FLAG_END_STREAM);
+        assert!(frame.payload.is_empty());
+    }
+
+    #[test]
+    fn parse_errors_map_to_their_error_codes() {
+        assert_eq!(
+            Http2ParseError::InvalidSettings.to_error_code(),
+            ErrorCode::ProtocolError
+        );
+        assert_eq!(
+            Http2ParseError::InvalidPreface.to_error_code(),
+            ErrorCode::ProtocolError
+        );
+        assert_eq!(
+            Http2ParseError::InvalidFrameSize.to_error_code(),
+            ErrorCode::FrameSizeError
+        );
+        assert_eq!(
+            Http2ParseError::FlowControlError.to_error_code(),
+            ErrorCode::FlowControlError
+        );
+    }
+}
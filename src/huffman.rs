@@ -0,0 +1,213 @@
+//! HPACK Huffman coding (RFC 7541 §5.2), driven by the build-generated
+//! byte-at-a-time decoding LUT.
+
+use crate::tables::{LutEntry, RFC7541_STATIC_HUFFMAN_TABLE, STATE_ERROR};
+use std::fmt;
+
+include!(concat!(env!("OUT_DIR"), "/decoding_lut.rs"));
+
+/// Errors produced while decoding a Huffman-coded string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HuffmanError {
+    /// The input contains a bit sequence that is no symbol — including the
+    /// EOS symbol, which must never appear in data (RFC 7541 §5.2).
+    InvalidSequence,
+    /// The input ends in padding that is not a short ones-only EOS prefix.
+    InvalidPadding,
+}
+
+impl fmt::Display for HuffmanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match self {
+            HuffmanError::InvalidSequence => "invalid Huffman sequence",
+            HuffmanError::InvalidPadding => "invalid Huffman padding",
+        };
+        f.write_str(msg)
+    }
+}
+
+impl std::error::Error for HuffmanError {}
+
+/// A streaming Huffman decoder over the build-generated FSM.
+///
+/// Each input byte is one table lookup: the entry lists the symbols the
+/// byte completed and the state its trailing partial symbol lands in, so
+/// codes of any length — up to the 30-bit EOS neighborhood — decode
+/// without bit-by-bit tree walks.
+#[derive(Debug, Clone)]
+pub struct HuffmanDecoder {
+    state: u16,
+}
+
+impl Default for HuffmanDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HuffmanDecoder {
+    pub fn new() -> Self {
+        Self { state: 0 }
+    }
+
+    /// Consumes input bytes, appending decoded symbols to `out`. The
+    /// decoder keeps its position, so a string may arrive in pieces.
+    pub fn feed(&mut self, input: &[u8], out: &mut Vec<u8>) -> Result<(), HuffmanError> {
+        for &byte in input {
+            let entry = DECODING_LUT[self.state as usize][byte as usize];
+            if entry.next_state == STATE_ERROR {
+                return Err(HuffmanError::InvalidSequence);
+            }
+            out.extend_from_slice(&entry.symbols[..entry.emitted as usize]);
+            self.state = entry.next_state;
+        }
+        Ok(())
+    }
+
+    /// Validates the final position: a string must end on a symbol
+    /// boundary or on at most seven one-bits of padding.
+    pub fn finish(self) -> Result<(), HuffmanError> {
+        if ACCEPTING_STATES[self.state as usize] {
+            Ok(())
+        } else {
+            Err(HuffmanError::InvalidPadding)
+        }
+    }
+
+    /// Decodes a complete Huffman-coded string in one call.
+    pub fn decode(input: &[u8]) -> Result<Vec<u8>, HuffmanError> {
+        let mut decoder = Self::new();
+        let mut out = Vec::with_capacity(input.len() * 2);
+        decoder.feed(input, &mut out)?;
+        decoder.finish()?;
+        Ok(out)
+    }
+}
+
+/// The exact size in bytes of the Huffman encoding of `input`.
+///
+/// HPACK lets the encoder pick plain or Huffman form per string; comparing
+/// this against `input.len()` decides whether coding actually shrinks it.
+pub fn encoded_len(input: &[u8]) -> usize {
+    let bits: usize = input
+        .iter()
+        .map(|&byte| RFC7541_STATIC_HUFFMAN_TABLE[byte as usize].1 as usize)
+        .sum();
+    bits.div_ceil(8)
+}
+
+/// Huffman-encodes `input`, packing code bits MSB-first and filling the
+/// final partial byte with one-bits — the EOS prefix RFC 7541 §5.2
+/// requires as padding.
+pub fn encode(input: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(encoded_len(input));
+    let mut acc: u64 = 0;
+    let mut pending = 0usize;
+    for &byte in input {
+        let (code, bits) = RFC7541_STATIC_HUFFMAN_TABLE[byte as usize];
+        acc = (acc << bits) | u64::from(code);
+        pending += bits as usize;
+        while pending >= 8 {
+            pending -= 8;
+            out.push((acc >> pending) as u8);
+        }
+    }
+    if pending > 0 {
+        let padding = 8 - pending;
+        out.push(((acc << padding) as u8) | ((1 << padding) - 1));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tables::STATE_ERROR;
+
+    #[test]
+    fn root_state_decodes_every_byte() {
+        // The code is complete and EOS is 30 bits, so no single byte can
+        // fail from the root state.
+        for (byte, entry) in DECODING_LUT[0].iter().enumerate() {
+            assert_ne!(
+                entry.next_state, STATE_ERROR,
+                "byte {byte:#04x} has no decoding from the root state"
+            );
+        }
+        assert!(ACCEPTING_STATES[0]);
+    }
+
+    #[test]
+    fn decodes_symbols_longer_than_the_lookup_window() {
+        // `$` is a 13-bit symbol (0x1ff9) plus three one-bits of padding.
+        assert_eq!(HuffmanDecoder::decode(&[0xff, 0xcf]), Ok(vec![b'$']));
+        // Byte 128 is a 20-bit symbol (0xfffe6) plus four one-bits.
+        assert_eq!(HuffmanDecoder::decode(&[0xff, 0xfe, 0x6f]), Ok(vec![128]));
+    }
+
+    #[test]
+    fn decoder_streams_across_chunk_boundaries() {
+        let mut decoder = HuffmanDecoder::new();
+        let mut out = Vec::new();
+        // The same 20-bit symbol, delivered one byte at a time.
+        for byte in [0xff, 0xfe, 0x6f] {
+            decoder.feed(&[byte], &mut out).unwrap();
+        }
+        decoder.finish().unwrap();
+        assert_eq!(out, vec![128]);
+    }
+
+    #[test]
+    fn zero_bit_padding_is_rejected() {
+        // `0` (00000) followed by three zero bits of "padding".
+        assert_eq!(
+            HuffmanDecoder::decode(&[0x00]),
+            Err(HuffmanError::InvalidPadding)
+        );
+    }
+
+    #[test]
+    fn overlong_padding_is_rejected() {
+        // `0`, `A`, then a full byte of one-bits: thirteen padding bits.
+        assert_eq!(
+            HuffmanDecoder::decode(&[0x04, 0x3f, 0xff]),
+            Err(HuffmanError::InvalidPadding)
+        );
+    }
+
+    #[test]
+    fn encodes_the_rfc7541_examples() {
+        // RFC 7541 §C.4 request examples.
+        assert_eq!(
+            encode(b"www.example.com"),
+            [0xf1, 0xe3, 0xc2, 0xe5, 0xf2, 0x3a, 0x6b, 0xa0, 0xab, 0x90, 0xf4, 0xff]
+        );
+        assert_eq!(encode(b"no-cache"), [0xa8, 0xeb, 0x10, 0x64, 0x9c, 0xbf]);
+        assert_eq!(
+            encode(b"custom-key"),
+            [0x25, 0xa8, 0x49, 0xe9, 0x5b, 0xa9, 0x7d, 0x7f]
+        );
+        assert_eq!(encoded_len(b"www.example.com"), 12);
+        assert_eq!(encoded_len(b""), 0);
+    }
+
+    #[test]
+    fn encode_round_trips_through_the_decoder() {
+        let ascii = b"GET /index.html?q=1&lang=en HTTP/1.1";
+        assert_eq!(HuffmanDecoder::decode(&encode(ascii)).unwrap(), ascii);
+
+        let binary: Vec<u8> = (0u8..=255).collect();
+        let encoded = encode(&binary);
+        assert_eq!(encoded.len(), encoded_len(&binary));
+        assert_eq!(HuffmanDecoder::decode(&encoded).unwrap(), binary);
+    }
+
+    #[test]
+    fn eos_in_data_is_rejected() {
+        // Thirty-plus one-bits walk straight into the EOS symbol.
+        assert_eq!(
+            HuffmanDecoder::decode(&[0xff, 0xff, 0xff, 0xff]),
+            Err(HuffmanError::InvalidSequence)
+        );
+    }
+}
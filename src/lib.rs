@@ -0,0 +1,34 @@
+//! Angelax is a dependency-free, performance-oriented HTTP server toolkit.
+//!
+//! The crate provides the building blocks of an HTTP server — request
+//! parsing, connection handling, and protocol upgrades — without pulling in
+//! any external dependencies. The one exception is opt-in: the `tls`
+//! feature adds a rustls-backed handshake to [`tls::TlsAcceptor`].
+
+pub mod atomic;
+pub mod base64;
+pub mod conditional;
+pub mod connection;
+pub mod cors;
+pub mod error;
+pub mod form;
+pub mod hpack;
+pub mod http1;
+pub mod http2;
+pub mod huffman;
+pub mod limiter;
+pub mod logging;
+pub mod lut_generator;
+pub mod metrics;
+pub mod percent;
+pub mod pool;
+pub mod router;
+pub mod server;
+pub mod simd;
+pub mod sse;
+pub mod streams;
+pub mod tables;
+pub mod tls;
+pub mod websocket;
+
+pub use error::{Error, ErrorCode};
@@ -0,0 +1,320 @@
+//! Generates the byte-driven Huffman decoding LUT from the RFC 7541 code.
+//!
+//! `build.rs` includes this module textually and writes the formatted table
+//! to `OUT_DIR`, where [`crate::huffman`] includes it; keeping the generator
+//! in the source tree also lets the unit tests exercise it directly.
+
+use crate::tables::{LutEntry, RFC7541_STATIC_HUFFMAN_TABLE, STATE_ERROR};
+
+/// The symbol index RFC 7541 assigns to EOS; it must never occur in data.
+const EOS_SYMBOL: u16 = 256;
+
+/// A node of the Huffman code tree, stored in an arena.
+#[derive(Debug, Default)]
+struct Node {
+    left: Option<usize>,
+    right: Option<usize>,
+    symbol: Option<u16>,
+}
+
+/// The generated decoding tables: one row of 256 [`LutEntry`] values per
+/// FSM state, plus which states may legally end a string (their bit path
+/// is a ones-only EOS prefix shorter than a byte).
+#[derive(Debug)]
+pub struct GeneratedLut {
+    pub states: Vec<[LutEntry; 256]>,
+    pub accepting: Vec<bool>,
+}
+
+/// Builds the FSM over the RFC 7541 code tree.
+///
+/// Each FSM state is an interior node of the tree, identified by the bit
+/// path leading to it; state 0 is the root. A decoding step consumes one
+/// input byte, emits every symbol completed along the way, and lands in
+/// the state for the remaining partial bit path.
+#[derive(Debug)]
+pub struct LutGenerator {
+    nodes: Vec<Node>,
+    state_paths: Vec<Vec<bool>>,
+    /// Reverse mapping from bit path to state id, the inverse of
+    /// `state_paths`, so partial-symbol transitions resolve exactly.
+    state_ids: std::collections::HashMap<Vec<bool>, u16>,
+}
+
+impl Default for LutGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LutGenerator {
+    pub fn new() -> Self {
+        let mut generator = Self {
+            nodes: vec![Node::default()],
+            state_paths: Vec::new(),
+            state_ids: std::collections::HashMap::new(),
+        };
+        for (symbol, &(code, bits)) in RFC7541_STATIC_HUFFMAN_TABLE.iter().enumerate() {
+            generator.insert_code(symbol as u16, code, bits);
+        }
+        generator.build_fsm_states();
+        generator
+    }
+
+    /// Inserts one symbol's code into the tree, MSB first.
+    fn insert_code(&mut self, symbol: u16, code: u32, bits: u8) {
+        let mut node = 0;
+        for shift in (0..bits).rev() {
+            let bit = (code >> shift) & 1 == 1;
+            let child = self.child(node, bit);
+            node = match child {
+                Some(index) => index,
+                None => {
+                    self.nodes.push(Node::default());
+                    let index = self.nodes.len() - 1;
+                    let slot = if bit {
+                        &mut self.nodes[node].right
+                    } else {
+                        &mut self.nodes[node].left
+                    };
+                    *slot = Some(index);
+                    index
+                }
+            };
+        }
+        self.nodes[node].symbol = Some(symbol);
+    }
+
+    fn child(&self, node: usize, bit: bool) -> Option<usize> {
+        if bit {
+            self.nodes[node].right
+        } else {
+            self.nodes[node].left
+        }
+    }
+
+    fn is_leaf(&self, node: usize) -> bool {
+        self.nodes[node].symbol.is_some()
+    }
+
+    /// Enumerates every interior node as an FSM state, in breadth-first
+    /// order so the root's empty path becomes state 0.
+    fn build_fsm_states(&mut self) {
+        let mut queue = std::collections::VecDeque::from([(0usize, Vec::new())]);
+        while let Some((node, path)) = queue.pop_front() {
+            let state = self.state_paths.len() as u16;
+            self.state_paths.push(path.clone());
+            self.state_ids.insert(path.clone(), state);
+            for bit in [false, true] {
+                if let Some(child) = self.child(node, bit) {
+                    if !self.is_leaf(child) {
+                        let mut child_path = path.clone();
+                        child_path.push(bit);
+                        queue.push_back((child, child_path));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Resolves a partial bit path to its FSM state id, or [`STATE_ERROR`]
+    /// when the path names no interior node of the code tree.
+    fn find_state_for_path(&self, path: &[bool]) -> u16 {
+        self.state_ids.get(path).copied().unwrap_or(STATE_ERROR)
+    }
+
+    fn node_at_path(&self, path: &[bool]) -> usize {
+        let mut node = 0;
+        for &bit in path {
+            node = self.child(node, bit).expect("state path exists in the tree");
+        }
+        node
+    }
+
+    /// Computes the full decoding LUT: for every state and input byte, the
+    /// symbols completed and the state the byte ends in.
+    pub fn generate_lut(&self) -> GeneratedLut {
+        let mut states = Vec::with_capacity(self.state_paths.len());
+        for state_path in &self.state_paths {
+            let mut row = [LutEntry::default(); 256];
+            for (byte, entry) in row.iter_mut().enumerate() {
+                *entry = self.entry_for(state_path, byte as u8);
+            }
+            states.push(row);
+        }
+        // A string may end on a symbol boundary (the root) or on up to
+        // seven one-bits of padding; more padding, or any zero bit, is an
+        // error (RFC 7541 §5.2).
+        let accepting = self
+            .state_paths
+            .iter()
+            .map(|path| path.len() < 8 && path.iter().all(|&bit| bit))
+            .collect();
+        GeneratedLut { states, accepting }
+    }
+
+    /// Simulates feeding one byte to the FSM from the state at `path`.
+    fn entry_for(&self, path: &[bool], byte: u8) -> LutEntry {
+        let mut node = self.node_at_path(path);
+        let mut current_path = path.to_vec();
+        let mut symbols = [0u8; 2];
+        let mut emitted = 0u8;
+        for shift in (0..8).rev() {
+            let bit = (byte >> shift) & 1 == 1;
+            node = self
+                .child(node, bit)
+                .expect("the RFC 7541 code is complete");
+            current_path.push(bit);
+            if let Some(symbol) = self.nodes[node].symbol {
+                if symbol == EOS_SYMBOL {
+                    return LutEntry::default();
+                }
+                if emitted as usize == symbols.len() {
+                    return LutEntry::default();
+                }
+                symbols[emitted as usize] = symbol as u8;
+                emitted += 1;
+                node = 0;
+                current_path.clear();
+            }
+        }
+        let next_state = self.find_state_for_path(&current_path);
+        if next_state == STATE_ERROR {
+            return LutEntry::default();
+        }
+        LutEntry {
+            symbols,
+            emitted,
+            next_state,
+        }
+    }
+}
+
+/// Sanity-checks a generated LUT before it is written out.
+///
+/// The RFC 7541 code is complete (its Kraft sum is exactly one), so from
+/// the root state every byte must decode: any error entry in state 0, an
+/// out-of-range transition, or a non-accepting root are generator bugs.
+pub fn validate_lut(lut: &GeneratedLut) -> Result<(), String> {
+    let kraft: u64 = RFC7541_STATIC_HUFFMAN_TABLE
+        .iter()
+        .map(|&(_, bits)| 1u64 << (30 - bits as u32))
+        .sum();
+    if kraft != 1 << 30 {
+        return Err(format!("code table is not complete (Kraft sum {kraft})"));
+    }
+    if lut.states.is_empty() || lut.states.len() != lut.accepting.len() {
+        return Err("state and accepting tables disagree".to_owned());
+    }
+    if !lut.accepting[0] {
+        return Err("the root state must accept".to_owned());
+    }
+    for (state, row) in lut.states.iter().enumerate() {
+        for (byte, entry) in row.iter().enumerate() {
+            if state == 0 && entry.next_state == STATE_ERROR {
+                return Err(format!("error entry for byte {byte:#04x} in the root state"));
+            }
+            if entry.next_state != STATE_ERROR && entry.next_state as usize >= lut.states.len() {
+                return Err(format!(
+                    "state {state}, byte {byte:#04x}: transition to missing state {}",
+                    entry.next_state
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Renders the LUT as the Rust source included by `crate::huffman`.
+pub fn format_lut_as_rust_code(lut: &GeneratedLut) -> String {
+    use std::fmt::Write as _;
+    let mut out = String::with_capacity(lut.states.len() * 256 * 32);
+    let _ = writeln!(out, "/// Number of FSM states in the decoding LUT.");
+    let _ = writeln!(out, "pub const FSM_STATE_COUNT: usize = {};", lut.states.len());
+    let _ = writeln!(out, "/// Byte-driven Huffman decoding table, one row per FSM state.");
+    let _ = writeln!(
+        out,
+        "pub static DECODING_LUT: [[LutEntry; 256]; FSM_STATE_COUNT] = ["
+    );
+    for row in &lut.states {
+        let _ = writeln!(out, "    [");
+        for entry in row {
+            let _ = writeln!(
+                out,
+                "        LutEntry::new({}, {}, {}, {}),",
+                entry.symbols[0], entry.symbols[1], entry.emitted, entry.next_state
+            );
+        }
+        let _ = writeln!(out, "    ],");
+    }
+    let _ = writeln!(out, "];");
+    let _ = writeln!(
+        out,
+        "/// Whether each state may legally end a Huffman string (RFC 7541 §5.2)."
+    );
+    let _ = writeln!(
+        out,
+        "pub static ACCEPTING_STATES: [bool; FSM_STATE_COUNT] = ["
+    );
+    for &accepting in &lut.accepting {
+        let _ = writeln!(out, "    {accepting},");
+    }
+    let _ = writeln!(out, "];");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Decodes a byte sequence by stepping the generated LUT directly.
+    fn decode_with_lut(lut: &GeneratedLut, input: &[u8]) -> Option<Vec<u8>> {
+        let mut state = 0u16;
+        let mut out = Vec::new();
+        for &byte in input {
+            let entry = lut.states[state as usize][byte as usize];
+            if entry.next_state == STATE_ERROR {
+                return None;
+            }
+            out.extend_from_slice(&entry.symbols[..entry.emitted as usize]);
+            state = entry.next_state;
+        }
+        lut.accepting[state as usize].then_some(out)
+    }
+
+    #[test]
+    fn paths_resolve_to_distinct_states() {
+        let generator = LutGenerator::new();
+        assert_eq!(generator.find_state_for_path(&[]), 0);
+        // `1` and `11` are interior nodes of the code tree with their own
+        // states; a path ending inside a leaf is unknown.
+        let one = generator.find_state_for_path(&[true]);
+        let ones = generator.find_state_for_path(&[true, true]);
+        assert_ne!(one, STATE_ERROR);
+        assert_ne!(ones, STATE_ERROR);
+        assert_ne!(one, ones);
+        // `00000` is the complete code for `0`, a leaf, not a state.
+        assert_eq!(
+            generator.find_state_for_path(&[false; 5]),
+            STATE_ERROR
+        );
+    }
+
+    #[test]
+    fn multi_byte_symbol_decodes_through_fsm_continuation() {
+        let lut = LutGenerator::new().generate_lut();
+        // `$` is 13 bits (0x1ff9); with three one-bits of padding it spans
+        // two bytes and forces a continuation through the FSM.
+        assert_eq!(decode_with_lut(&lut, &[0xff, 0xcf]), Some(vec![b'$']));
+        // `0` then `A`: 5 + 6 bits, the second symbol straddling the byte
+        // boundary, plus five one-bits of padding.
+        // 00000 100001 11111 -> 0000_0100, 0011_1111
+        assert_eq!(decode_with_lut(&lut, &[0x04, 0x3f]), Some(vec![b'0', b'A']));
+    }
+
+    #[test]
+    fn generated_lut_validates() {
+        let lut = LutGenerator::new().generate_lut();
+        validate_lut(&lut).unwrap();
+    }
+}
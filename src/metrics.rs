@@ -0,0 +1,410 @@
+//! Metrics recorded by the parsers and connections.
+
+use crate::atomic::AtomicCounter;
+use crate::pool::BufferPool;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// A histogram of nanosecond durations in power-of-two buckets.
+///
+/// Bucket `i` counts durations in `[2^(i-1), 2^i)`, so recording is one
+/// `leading_zeros` plus one atomic increment — O(1) and allocation-free.
+/// Percentiles are approximate: a bucket's upper bound is reported.
+#[derive(Debug)]
+struct LatencyHistogram {
+    buckets: [AtomicU64; 64],
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self {
+            buckets: [const { AtomicU64::new(0) }; 64],
+        }
+    }
+}
+
+impl LatencyHistogram {
+    fn reset(&self) {
+        for bucket in &self.buckets {
+            bucket.swap(0, Ordering::Relaxed);
+        }
+    }
+
+    fn record(&self, ns: u64) {
+        let index = usize::min(64 - ns.leading_zeros() as usize, 63);
+        self.buckets[index].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The approximate duration at percentile `p` (0–100), or zero when
+    /// nothing has been recorded.
+    fn percentile(&self, p: f64) -> u64 {
+        let total: u64 = self
+            .buckets
+            .iter()
+            .map(|bucket| bucket.load(Ordering::Relaxed))
+            .sum();
+        if total == 0 {
+            return 0;
+        }
+        let rank = ((p.clamp(0.0, 100.0) / 100.0 * total as f64).ceil() as u64).max(1);
+        let mut cumulative = 0;
+        for (index, bucket) in self.buckets.iter().enumerate() {
+            cumulative += bucket.load(Ordering::Relaxed);
+            if cumulative >= rank {
+                return bucket_upper_bound(index);
+            }
+        }
+        u64::MAX
+    }
+}
+
+/// The largest duration that falls into bucket `index`.
+fn bucket_upper_bound(index: usize) -> u64 {
+    match index {
+        0 => 0,
+        63 => u64::MAX,
+        _ => (1u64 << index) - 1,
+    }
+}
+
+/// A plain-data copy of [`ParserMetrics`] at one instant, for reporting.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ParserSnapshot {
+    pub requests_parsed: u64,
+    pub parse_errors: u64,
+    pub total_parse_time_ns: u64,
+}
+
+/// Aggregate counters for request parsing, safe to share across threads.
+#[derive(Debug, Default)]
+pub struct ParserMetrics {
+    requests_parsed: AtomicCounter,
+    parse_errors: AtomicCounter,
+    parse_time_histogram: LatencyHistogram,
+    /// Total nanoseconds spent in successful parses; the average is derived
+    /// on demand so no incremental-mean formula can drift, and saturation
+    /// bounds the (584-year) accumulation overflow instead of wrapping into
+    /// a nonsense average.
+    total_parse_time_ns: AtomicU64,
+}
+
+impl ParserMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records one successful parse and adds its duration to the running
+    /// total.
+    pub fn record_parse(&self, duration: Duration) {
+        let duration_ns = u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX);
+        self.requests_parsed.increment();
+        self.parse_time_histogram.record(duration_ns);
+        let mut current = self.total_parse_time_ns.load(Ordering::Relaxed);
+        loop {
+            let next = current.saturating_add(duration_ns);
+            match self.total_parse_time_ns.compare_exchange_weak(
+                current,
+                next,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => break,
+                Err(observed) => current = observed,
+            }
+        }
+    }
+
+    /// Records a request that failed to parse.
+    pub fn record_error(&self) {
+        self.parse_errors.increment();
+    }
+
+    pub fn requests_parsed(&self) -> u64 {
+        self.requests_parsed.load()
+    }
+
+    pub fn parse_errors(&self) -> u64 {
+        self.parse_errors.load()
+    }
+
+    /// The exact arithmetic mean of all recorded parse durations, or zero
+    /// before the first parse.
+    pub fn average_parse_time_ns(&self) -> u64 {
+        let parsed = self.requests_parsed.load();
+        if parsed == 0 {
+            return 0;
+        }
+        self.total_parse_time_ns.load(Ordering::Relaxed) / parsed
+    }
+
+    /// The approximate parse time at percentile `p` (0–100), reported as
+    /// the upper bound of the histogram bucket containing that rank.
+    pub fn percentile(&self, p: f64) -> u64 {
+        self.parse_time_histogram.percentile(p)
+    }
+
+    /// A plain-data copy of the counters, leaving them untouched.
+    pub fn snapshot(&self) -> ParserSnapshot {
+        ParserSnapshot {
+            requests_parsed: self.requests_parsed.load(),
+            parse_errors: self.parse_errors.load(),
+            total_parse_time_ns: self.total_parse_time_ns.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Zeroes every counter and clears the histogram, returning what was
+    /// drained. The counters are taken with `swap(0)`, so an increment
+    /// racing the reset lands in exactly one reporting interval — either
+    /// the returned snapshot or the next one — never in both or neither.
+    pub fn reset(&self) -> ParserSnapshot {
+        let snapshot = ParserSnapshot {
+            requests_parsed: self.requests_parsed.swap(0),
+            parse_errors: self.parse_errors.swap(0),
+            total_parse_time_ns: self.total_parse_time_ns.swap(0, Ordering::Relaxed),
+        };
+        self.parse_time_histogram.reset();
+        snapshot
+    }
+}
+
+/// Per-connection counters, owned by a single [`crate::connection::Connection`].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ConnectionMetrics {
+    pub bytes_read: u64,
+    pub bytes_written: u64,
+    pub requests_served: u64,
+}
+
+/// The label applied to a buffer-pool tier, by position.
+fn tier_label(index: usize) -> &'static str {
+    match index {
+        0 => "small",
+        1 => "medium",
+        _ => "large",
+    }
+}
+
+/// Renders the given metrics in the Prometheus text exposition format,
+/// ready to serve from a scrape endpoint.
+pub fn render_prometheus(
+    parser: &ParserMetrics,
+    connections: &ConnectionMetrics,
+    buffers: &BufferPool,
+) -> String {
+    use std::fmt::Write as _;
+    let mut out = String::with_capacity(1024);
+
+    let counter = |out: &mut String, name: &str, help: &str, value: u64| {
+        let _ = writeln!(out, "# HELP {name} {help}");
+        let _ = writeln!(out, "# TYPE {name} counter");
+        let _ = writeln!(out, "{name} {value}");
+    };
+    counter(
+        &mut out,
+        "angelax_requests_parsed_total",
+        "Requests successfully parsed.",
+        parser.requests_parsed(),
+    );
+    counter(
+        &mut out,
+        "angelax_parse_errors_total",
+        "Requests that failed to parse.",
+        parser.parse_errors(),
+    );
+    counter(
+        &mut out,
+        "angelax_connection_bytes_read_total",
+        "Bytes read from client connections.",
+        connections.bytes_read,
+    );
+    counter(
+        &mut out,
+        "angelax_connection_bytes_written_total",
+        "Bytes written to client connections.",
+        connections.bytes_written,
+    );
+    counter(
+        &mut out,
+        "angelax_requests_served_total",
+        "Requests served over client connections.",
+        connections.requests_served,
+    );
+    counter(
+        &mut out,
+        "angelax_buffer_pool_reclaimed_bytes_total",
+        "Buffer capacity reclaimed by shrinking overgrown buffers.",
+        buffers.reclaimed_bytes() as u64,
+    );
+
+    let name = "angelax_parse_time_ns";
+    let _ = writeln!(out, "# HELP {name} Approximate parse-time quantiles.");
+    let _ = writeln!(out, "# TYPE {name} gauge");
+    for (quantile, p) in [("0.5", 50.0), ("0.95", 95.0), ("0.99", 99.0)] {
+        let _ = writeln!(out, "{name}{{quantile=\"{quantile}\"}} {}", parser.percentile(p));
+    }
+
+    let name = "angelax_buffer_pool_available";
+    let _ = writeln!(out, "# HELP {name} Buffers currently checked in, per tier.");
+    let _ = writeln!(out, "# TYPE {name} gauge");
+    let occupancy = buffers.tier_occupancy();
+    for (index, tier) in occupancy.iter().enumerate() {
+        let _ = writeln!(out, "{name}{{tier=\"{}\"}} {}", tier_label(index), tier.available);
+    }
+    let name = "angelax_buffer_pool_slots";
+    let _ = writeln!(out, "# HELP {name} Total buffer slots, per tier.");
+    let _ = writeln!(out, "# TYPE {name} gauge");
+    for (index, tier) in occupancy.iter().enumerate() {
+        let _ = writeln!(out, "{name}{{tier=\"{}\"}} {}", tier_label(index), tier.slots);
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_metrics_count_events() {
+        let metrics = ParserMetrics::new();
+        metrics.record_parse(Duration::from_nanos(100));
+        metrics.record_parse(Duration::from_nanos(200));
+        metrics.record_error();
+        assert_eq!(metrics.requests_parsed(), 2);
+        assert_eq!(metrics.parse_errors(), 1);
+        assert!(metrics.average_parse_time_ns() > 0);
+    }
+
+    #[test]
+    fn average_is_the_exact_mean() {
+        let metrics = ParserMetrics::new();
+        assert_eq!(metrics.average_parse_time_ns(), 0);
+        let durations = [120u64, 80, 400, 200, 1000];
+        for &ns in &durations {
+            metrics.record_parse(Duration::from_nanos(ns));
+        }
+        let mean = durations.iter().sum::<u64>() / durations.len() as u64;
+        assert_eq!(metrics.average_parse_time_ns(), mean);
+    }
+
+    #[test]
+    fn percentiles_reflect_a_skewed_distribution() {
+        let metrics = ParserMetrics::new();
+        // 98 fast parses around 100ns, 2 slow outliers around 100µs.
+        for _ in 0..98 {
+            metrics.record_parse(Duration::from_nanos(100));
+        }
+        for _ in 0..2 {
+            metrics.record_parse(Duration::from_nanos(100_000));
+        }
+        let p50 = metrics.percentile(50.0);
+        let p99 = metrics.percentile(99.0);
+        assert!(p99 > p50, "tail must exceed the median ({p50} vs {p99})");
+        // 100ns lands in the [64, 128) bucket.
+        assert_eq!(p50, 127);
+        // 100µs lands in the [65536, 131072) bucket.
+        assert_eq!(p99, 131_071);
+    }
+
+    #[test]
+    fn percentile_of_empty_histogram_is_zero() {
+        let metrics = ParserMetrics::new();
+        assert_eq!(metrics.percentile(99.0), 0);
+    }
+
+    #[test]
+    fn prometheus_output_is_well_formed() {
+        let parser = ParserMetrics::new();
+        parser.record_parse(Duration::from_nanos(500));
+        parser.record_error();
+        let connections = ConnectionMetrics {
+            bytes_read: 10,
+            bytes_written: 20,
+            requests_served: 1,
+        };
+        let buffers = BufferPool::new(2);
+        let _held = buffers.get(1000);
+
+        let rendered = render_prometheus(&parser, &connections, &buffers);
+
+        for name in [
+            "angelax_requests_parsed_total",
+            "angelax_parse_errors_total",
+            "angelax_connection_bytes_read_total",
+            "angelax_buffer_pool_reclaimed_bytes_total",
+            "angelax_parse_time_ns{quantile=\"0.99\"}",
+            "angelax_buffer_pool_available{tier=\"small\"}",
+            "angelax_buffer_pool_slots{tier=\"large\"}",
+        ] {
+            assert!(rendered.contains(name), "missing {name}");
+        }
+        assert!(rendered.contains("angelax_buffer_pool_available{tier=\"small\"} 1"));
+
+        // Every line is a comment or a `name[{labels}] value` sample whose
+        // value parses as a number, per the exposition format.
+        for line in rendered.lines() {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (name, value) = line.rsplit_once(' ').expect("sample has a value");
+            assert!(!name.is_empty());
+            assert!(value.parse::<f64>().is_ok(), "unparsable value in {line}");
+        }
+    }
+
+    #[test]
+    fn reset_drains_the_counters() {
+        let metrics = ParserMetrics::new();
+        metrics.record_parse(Duration::from_nanos(100));
+        metrics.record_error();
+
+        let drained = metrics.reset();
+        assert_eq!(drained.requests_parsed, 1);
+        assert_eq!(drained.parse_errors, 1);
+        assert_eq!(drained.total_parse_time_ns, 100);
+        assert_eq!(metrics.snapshot(), ParserSnapshot::default());
+        assert_eq!(metrics.percentile(99.0), 0);
+    }
+
+    #[test]
+    fn concurrent_resets_never_lose_an_increment() {
+        use std::sync::Arc;
+
+        let metrics = Arc::new(ParserMetrics::new());
+        const THREADS: u64 = 4;
+        const PER_THREAD: u64 = 10_000;
+
+        let writers: Vec<_> = (0..THREADS)
+            .map(|_| {
+                let metrics = Arc::clone(&metrics);
+                std::thread::spawn(move || {
+                    for _ in 0..PER_THREAD {
+                        metrics.record_parse(Duration::from_nanos(1));
+                    }
+                })
+            })
+            .collect();
+
+        // Drain repeatedly while the writers run; every increment must
+        // land in exactly one interval.
+        let mut collected = 0;
+        for _ in 0..100 {
+            collected += metrics.reset().requests_parsed;
+            std::thread::yield_now();
+        }
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        collected += metrics.reset().requests_parsed;
+        assert_eq!(collected, THREADS * PER_THREAD);
+    }
+
+    #[test]
+    fn average_survives_extreme_durations() {
+        let metrics = ParserMetrics::new();
+        metrics.record_parse(Duration::from_nanos(u64::MAX));
+        metrics.record_parse(Duration::from_nanos(u64::MAX));
+        // The total saturates instead of wrapping into a tiny average.
+        assert_eq!(metrics.average_parse_time_ns(), u64::MAX / 2);
+    }
+}
@@ -0,0 +1,105 @@
+//! Percent-encoding for constructed URIs and header values — the inverse
+//! of the decoding applied when parsing paths and form bodies.
+
+use std::borrow::Cow;
+
+/// Which bytes a component must escape, per the WHATWG URL percent-encode
+/// sets. Every set escapes control bytes and everything outside ASCII;
+/// the variants add the characters that would change the component's
+/// meaning if left literal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncodeSet {
+    /// A path segment: the query set plus `?`, `` ` ``, `{` and `}`.
+    Path,
+    /// The query string: space, `"`, `#`, `<` and `>`.
+    Query,
+    /// The fragment: space, `"`, `<`, `>` and `` ` ``.
+    Fragment,
+    /// The userinfo subcomponent: the path set plus `/`, `:`, `;`, `=`,
+    /// `@`, `[`, `\`, `]`, `^` and `|`.
+    Userinfo,
+}
+
+impl EncodeSet {
+    /// Whether `byte` must be escaped in this component.
+    fn must_encode(self, byte: u8) -> bool {
+        if !(0x20..0x7f).contains(&byte) {
+            return true;
+        }
+        let query = matches!(byte, b' ' | b'"' | b'#' | b'<' | b'>');
+        let path = query || matches!(byte, b'?' | b'`' | b'{' | b'}');
+        match self {
+            EncodeSet::Fragment => matches!(byte, b' ' | b'"' | b'<' | b'>' | b'`'),
+            EncodeSet::Query => query,
+            EncodeSet::Path => path,
+            EncodeSet::Userinfo => {
+                path || matches!(
+                    byte,
+                    b'/' | b':' | b';' | b'=' | b'@' | b'[' | b'\\' | b']' | b'^' | b'|'
+                )
+            }
+        }
+    }
+}
+
+/// Percent-encodes `input` for the given component, borrowing when no
+/// byte needs escaping. Escapes use uppercase hex digits, the canonical
+/// form both RFC 3986 §2.1 and the URL spec produce.
+pub fn percent_encode(input: &[u8], set: EncodeSet) -> Cow<'_, str> {
+    if input.iter().all(|&b| !set.must_encode(b)) {
+        // Unescaped bytes are printable ASCII, so this cannot fail.
+        return Cow::Borrowed(std::str::from_utf8(input).expect("printable ASCII"));
+    }
+    const HEX: &[u8; 16] = b"0123456789ABCDEF";
+    let mut out = String::with_capacity(input.len() + 8);
+    for &byte in input {
+        if set.must_encode(byte) {
+            out.push('%');
+            out.push(HEX[usize::from(byte >> 4)] as char);
+            out.push(HEX[usize::from(byte & 0xf)] as char);
+        } else {
+            out.push(byte as char);
+        }
+    }
+    Cow::Owned(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_with_spaces_and_utf8_is_escaped() {
+        let encoded = percent_encode("/files/résumé v2.pdf".as_bytes(), EncodeSet::Path);
+        assert_eq!(encoded, "/files/r%C3%A9sum%C3%A9%20v2.pdf");
+    }
+
+    #[test]
+    fn unreserved_input_stays_borrowed() {
+        let encoded = percent_encode(b"/static/app-1.2.3.js", EncodeSet::Path);
+        assert!(matches!(encoded, Cow::Borrowed("/static/app-1.2.3.js")));
+    }
+
+    #[test]
+    fn sets_escape_their_own_reserved_characters() {
+        // `?` ends a path but is literal inside the query it starts.
+        assert_eq!(percent_encode(b"a?b", EncodeSet::Path), "a%3Fb");
+        assert_eq!(percent_encode(b"a?b", EncodeSet::Query), "a?b");
+        // `:` and `@` delimit userinfo but are fine in a path.
+        assert_eq!(percent_encode(b"u:p@h", EncodeSet::Userinfo), "u%3Ap%40h");
+        assert_eq!(percent_encode(b"u:p@h", EncodeSet::Path), "u:p@h");
+        // The fragment set is the loosest: `#` has already done its job.
+        assert_eq!(percent_encode(b"#x `", EncodeSet::Fragment), "#x%20%60");
+    }
+
+    #[test]
+    fn encoded_values_round_trip_through_the_form_decoder() {
+        let original = "weird value: 100% \"done\" ✓";
+        let encoded = percent_encode(original.as_bytes(), EncodeSet::Userinfo);
+        let body = format!("k={encoded}");
+        let (_, decoded) = crate::form::parse_urlencoded(body.as_bytes())
+            .next()
+            .unwrap();
+        assert_eq!(decoded, original);
+    }
+}
@@ -0,0 +1,579 @@
+//! Lock-free object pooling for connection and buffer reuse.
+//!
+//! A pool owns a fixed array of slots guarded by an atomic freelist: a slot
+//! listed as free holds an initialized object available for checkout. `get`
+//! claims a slot and moves the value out, so a checked-out slot is logically
+//! uninitialized and its stale bytes are never read again; `PooledObject`
+//! moves the value back in before re-publishing the slot. This protocol
+//! makes double-reads (and therefore double-drops) impossible by
+//! construction.
+//!
+//! Two freelist backings are offered: an [`AtomicBitmap`] scan that supports
+//! cheap occupancy queries, and an [`AtomicStack`] giving O(1) claim and
+//! release for large pools.
+
+use std::cell::UnsafeCell;
+use std::mem::{ManuallyDrop, MaybeUninit};
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::atomic::{AtomicBitmap, AtomicStack};
+
+/// The freelist strategy backing an [`ObjectPool`].
+enum FreeList {
+    /// One bit per slot, set when the slot is available; claimed by a
+    /// linear CAS scan.
+    Bitmap(AtomicBitmap),
+    /// Free slot indices on a lock-free stack; claimed in O(1). The side
+    /// counter tracks availability, which the stack cannot report itself.
+    Stack {
+        indices: AtomicStack<usize>,
+        available: AtomicUsize,
+    },
+}
+
+impl FreeList {
+    fn claim(&self) -> Option<usize> {
+        match self {
+            FreeList::Bitmap(bitmap) => bitmap.find_and_clear(),
+            FreeList::Stack { indices, available } => {
+                let slot = indices.pop()?;
+                available.fetch_sub(1, Ordering::Relaxed);
+                Some(slot)
+            }
+        }
+    }
+
+    fn release(&self, slot: usize) {
+        match self {
+            FreeList::Bitmap(bitmap) => {
+                bitmap.set(slot);
+            }
+            FreeList::Stack { indices, available } => {
+                indices
+                    .push(slot)
+                    .unwrap_or_else(|_| unreachable!("freelist sized to capacity"));
+                available.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    fn available(&self) -> usize {
+        match self {
+            FreeList::Bitmap(bitmap) => bitmap.count_set(),
+            FreeList::Stack { available, .. } => available.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// A fixed-capacity, lock-free pool of reusable objects.
+pub struct ObjectPool<T> {
+    slots: Vec<UnsafeCell<MaybeUninit<T>>>,
+    free: FreeList,
+    capacity: usize,
+    /// Number of threads parked in [`ObjectPool::get_blocking`]; only when
+    /// this is non-zero does a return take the wait lock to notify, keeping
+    /// the uncontended return path lock-free.
+    waiter_count: AtomicUsize,
+    wait_lock: Mutex<()>,
+    slot_freed: Condvar,
+}
+
+// SAFETY: slots are only accessed by the thread that atomically claimed (or
+// is returning) the corresponding freelist entry, so `&ObjectPool` may be
+// shared across threads whenever the objects themselves can be sent between
+// them.
+unsafe impl<T: Send> Sync for ObjectPool<T> {}
+unsafe impl<T: Send> Send for ObjectPool<T> {}
+
+impl<T> ObjectPool<T> {
+    /// Creates a bitmap-backed pool of `capacity` objects, eagerly
+    /// constructed by `init`. This backing answers occupancy queries in a
+    /// single pass and is the default.
+    pub fn new(capacity: usize, init: impl FnMut() -> T) -> Self {
+        let bitmap = AtomicBitmap::new(capacity);
+        for slot in 0..capacity {
+            bitmap.set(slot);
+        }
+        Self::with_freelist(capacity, init, FreeList::Bitmap(bitmap))
+    }
+
+    /// Creates a pool whose free slots are tracked on an [`AtomicStack`],
+    /// trading the bitmap's cheap occupancy scan for O(1) claim and release
+    /// under contention.
+    pub fn new_stack_backed(capacity: usize, init: impl FnMut() -> T) -> Self {
+        let indices = AtomicStack::new(capacity);
+        for slot in 0..capacity {
+            indices
+                .push(slot)
+                .unwrap_or_else(|_| unreachable!("freelist sized to capacity"));
+        }
+        Self::with_freelist(
+            capacity,
+            init,
+            FreeList::Stack {
+                indices,
+                available: AtomicUsize::new(capacity),
+            },
+        )
+    }
+
+    fn with_freelist(capacity: usize, mut init: impl FnMut() -> T, free: FreeList) -> Self {
+        assert!(capacity > 0, "pool capacity must be non-zero");
+        let slots = (0..capacity)
+            .map(|_| UnsafeCell::new(MaybeUninit::new(init())))
+            .collect();
+        Self {
+            slots,
+            free,
+            capacity,
+            waiter_count: AtomicUsize::new(0),
+            wait_lock: Mutex::new(()),
+            slot_freed: Condvar::new(),
+        }
+    }
+
+    /// The total number of slots.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// The number of objects currently available for checkout.
+    pub fn available(&self) -> usize {
+        self.free.available()
+    }
+
+    /// Checks an object out of the pool, or returns `None` when every slot
+    /// is in use.
+    pub fn get(&self) -> Option<PooledObject<'_, T>> {
+        self.claim_slot().map(|slot| self.checkout(slot))
+    }
+
+    /// Checks an object out of the pool, parking the caller until a slot
+    /// frees up or `timeout` elapses. The uncontended case claims a slot
+    /// without touching the wait lock.
+    pub fn get_blocking(&self, timeout: Duration) -> Option<PooledObject<'_, T>> {
+        if let Some(object) = self.get() {
+            return Some(object);
+        }
+        let deadline = Instant::now() + timeout;
+        let mut guard = self.wait_lock.lock().unwrap();
+        self.waiter_count.fetch_add(1, Ordering::SeqCst);
+        let slot = loop {
+            // Re-check under the lock: a return that published its bit
+            // before we registered must not be missed.
+            if let Some(slot) = self.claim_slot() {
+                break Some(slot);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                break None;
+            }
+            let (reacquired, _) = self.slot_freed.wait_timeout(guard, deadline - now).unwrap();
+            guard = reacquired;
+        };
+        self.waiter_count.fetch_sub(1, Ordering::SeqCst);
+        drop(guard);
+        slot.map(|slot| self.checkout(slot))
+    }
+
+    /// Moves the value out of a freshly claimed slot.
+    fn checkout(&self, slot: usize) -> PooledObject<'_, T> {
+        // SAFETY: claiming the bit with acquire ordering gives us exclusive
+        // ownership of an initialized slot; we move the value out, leaving
+        // the slot logically uninitialized until `return_to_slot`.
+        let value = unsafe { (*self.slots[slot].get()).assume_init_read() };
+        PooledObject {
+            pool: self,
+            value: ManuallyDrop::new(value),
+            slot: Some(slot),
+        }
+    }
+
+    /// Checks an object out of the pool, constructing a detached one with
+    /// `init` when the pool is exhausted. A detached object is dropped
+    /// normally instead of being returned to a slot.
+    pub fn get_or_create(&self, init: impl FnOnce() -> T) -> PooledObject<'_, T> {
+        self.get().unwrap_or_else(|| self.detached(init()))
+    }
+
+    /// Wraps `value` in a guard without backing it by a slot; it is dropped
+    /// normally instead of being returned to the pool.
+    pub fn detached(&self, value: T) -> PooledObject<'_, T> {
+        PooledObject {
+            pool: self,
+            value: ManuallyDrop::new(value),
+            slot: None,
+        }
+    }
+
+    /// Atomically claims an available slot off the freelist.
+    fn claim_slot(&self) -> Option<usize> {
+        self.free.claim()
+    }
+
+    /// Moves `value` back into `slot` and re-publishes it on the freelist.
+    fn return_to_slot(&self, slot: usize, value: T) {
+        // SAFETY: the slot is absent from the freelist, so no other thread
+        // touches it until the release below publishes the write.
+        unsafe { (*self.slots[slot].get()).write(value) };
+        self.free.release(slot);
+        if self.waiter_count.load(Ordering::SeqCst) > 0 {
+            // Taking the lock serializes with a registering waiter, so the
+            // notification cannot slip between its claim re-check and its
+            // wait.
+            let _guard = self.wait_lock.lock().unwrap();
+            self.slot_freed.notify_one();
+        }
+    }
+}
+
+impl<T> Drop for ObjectPool<T> {
+    fn drop(&mut self) {
+        // Only slots still on the freelist hold initialized values;
+        // checked-out objects are dropped by their guards.
+        while let Some(slot) = self.free.claim() {
+            // SAFETY: the freelist says the slot is initialized, and `&mut
+            // self` excludes concurrent access.
+            unsafe { (*self.slots[slot].get()).assume_init_drop() };
+        }
+    }
+}
+
+/// An RAII guard around a pooled object, returning it on drop.
+pub struct PooledObject<'a, T> {
+    pool: &'a ObjectPool<T>,
+    value: ManuallyDrop<T>,
+    /// `None` for detached objects created past pool capacity.
+    slot: Option<usize>,
+}
+
+impl<T> PooledObject<'_, T> {
+    /// Returns whether the object came from a pool slot rather than a
+    /// detached allocation.
+    pub fn is_pooled(&self) -> bool {
+        self.slot.is_some()
+    }
+}
+
+impl<T> Deref for PooledObject<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> DerefMut for PooledObject<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl<T> Drop for PooledObject<'_, T> {
+    fn drop(&mut self) {
+        // SAFETY: `value` is never touched again after this take.
+        let value = unsafe { ManuallyDrop::take(&mut self.value) };
+        match self.slot {
+            Some(slot) => self.pool.return_to_slot(slot, value),
+            None => drop(value),
+        }
+    }
+}
+
+/// The buffer capacities served by [`BufferPool`], smallest first.
+const BUFFER_TIERS: [usize; 3] = [4096, 16384, 65536];
+
+/// A pool of reusable byte buffers, bucketed by capacity tier.
+///
+/// Requests are routed to the smallest tier that satisfies them. When a tier
+/// is exhausted a detached buffer is allocated with the tier's full
+/// capacity, so callers always receive at least the tier they asked for.
+/// Buffers that outgrew their tier while checked out are shrunk back on
+/// return, and the released bytes are tracked in `reclaimed_bytes`.
+pub struct BufferPool {
+    tiers: Vec<Tier>,
+    reclaimed_bytes: AtomicUsize,
+}
+
+struct Tier {
+    capacity: usize,
+    pool: ObjectPool<Vec<u8>>,
+}
+
+impl BufferPool {
+    /// Creates a pool holding `buffers_per_tier` buffers in each size tier.
+    pub fn new(buffers_per_tier: usize) -> Self {
+        let tiers = BUFFER_TIERS
+            .iter()
+            .map(|&capacity| Tier {
+                capacity,
+                pool: ObjectPool::new(buffers_per_tier, || Vec::with_capacity(capacity)),
+            })
+            .collect();
+        Self {
+            tiers,
+            reclaimed_bytes: AtomicUsize::new(0),
+        }
+    }
+
+    /// Checks out an empty buffer with capacity of at least `size`.
+    ///
+    /// Requests beyond the largest tier get a detached buffer of exactly the
+    /// requested capacity.
+    pub fn get(&self, size: usize) -> PooledBuffer<'_> {
+        let tier = match self.tiers.iter().find(|tier| tier.capacity >= size) {
+            Some(tier) => tier,
+            None => {
+                let last = self.tiers.last().expect("at least one tier");
+                return PooledBuffer {
+                    inner: last.pool.detached(Vec::with_capacity(size)),
+                    tier_capacity: size,
+                    reclaimed_bytes: &self.reclaimed_bytes,
+                };
+            }
+        };
+        PooledBuffer {
+            inner: tier.pool.get_or_create(|| Vec::with_capacity(tier.capacity)),
+            tier_capacity: tier.capacity,
+            reclaimed_bytes: &self.reclaimed_bytes,
+        }
+    }
+
+    /// Total capacity in bytes released by shrinking overgrown buffers on
+    /// return.
+    pub fn reclaimed_bytes(&self) -> usize {
+        self.reclaimed_bytes.load(Ordering::Relaxed)
+    }
+
+    /// Zeroes the reclaimed-bytes counter for the next reporting interval,
+    /// returning the drained total. Uses `swap(0)` so a concurrent
+    /// reclamation is never lost between read and reset.
+    pub fn reset_reclaimed_bytes(&self) -> usize {
+        self.reclaimed_bytes.swap(0, Ordering::Relaxed)
+    }
+
+    /// Current occupancy of every tier, smallest first.
+    pub fn tier_occupancy(&self) -> Vec<TierOccupancy> {
+        self.tiers
+            .iter()
+            .map(|tier| TierOccupancy {
+                buffer_capacity: tier.capacity,
+                slots: tier.pool.capacity(),
+                available: tier.pool.available(),
+            })
+            .collect()
+    }
+}
+
+/// A snapshot of one [`BufferPool`] tier, as reported by
+/// [`BufferPool::tier_occupancy`].
+#[derive(Debug, Clone, Copy)]
+pub struct TierOccupancy {
+    /// The byte capacity of buffers in this tier.
+    pub buffer_capacity: usize,
+    /// The number of slots in the tier.
+    pub slots: usize,
+    /// How many buffers are currently checked in.
+    pub available: usize,
+}
+
+impl Default for BufferPool {
+    fn default() -> Self {
+        Self::new(16)
+    }
+}
+
+/// An RAII guard around a pooled buffer; cleared and, if overgrown, shrunk
+/// back to its tier capacity on drop.
+pub struct PooledBuffer<'a> {
+    inner: PooledObject<'a, Vec<u8>>,
+    tier_capacity: usize,
+    reclaimed_bytes: &'a AtomicUsize,
+}
+
+impl Deref for PooledBuffer<'_> {
+    type Target = Vec<u8>;
+
+    fn deref(&self) -> &Vec<u8> {
+        &self.inner
+    }
+}
+
+impl DerefMut for PooledBuffer<'_> {
+    fn deref_mut(&mut self) -> &mut Vec<u8> {
+        &mut self.inner
+    }
+}
+
+impl Drop for PooledBuffer<'_> {
+    fn drop(&mut self) {
+        self.inner.clear();
+        let capacity = self.inner.capacity();
+        if capacity > self.tier_capacity {
+            self.inner.shrink_to(self.tier_capacity);
+            self.reclaimed_bytes
+                .fetch_add(capacity - self.inner.capacity(), Ordering::Relaxed);
+        }
+        // `inner` now returns the right-sized buffer to its slot.
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Arc;
+
+    struct DropCounter {
+        drops: Arc<AtomicUsize>,
+    }
+
+    impl Drop for DropCounter {
+        fn drop(&mut self) {
+            self.drops.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn get_and_return_cycle() {
+        let pool = ObjectPool::new(2, Vec::<u8>::new);
+        assert_eq!(pool.capacity(), 2);
+        assert_eq!(pool.available(), 2);
+
+        let mut first = pool.get().expect("slot available");
+        first.push(1);
+        let _second = pool.get().expect("slot available");
+        assert_eq!(pool.available(), 0);
+        assert!(pool.get().is_none());
+
+        drop(first);
+        assert_eq!(pool.available(), 1);
+        let reused = pool.get().expect("returned slot");
+        assert_eq!(*reused, vec![1]);
+    }
+
+    #[test]
+    fn get_or_create_detaches_past_capacity() {
+        let pool = ObjectPool::new(1, || 7u32);
+        let held = pool.get().expect("slot available");
+        let extra = pool.get_or_create(|| 9);
+        assert!(held.is_pooled());
+        assert!(!extra.is_pooled());
+        assert_eq!(*extra, 9);
+        drop(extra);
+        assert_eq!(pool.available(), 0);
+        drop(held);
+        assert_eq!(pool.available(), 1);
+    }
+
+    #[test]
+    fn get_blocking_times_out_when_exhausted() {
+        let pool = ObjectPool::new(1, || 0u8);
+        let held = pool.get().expect("slot available");
+        let start = Instant::now();
+        assert!(pool.get_blocking(Duration::from_millis(20)).is_none());
+        assert!(start.elapsed() >= Duration::from_millis(20));
+        drop(held);
+    }
+
+    #[test]
+    fn get_blocking_wakes_when_holder_returns() {
+        let pool = ObjectPool::new(1, || 0u8);
+        let held = pool.get().expect("slot available");
+
+        std::thread::scope(|scope| {
+            let waiter = scope.spawn(|| pool.get_blocking(Duration::from_secs(5)));
+            std::thread::sleep(Duration::from_millis(30));
+            drop(held);
+            let acquired = waiter.join().expect("waiter thread");
+            assert!(acquired.is_some(), "waiter should acquire the freed slot");
+        });
+    }
+
+    #[test]
+    fn buffer_pool_routes_by_tier() {
+        let pool = BufferPool::new(2);
+        assert!(pool.get(1000).capacity() >= 4096);
+        assert!(pool.get(5000).capacity() >= 16384);
+        assert!(pool.get(100_000).capacity() >= 100_000);
+    }
+
+    #[test]
+    fn buffer_pool_right_sizes_exhausted_allocations() {
+        let pool = BufferPool::new(1);
+        let held = pool.get(1000);
+        // The single 4096 slot is checked out; the fallback allocation must
+        // still honor the tier capacity.
+        let extra = pool.get(1000);
+        assert!(extra.capacity() >= 4096);
+        drop(extra);
+        drop(held);
+    }
+
+    #[test]
+    fn buffer_pool_reclaims_overgrown_buffers() {
+        let pool = BufferPool::new(1);
+        {
+            let mut buffer = pool.get(1000);
+            buffer.resize(50_000, 0);
+            assert!(buffer.capacity() > 4096);
+        }
+        assert!(pool.reclaimed_bytes() > 0);
+        let reused = pool.get(1000);
+        assert!(reused.is_empty());
+        assert!(reused.capacity() >= 4096);
+        assert!(reused.capacity() <= 16384, "buffer shrunk back to its tier");
+    }
+
+    #[test]
+    fn stack_backed_pool_cycles_objects() {
+        let pool = ObjectPool::new_stack_backed(2, Vec::<u8>::new);
+        assert_eq!(pool.available(), 2);
+        let mut first = pool.get().expect("slot available");
+        first.push(9);
+        assert_eq!(pool.available(), 1);
+        drop(first);
+        assert_eq!(pool.available(), 2);
+        let reused = pool.get().expect("returned slot");
+        assert_eq!(*reused, vec![9]);
+    }
+
+    #[test]
+    fn each_object_drops_exactly_once() {
+        const SLOTS: usize = 8;
+        const THREADS: usize = 4;
+        const CYCLES: usize = 10_000;
+
+        // Both freelist backings must uphold the same ownership protocol.
+        for stack_backed in [false, true] {
+            let drops = Arc::new(AtomicUsize::new(0));
+            let init = || DropCounter {
+                drops: Arc::clone(&drops),
+            };
+            let pool = if stack_backed {
+                ObjectPool::new_stack_backed(SLOTS, init)
+            } else {
+                ObjectPool::new(SLOTS, init)
+            };
+
+            std::thread::scope(|scope| {
+                for _ in 0..THREADS {
+                    scope.spawn(|| {
+                        for _ in 0..CYCLES {
+                            if let Some(object) = pool.get() {
+                                drop(object);
+                            }
+                        }
+                    });
+                }
+            });
+
+            assert_eq!(drops.load(Ordering::SeqCst), 0, "no drops while pooled");
+            drop(pool);
+            assert_eq!(drops.load(Ordering::SeqCst), SLOTS, "one drop per slot");
+        }
+    }
+}
@@ -0,0 +1,228 @@
+//! Method-and-path request routing over a segment trie, with `:name`
+//! parameter and trailing `*name` wildcard captures.
+
+use crate::connection::HttpRequest;
+use crate::http1::Method;
+use crate::server::Response;
+use std::collections::HashMap;
+
+type Handler = Box<dyn Fn(HttpRequest, &Params) -> Response + Send + Sync>;
+
+/// The values captured from a matched pattern's `:name` and `*name`
+/// segments.
+#[derive(Debug, Default)]
+pub struct Params {
+    captures: Vec<(String, String)>,
+}
+
+impl Params {
+    /// The capture for a parameter named in the matched pattern.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.captures
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// One trie node per path segment. Lookup tries static children first,
+/// then the parameter child, then the wildcard, so more specific routes
+/// always win.
+#[derive(Default)]
+struct Node {
+    statics: HashMap<String, Node>,
+    /// A `:name` child matching any single segment.
+    param: Option<(String, Box<Node>)>,
+    /// A trailing `*name` capture swallowing the rest of the path.
+    wildcard: Option<(String, Vec<(Method, Handler)>)>,
+    endpoints: Vec<(Method, Handler)>,
+}
+
+/// A routing table mapping `(method, path)` to handlers in O(path
+/// segments) per lookup, independent of how many routes are registered.
+pub struct Router {
+    root: Node,
+}
+
+impl Router {
+    pub fn new() -> Self {
+        Self {
+            root: Node::default(),
+        }
+    }
+
+    /// Registers `handler` for `method` on `pattern`.
+    ///
+    /// A segment starting with `:` matches any one path segment and is
+    /// captured under its name; a final segment starting with `*`
+    /// captures the remainder of the path. Panics on an ill-formed
+    /// pattern or a duplicate registration, since routes are wired at
+    /// startup.
+    pub fn route<H>(mut self, method: Method, pattern: &str, handler: H) -> Self
+    where
+        H: Fn(HttpRequest, &Params) -> Response + Send + Sync + 'static,
+    {
+        assert!(pattern.starts_with('/'), "route pattern must start with /");
+        let mut segments: Vec<&str> = pattern.split('/').filter(|s| !s.is_empty()).collect();
+        let wildcard = match segments.last() {
+            Some(last) if last.starts_with('*') => segments.pop().map(|s| &s[1..]),
+            _ => None,
+        };
+
+        let mut node = &mut self.root;
+        for segment in segments {
+            assert!(
+                !segment.starts_with('*'),
+                "wildcard must be the final pattern segment"
+            );
+            if let Some(name) = segment.strip_prefix(':') {
+                let (existing, child) = node
+                    .param
+                    .get_or_insert_with(|| (name.to_owned(), Box::default()));
+                assert!(
+                    existing == name,
+                    "conflicting parameter names :{existing} and :{name} at the same position"
+                );
+                node = child;
+            } else {
+                node = node.statics.entry(segment.to_owned()).or_default();
+            }
+        }
+
+        let endpoints = match wildcard {
+            Some(name) => {
+                let (existing, endpoints) = node
+                    .wildcard
+                    .get_or_insert_with(|| (name.to_owned(), Vec::new()));
+                assert!(
+                    existing == name,
+                    "conflicting wildcard names *{existing} and *{name} at the same position"
+                );
+                endpoints
+            }
+            None => &mut node.endpoints,
+        };
+        assert!(
+            endpoints.iter().all(|(m, _)| *m != method),
+            "duplicate route: {} {pattern}",
+            method.as_str()
+        );
+        endpoints.push((method, Box::new(handler)));
+        self
+    }
+
+    /// Routes a request to its handler, answering 404 for an unknown
+    /// path and 405 with an `Allow` header when the path is known but no
+    /// handler is registered for the method.
+    pub fn dispatch(&self, request: HttpRequest) -> Response {
+        let method = request.method;
+        let path = request.path().to_owned();
+        let mut params = Params::default();
+        let mut node = &self.root;
+        let mut remaining = path.split('/').filter(|s| !s.is_empty());
+        let endpoints = loop {
+            let Some(segment) = remaining.next() else {
+                break &node.endpoints;
+            };
+            if let Some(child) = node.statics.get(segment) {
+                node = child;
+            } else if let Some((name, child)) = &node.param {
+                params.captures.push((name.clone(), segment.to_owned()));
+                node = child;
+            } else if let Some((name, endpoints)) = &node.wildcard {
+                let mut rest = segment.to_owned();
+                for extra in remaining.by_ref() {
+                    rest.push('/');
+                    rest.push_str(extra);
+                }
+                params.captures.push((name.clone(), rest));
+                break endpoints;
+            } else {
+                return Response::new(404);
+            }
+        };
+
+        if let Some((_, handler)) = endpoints.iter().find(|(m, _)| *m == method) {
+            return handler(request, &params);
+        }
+        let allowed: Vec<&str> = endpoints.iter().map(|(m, _)| m.as_str()).collect();
+        if allowed.is_empty() {
+            return Response::new(404);
+        }
+        Response::new(405).header("Allow", &allowed.join(", "))
+    }
+}
+
+impl Default for Router {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http1::Version;
+
+    fn get(path: &str) -> HttpRequest {
+        HttpRequest {
+            method: Method::Get,
+            target: path.to_owned(),
+            version: Version::Http11,
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    fn router() -> Router {
+        Router::new()
+            .route(Method::Get, "/health", |_, _| Response::new(200).body(b"ok"))
+            .route(Method::Get, "/users/:id", |_, params| {
+                Response::new(200).body(params.get("id").unwrap().as_bytes())
+            })
+            .route(Method::Post, "/users/:id", |_, _| Response::new(201))
+            .route(Method::Get, "/static/*path", |_, params| {
+                Response::new(200).body(params.get("path").unwrap().as_bytes())
+            })
+    }
+
+    #[test]
+    fn static_route_matches() {
+        let response = router().dispatch(get("/health"));
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.body_bytes(), b"ok");
+    }
+
+    #[test]
+    fn param_segment_is_captured() {
+        let response = router().dispatch(get("/users/42"));
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.body_bytes(), b"42");
+    }
+
+    #[test]
+    fn wildcard_captures_the_rest_of_the_path() {
+        let response = router().dispatch(get("/static/css/site.css"));
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.body_bytes(), b"css/site.css");
+    }
+
+    #[test]
+    fn wrong_method_on_a_known_path_is_405_with_allow() {
+        let mut request = get("/users/42");
+        request.method = Method::Delete;
+        let response = router().dispatch(request);
+        assert_eq!(response.status(), 405);
+        let allow = response
+            .headers()
+            .iter()
+            .find(|(name, _)| name == "Allow")
+            .map(|(_, value)| value.as_str());
+        assert_eq!(allow, Some("GET, POST"));
+    }
+
+    #[test]
+    fn unknown_path_is_404() {
+        assert_eq!(router().dispatch(get("/nope/deeper")).status(), 404);
+    }
+}
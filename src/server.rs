@@ -0,0 +1,570 @@
+//! The top-level server: socket binding, the accept loop, and dispatch of
+//! parsed requests to a handler, one thread per connection.
+
+use crate::connection::{
+    Connection, ConnectionAction, ConnectionConfig, HttpRequest, Protocol, Timeouts,
+};
+use crate::error::Error;
+use crate::hpack::HpackEncoder;
+use crate::http1::Http1ResponseBuilder;
+use crate::http2::{Http2FrameBuilder, DEFAULT_MAX_FRAME_SIZE};
+use crate::metrics::ConnectionMetrics;
+use crate::pool::BufferPool;
+use crate::tls::TlsAcceptor;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// The `Server` header value advertised on every response.
+const SERVER_NAME: &str = concat!("angelax/", env!("CARGO_PKG_VERSION"));
+
+/// A handler's answer to one request: status, extra headers, and body.
+///
+/// Framing headers (`Content-Length`, `Date`, `Server`) are added by the
+/// server when the response is serialized.
+#[derive(Debug, Clone)]
+pub struct Response {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Response {
+    pub fn new(status: u16) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    /// Appends a header field.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_owned(), value.to_owned()));
+        self
+    }
+
+    /// Sets the response body.
+    pub fn body(mut self, body: &[u8]) -> Self {
+        self.body = body.to_vec();
+        self
+    }
+
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+
+    pub fn body_bytes(&self) -> &[u8] {
+        &self.body
+    }
+
+    /// Serializes the response for HTTP/1.1, framing headers included.
+    pub fn to_http1_bytes(&self) -> Vec<u8> {
+        let mut builder = Http1ResponseBuilder::new(self.status);
+        for (name, value) in &self.headers {
+            builder = builder.header(name, value);
+        }
+        builder
+            .auto_headers(Some(SERVER_NAME))
+            .body(&self.body)
+            .build()
+    }
+
+    /// Serializes the response for HTTP/2 on `stream_id`: a HEADERS frame
+    /// carrying `:status` plus the lowercased header fields, followed by
+    /// the body as DATA frames. The last frame carries `END_STREAM`.
+    pub fn to_http2_frames(&self, stream_id: u32, encoder: &mut HpackEncoder) -> Vec<Vec<u8>> {
+        let status = self.status.to_string();
+        let mut fields: Vec<(Vec<u8>, Vec<u8>)> =
+            vec![(b":status".to_vec(), status.into_bytes())];
+        for (name, value) in &self.headers {
+            fields.push((
+                name.to_ascii_lowercase().into_bytes(),
+                value.as_bytes().to_vec(),
+            ));
+        }
+        let block = encoder
+            .encode_block(fields.iter().map(|(n, v)| (n.as_slice(), v.as_slice())));
+
+        let builder = Http2FrameBuilder::new();
+        let end_stream = self.body.is_empty();
+        let mut frames = vec![builder.headers_frame(
+            stream_id,
+            &block,
+            end_stream,
+            true,
+            DEFAULT_MAX_FRAME_SIZE,
+        )];
+        if !end_stream {
+            frames.extend(builder.data_frames(
+                stream_id,
+                &self.body,
+                DEFAULT_MAX_FRAME_SIZE,
+                true,
+            ));
+        }
+        frames
+    }
+}
+
+/// Answers one request; the composable unit middleware wraps.
+///
+/// Any `Fn(&HttpRequest) -> Response` closure is a handler, so leaf
+/// services stay as terse as the closure [`Server::serve`] accepts.
+pub trait Handler: Send + Sync {
+    fn handle(&self, request: &HttpRequest) -> Response;
+}
+
+impl<F> Handler for F
+where
+    F: Fn(&HttpRequest) -> Response + Send + Sync,
+{
+    fn handle(&self, request: &HttpRequest) -> Response {
+        self(request)
+    }
+}
+
+/// Wraps a handler with behavior before and/or after it runs — logging,
+/// auth, shaping — or short-circuits by not calling `next` at all.
+pub trait Middleware: Send + Sync {
+    fn wrap(&self, request: &HttpRequest, next: &dyn Handler) -> Response;
+}
+
+/// A handler built from middleware layered around an innermost handler.
+///
+/// Layers run in the order they were added: the first
+/// [`MiddlewareStack::layer`] call is the outermost wrapper.
+pub struct MiddlewareStack {
+    layers: Vec<Box<dyn Middleware>>,
+    inner: Box<dyn Handler>,
+}
+
+impl MiddlewareStack {
+    pub fn new(inner: impl Handler + 'static) -> Self {
+        Self {
+            layers: Vec::new(),
+            inner: Box::new(inner),
+        }
+    }
+
+    /// Adds a layer outside the previous ones' inner handler but inside
+    /// every layer added before it.
+    pub fn layer(mut self, middleware: impl Middleware + 'static) -> Self {
+        self.layers.push(Box::new(middleware));
+        self
+    }
+
+    /// Runs the chain from layer `index` inward.
+    fn run(&self, index: usize, request: &HttpRequest) -> Response {
+        match self.layers.get(index) {
+            Some(middleware) => {
+                let next = NextLayer { stack: self, index: index + 1 };
+                middleware.wrap(request, &next)
+            }
+            None => self.inner.handle(request),
+        }
+    }
+}
+
+impl Handler for MiddlewareStack {
+    fn handle(&self, request: &HttpRequest) -> Response {
+        self.run(0, request)
+    }
+}
+
+/// The `next` handler a middleware sees: the rest of the chain.
+struct NextLayer<'a> {
+    stack: &'a MiddlewareStack,
+    index: usize,
+}
+
+impl Handler for NextLayer<'_> {
+    fn handle(&self, request: &HttpRequest) -> Response {
+        self.stack.run(self.index, request)
+    }
+}
+
+/// An HTTP server bound to a socket, dispatching each accepted connection
+/// to its own thread.
+pub struct Server {
+    listener: TcpListener,
+    config: ConnectionConfig,
+    tls: Option<Arc<TlsAcceptor>>,
+    buffers: Arc<BufferPool>,
+    /// Per-connection metrics folded together as connections finish.
+    totals: Arc<Mutex<ConnectionMetrics>>,
+}
+
+impl Server {
+    /// Binds to `addr` with the default connection configuration.
+    pub fn bind(addr: impl ToSocketAddrs) -> Result<Self, Error> {
+        Ok(Self {
+            listener: TcpListener::bind(addr).map_err(Error::Io)?,
+            config: ConnectionConfig::default(),
+            tls: None,
+            buffers: Arc::new(BufferPool::default()),
+            totals: Arc::new(Mutex::new(ConnectionMetrics::default())),
+        })
+    }
+
+    /// Replaces the connection configuration applied to accepted sockets.
+    pub fn config(mut self, config: ConnectionConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Terminates TLS on every accepted connection before HTTP handling.
+    pub fn tls(mut self, acceptor: TlsAcceptor) -> Self {
+        self.tls = Some(Arc::new(acceptor));
+        self
+    }
+
+    /// The address the server actually bound — useful with port 0.
+    pub fn local_addr(&self) -> Result<SocketAddr, Error> {
+        self.listener.local_addr().map_err(Error::Io)
+    }
+
+    /// The metrics of every finished connection, folded together.
+    pub fn metrics(&self) -> ConnectionMetrics {
+        *self.totals.lock().expect("metrics lock poisoned")
+    }
+
+    /// Accepts connections forever, spawning a thread per connection and
+    /// answering each parsed request with what `handler` returns.
+    ///
+    /// Transient accept failures are skipped; only losing the listening
+    /// socket itself ends the loop.
+    pub fn serve<H>(&self, handler: H) -> Result<(), Error>
+    where
+        H: Fn(HttpRequest) -> Response + Send + Sync + 'static,
+    {
+        let handler = Arc::new(handler);
+        for stream in self.listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    // Per-connection failures (aborted handshakes, RST on
+                    // the backlog) must not take the server down.
+                    if e.kind() == std::io::ErrorKind::WouldBlock {
+                        return Err(Error::Io(e));
+                    }
+                    continue;
+                }
+            };
+            let Ok(peer_addr) = stream.peer_addr() else {
+                continue;
+            };
+            let config = self.config.clone();
+            let tls = self.tls.clone();
+            let handler = Arc::clone(&handler);
+            let buffers = Arc::clone(&self.buffers);
+            let totals = Arc::clone(&self.totals);
+            thread::spawn(move || {
+                let finished = match &tls {
+                    Some(acceptor) => acceptor.accept(stream).and_then(|tls_stream| {
+                        // The handshake already named the protocol via
+                        // ALPN; seed it so an `h2` connection skips byte
+                        // detection.
+                        let protocol = tls_stream
+                            .negotiated_protocol()
+                            .and_then(Protocol::from_alpn);
+                        serve_connection(
+                            Connection::new_with_protocol(tls_stream, peer_addr, config, protocol),
+                            handler.as_ref(),
+                            &buffers,
+                        )
+                    }),
+                    None => serve_connection(
+                        Connection::new(stream, peer_addr, config),
+                        handler.as_ref(),
+                        &buffers,
+                    ),
+                };
+                if let Ok(metrics) = finished {
+                    let mut totals = totals.lock().expect("metrics lock poisoned");
+                    totals.bytes_read += metrics.bytes_read;
+                    totals.bytes_written += metrics.bytes_written;
+                    totals.requests_served += metrics.requests_served;
+                }
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Drives one connection until it closes, returning its final metrics.
+fn serve_connection<S: Read + Write + Timeouts>(
+    mut conn: Connection<S>,
+    handler: &dyn Fn(HttpRequest) -> Response,
+    buffers: &BufferPool,
+) -> Result<ConnectionMetrics, Error> {
+    loop {
+        let action = match conn.process() {
+            Ok(action) => action,
+            // A request the parser refused — oversized declared body,
+            // malformed framing — still deserves a final status before the
+            // close; in the Expect flow this is what tells the client not
+            // to send the body at all.
+            Err(e @ (Error::Http1(_) | Error::ParseError(_))) => {
+                let _ = conn.write_all(&e.to_response());
+                let _ = conn.flush();
+                return Ok(*conn.metrics());
+            }
+            Err(e) => return Err(e),
+        };
+        match action {
+            ConnectionAction::NeedMore => match conn.read_available() {
+                Ok(0) => return Ok(*conn.metrics()),
+                Ok(_) => {}
+                // An idle keep-alive connection that ran out its read
+                // timeout is retired, not reported as a failure.
+                Err(Error::Timeout) => return Ok(*conn.metrics()),
+                Err(e) => return Err(e),
+            },
+            ConnectionAction::Requests(requests) => {
+                let last = requests.len().saturating_sub(1);
+                for (index, request) in requests.into_iter().enumerate() {
+                    let method = request.method;
+                    let response = handler(request);
+                    let mut out = buffers.get(response.body.len() + 256);
+                    let mut builder = Http1ResponseBuilder::new(response.status);
+                    for (name, value) in &response.headers {
+                        builder = builder.header(name, value);
+                    }
+                    if index == last && conn.is_closing() {
+                        builder = builder.header("Connection", "close");
+                    }
+                    builder
+                        .auto_headers(Some(SERVER_NAME))
+                        .body_for_method(method, Some(&response.body))
+                        .build_into(&mut out);
+                    conn.write_all(&out)?;
+                }
+                conn.flush()?;
+            }
+            // This server does not proxy; tunnel requests are refused.
+            ConnectionAction::Connect { stream_id, .. } => {
+                let rst = Http2FrameBuilder::new()
+                    .rst_stream(stream_id, crate::streams::REFUSED_STREAM);
+                conn.write_all(&rst)?;
+                conn.flush()?;
+            }
+            ConnectionAction::Close => {
+                conn.flush()?;
+                return Ok(*conn.metrics());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpStream;
+
+    #[test]
+    fn serves_a_loopback_get() {
+        let server = Server::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+        thread::spawn(move || {
+            server
+                .serve(|request| {
+                    let body = format!("hello {}", request.path());
+                    Response::new(200)
+                        .header("Content-Type", "text/plain")
+                        .body(body.as_bytes())
+                })
+                .unwrap();
+        });
+
+        let mut client = TcpStream::connect(addr).unwrap();
+        client
+            .write_all(b"GET /greet HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .unwrap();
+        let mut response = Vec::new();
+        client.read_to_end(&mut response).unwrap();
+
+        let text = String::from_utf8(response).unwrap();
+        assert!(text.starts_with("HTTP/1.1 200 OK\r\n"), "got: {text}");
+        assert!(text.contains("Content-Type: text/plain\r\n"));
+        assert!(text.contains("Date: "));
+        assert!(text.contains(&format!("Server: {SERVER_NAME}\r\n")));
+        assert!(text.ends_with("hello /greet"));
+    }
+
+    #[test]
+    fn reuse_limit_closes_the_second_response() {
+        let config = ConnectionConfig {
+            max_requests_per_connection: 2,
+            ..ConnectionConfig::default()
+        };
+        let server = Server::bind("127.0.0.1:0").unwrap().config(config);
+        let addr = server.local_addr().unwrap();
+        thread::spawn(move || {
+            server.serve(|_| Response::new(200)).unwrap();
+        });
+
+        let mut client = TcpStream::connect(addr).unwrap();
+        client
+            .write_all(
+                b"GET /one HTTP/1.1\r\nHost: x\r\n\r\n\
+                  GET /two HTTP/1.1\r\nHost: x\r\n\r\n\
+                  GET /three HTTP/1.1\r\nHost: x\r\n\r\n",
+            )
+            .unwrap();
+        let mut response = Vec::new();
+        client.read_to_end(&mut response).unwrap();
+
+        let text = String::from_utf8(response).unwrap();
+        // Two responses, the last advertising the close; the third request
+        // is never answered.
+        assert_eq!(text.matches("HTTP/1.1 200 OK\r\n").count(), 2, "got: {text}");
+        assert!(text.contains("Connection: close\r\n"), "got: {text}");
+    }
+
+    #[test]
+    fn oversized_expect_continue_gets_413_without_the_body() {
+        let config = ConnectionConfig {
+            max_request_size: 1024,
+            ..ConnectionConfig::default()
+        };
+        let server = Server::bind("127.0.0.1:0").unwrap().config(config);
+        let addr = server.local_addr().unwrap();
+        thread::spawn(move || {
+            server.serve(|_| Response::new(200)).unwrap();
+        });
+
+        // Headers only; the body is held back pending the interim 100.
+        let mut client = TcpStream::connect(addr).unwrap();
+        client
+            .write_all(
+                b"POST /upload HTTP/1.1\r\nHost: x\r\nContent-Length: 1000000\r\n\
+                  Expect: 100-continue\r\n\r\n",
+            )
+            .unwrap();
+        let mut response = Vec::new();
+        client.read_to_end(&mut response).unwrap();
+
+        // The declared length alone earns the final status: no interim
+        // 100 Continue is offered, and the connection closes without the
+        // server ever asking for the body.
+        let text = String::from_utf8(response).unwrap();
+        assert!(text.starts_with("HTTP/1.1 413 Payload Too Large\r\n"), "got: {text}");
+        assert!(!text.contains("100 Continue"), "got: {text}");
+        assert!(text.contains("Connection: close\r\n"));
+    }
+
+    #[test]
+    fn one_response_serializes_to_both_protocols() {
+        use crate::hpack::HpackDecoder;
+        use crate::http2::{FrameType, Http2Parser, FLAG_END_STREAM};
+
+        let response = Response::new(200)
+            .header("Content-Type", "text/plain")
+            .header("X-Request-Id", "abc123")
+            .body(b"hello");
+
+        let text = String::from_utf8(response.to_http1_bytes()).unwrap();
+        assert!(text.starts_with("HTTP/1.1 200 OK\r\n"), "got: {text}");
+        assert!(text.contains("Content-Type: text/plain\r\n"));
+        assert!(text.contains("X-Request-Id: abc123\r\n"));
+        assert!(text.ends_with("hello"));
+
+        let mut encoder = HpackEncoder::new();
+        let frames = response.to_http2_frames(3, &mut encoder);
+        assert_eq!(frames.len(), 2);
+
+        let parser = Http2Parser::new();
+        let (headers, _) = parser.parse_frame(&frames[0]).unwrap();
+        assert_eq!(headers.header.frame_type, FrameType::Headers);
+        let mut decoder = HpackDecoder::new(4096);
+        let decoded = decoder.decode_block(headers.payload, None).unwrap();
+        // The same fields the HTTP/1.1 serialization carried, in HTTP/2
+        // dress: `:status` plus lowercased names.
+        assert_eq!(decoded[0], (b":status".to_vec(), b"200".to_vec()));
+        assert!(decoded.contains(&(b"content-type".to_vec(), b"text/plain".to_vec())));
+        assert!(decoded.contains(&(b"x-request-id".to_vec(), b"abc123".to_vec())));
+
+        let (data, _) = parser.parse_frame(&frames[1]).unwrap();
+        assert_eq!(data.header.frame_type, FrameType::Data);
+        assert_eq!(data.header.flags, FLAG_END_STREAM);
+        assert_eq!(data.payload, b"hello");
+    }
+
+    fn sample_request() -> HttpRequest {
+        HttpRequest {
+            method: crate::http1::Method::Get,
+            target: "/".to_owned(),
+            version: crate::http1::Version::Http11,
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    /// Records its label around the rest of the chain.
+    struct Trace {
+        log: Arc<Mutex<Vec<String>>>,
+        label: &'static str,
+    }
+
+    impl Middleware for Trace {
+        fn wrap(&self, request: &HttpRequest, next: &dyn Handler) -> Response {
+            self.log.lock().unwrap().push(format!("{} before", self.label));
+            let response = next.handle(request);
+            self.log.lock().unwrap().push(format!("{} after", self.label));
+            response
+        }
+    }
+
+    #[test]
+    fn middleware_runs_around_the_inner_handler_in_order() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let inner_log = Arc::clone(&log);
+        let stack = MiddlewareStack::new(move |_: &HttpRequest| {
+            inner_log.lock().unwrap().push("inner".to_owned());
+            Response::new(200)
+        })
+        .layer(Trace {
+            log: Arc::clone(&log),
+            label: "outer",
+        })
+        .layer(Trace {
+            log: Arc::clone(&log),
+            label: "access",
+        });
+
+        let response = stack.handle(&sample_request());
+        assert_eq!(response.status(), 200);
+        assert_eq!(
+            *log.lock().unwrap(),
+            ["outer before", "access before", "inner", "access after", "outer after"]
+        );
+    }
+
+    #[test]
+    fn middleware_can_short_circuit_the_chain() {
+        struct Deny;
+        impl Middleware for Deny {
+            fn wrap(&self, _request: &HttpRequest, _next: &dyn Handler) -> Response {
+                Response::new(403)
+            }
+        }
+
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let inner_log = Arc::clone(&log);
+        let stack = MiddlewareStack::new(move |_: &HttpRequest| {
+            inner_log.lock().unwrap().push("inner".to_owned());
+            Response::new(200)
+        })
+        .layer(Deny);
+
+        let response = stack.handle(&sample_request());
+        assert_eq!(response.status(), 403);
+        assert!(log.lock().unwrap().is_empty(), "inner handler ran");
+    }
+}
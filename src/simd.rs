@@ -0,0 +1,1204 @@
+//! SIMD-accelerated byte scanning primitives used by the parsers.
+//!
+//! Each helper carries a portable scalar implementation. On x86-64 the AVX2
+//! paths are selected by a one-time runtime feature check, so a generic
+//! build still uses them on capable CPUs; no `-C target-feature` flags are
+//! required. On aarch64 the NEON paths are used unconditionally, since NEON
+//! is part of the baseline architecture.
+
+#[cfg(target_arch = "aarch64")]
+use std::arch::aarch64::*;
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::*;
+
+/// Returns whether AVX2 is available, probing the CPU once and caching the
+/// answer so the hot paths pay only a relaxed atomic load.
+#[cfg(target_arch = "x86_64")]
+#[inline]
+fn avx2_available() -> bool {
+    use std::sync::atomic::{AtomicU8, Ordering};
+    const UNKNOWN: u8 = 0;
+    const ABSENT: u8 = 1;
+    const PRESENT: u8 = 2;
+    static AVX2: AtomicU8 = AtomicU8::new(UNKNOWN);
+    match AVX2.load(Ordering::Relaxed) {
+        PRESENT => true,
+        ABSENT => false,
+        _ => {
+            let detected = std::arch::is_x86_feature_detected!("avx2");
+            AVX2.store(if detected { PRESENT } else { ABSENT }, Ordering::Relaxed);
+            detected
+        }
+    }
+}
+
+/// Finds the first occurrence of a single delimiter byte.
+#[derive(Debug, Clone, Copy)]
+pub struct SimdDelimiterFinder {
+    delimiter: u8,
+}
+
+impl SimdDelimiterFinder {
+    pub fn new(delimiter: u8) -> Self {
+        Self { delimiter }
+    }
+
+    /// Returns the index of the first occurrence of the delimiter.
+    pub fn find_in(&self, haystack: &[u8]) -> Option<usize> {
+        #[cfg(target_arch = "x86_64")]
+        if avx2_available() {
+            // SAFETY: AVX2 presence verified at runtime.
+            return unsafe { self.find_in_avx2(haystack) };
+        }
+        #[cfg(target_arch = "aarch64")]
+        {
+            // SAFETY: NEON is mandatory on aarch64.
+            return unsafe { self.find_in_neon(haystack) };
+        }
+        #[allow(unreachable_code)]
+        self.find_in_scalar(haystack)
+    }
+
+    /// Returns a lazy iterator over every occurrence of the delimiter.
+    ///
+    /// Positions are computed one 32-byte block at a time, so a vectorized
+    /// comparison is shared by all matches within a block.
+    pub fn find_all<'a>(&self, haystack: &'a [u8]) -> DelimiterPositions<'a> {
+        DelimiterPositions {
+            finder: *self,
+            haystack,
+            block_start: 0,
+            next_offset: 0,
+            mask: 0,
+        }
+    }
+
+    /// Counts the occurrences of the delimiter.
+    pub fn count_in(&self, haystack: &[u8]) -> usize {
+        self.find_all(haystack).count()
+    }
+
+    fn find_in_scalar(&self, haystack: &[u8]) -> Option<usize> {
+        haystack.iter().position(|&b| b == self.delimiter)
+    }
+
+    /// Computes a match bitmask for a window of at most 32 bytes.
+    fn block_mask(&self, window: &[u8]) -> u32 {
+        debug_assert!(window.len() <= 32);
+        #[cfg(target_arch = "x86_64")]
+        if window.len() == 32 && avx2_available() {
+            // SAFETY: AVX2 presence verified at runtime.
+            return unsafe { self.block_mask_avx2(window) };
+        }
+        let mut mask = 0u32;
+        for (i, &b) in window.iter().enumerate() {
+            if b == self.delimiter {
+                mask |= 1 << i;
+            }
+        }
+        mask
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[target_feature(enable = "avx2")]
+    unsafe fn block_mask_avx2(&self, window: &[u8]) -> u32 {
+        let needle = _mm256_set1_epi8(self.delimiter as i8);
+        let block = _mm256_loadu_si256(window.as_ptr() as *const __m256i);
+        _mm256_movemask_epi8(_mm256_cmpeq_epi8(block, needle)) as u32
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[target_feature(enable = "avx2")]
+    unsafe fn find_in_avx2(&self, haystack: &[u8]) -> Option<usize> {
+        let needle = _mm256_set1_epi8(self.delimiter as i8);
+        let mut offset = 0;
+        while offset + 32 <= haystack.len() {
+            let block = _mm256_loadu_si256(haystack.as_ptr().add(offset) as *const __m256i);
+            let eq = _mm256_cmpeq_epi8(block, needle);
+            let mask = _mm256_movemask_epi8(eq) as u32;
+            if mask != 0 {
+                return Some(offset + mask.trailing_zeros() as usize);
+            }
+            offset += 32;
+        }
+        haystack[offset..]
+            .iter()
+            .position(|&b| b == self.delimiter)
+            .map(|pos| offset + pos)
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    #[target_feature(enable = "neon")]
+    unsafe fn find_in_neon(&self, haystack: &[u8]) -> Option<usize> {
+        let needle = vdupq_n_u8(self.delimiter);
+        let mut offset = 0;
+        while offset + 16 <= haystack.len() {
+            let block = vld1q_u8(haystack.as_ptr().add(offset));
+            let eq = vceqq_u8(block, needle);
+            if let Some(lane) = first_matching_lane(eq) {
+                return Some(offset + lane);
+            }
+            offset += 16;
+        }
+        haystack[offset..]
+            .iter()
+            .position(|&b| b == self.delimiter)
+            .map(|pos| offset + pos)
+    }
+}
+
+/// Lazy iterator over delimiter positions, produced by
+/// [`SimdDelimiterFinder::find_all`].
+#[derive(Debug, Clone)]
+pub struct DelimiterPositions<'a> {
+    finder: SimdDelimiterFinder,
+    haystack: &'a [u8],
+    block_start: usize,
+    next_offset: usize,
+    mask: u32,
+}
+
+impl Iterator for DelimiterPositions<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        loop {
+            if self.mask != 0 {
+                let bit = self.mask.trailing_zeros() as usize;
+                self.mask &= self.mask - 1;
+                return Some(self.block_start + bit);
+            }
+            if self.next_offset >= self.haystack.len() {
+                return None;
+            }
+            let end = usize::min(self.next_offset + 32, self.haystack.len());
+            self.block_start = self.next_offset;
+            self.mask = self
+                .finder
+                .block_mask(&self.haystack[self.next_offset..end]);
+            self.next_offset = end;
+        }
+    }
+}
+
+/// Narrows a NEON byte-comparison result into a 64-bit mask with four bits
+/// per lane, the aarch64 analogue of `_mm256_movemask_epi8`.
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+unsafe fn neon_lane_mask(eq: uint8x16_t) -> u64 {
+    let narrowed = vshrn_n_u16::<4>(vreinterpretq_u16_u8(eq));
+    vget_lane_u64::<0>(vreinterpret_u64_u8(narrowed))
+}
+
+/// Returns the index of the first all-ones lane of a NEON comparison result.
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+unsafe fn first_matching_lane(eq: uint8x16_t) -> Option<usize> {
+    let mask = neon_lane_mask(eq);
+    if mask == 0 {
+        None
+    } else {
+        Some(mask.trailing_zeros() as usize / 4)
+    }
+}
+
+/// Finds the first occurrence of any of up to eight delimiter bytes,
+/// reporting both the position and which delimiter matched.
+#[derive(Debug, Clone, Copy)]
+pub struct SimdMultiDelimiterFinder {
+    delimiters: [u8; 8],
+    count: usize,
+}
+
+impl SimdMultiDelimiterFinder {
+    /// Builds a finder for the given delimiters.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `delimiters` is empty or holds more than eight bytes.
+    pub fn new(delimiters: &[u8]) -> Self {
+        assert!(
+            !delimiters.is_empty() && delimiters.len() <= 8,
+            "between 1 and 8 delimiters required"
+        );
+        let mut stored = [0u8; 8];
+        stored[..delimiters.len()].copy_from_slice(delimiters);
+        Self {
+            delimiters: stored,
+            count: delimiters.len(),
+        }
+    }
+
+    /// Returns the index of the first byte matching any delimiter, together
+    /// with the delimiter that matched.
+    pub fn find_in(&self, haystack: &[u8]) -> Option<(usize, u8)> {
+        #[cfg(target_arch = "x86_64")]
+        if avx2_available() {
+            // SAFETY: AVX2 presence verified at runtime.
+            return unsafe { self.find_in_avx2(haystack) };
+        }
+        self.find_in_scalar(haystack)
+    }
+
+    fn find_in_scalar(&self, haystack: &[u8]) -> Option<(usize, u8)> {
+        let delimiters = &self.delimiters[..self.count];
+        haystack
+            .iter()
+            .position(|b| delimiters.contains(b))
+            .map(|pos| (pos, haystack[pos]))
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[target_feature(enable = "avx2")]
+    unsafe fn find_in_avx2(&self, haystack: &[u8]) -> Option<(usize, u8)> {
+        let mut needles = [_mm256_setzero_si256(); 8];
+        for (needle, &delimiter) in needles.iter_mut().zip(&self.delimiters[..self.count]) {
+            *needle = _mm256_set1_epi8(delimiter as i8);
+        }
+        let needles = &needles[..self.count];
+        let mut offset = 0;
+        while offset + 32 <= haystack.len() {
+            let block = _mm256_loadu_si256(haystack.as_ptr().add(offset) as *const __m256i);
+            let mut any = _mm256_setzero_si256();
+            for needle in needles {
+                any = _mm256_or_si256(any, _mm256_cmpeq_epi8(block, *needle));
+            }
+            let mask = _mm256_movemask_epi8(any) as u32;
+            if mask != 0 {
+                let pos = offset + mask.trailing_zeros() as usize;
+                return Some((pos, haystack[pos]));
+            }
+            offset += 32;
+        }
+        self.find_in_scalar(&haystack[offset..])
+            .map(|(pos, delim)| (offset + pos, delim))
+    }
+}
+
+/// Finds the first occurrence of an arbitrary two-byte sequence, in the
+/// style of `memchr`: the vector pass locates candidates for the first
+/// byte and a cheap follow-up check confirms the second.
+#[derive(Debug, Clone, Copy)]
+pub struct SimdPairFinder {
+    first: u8,
+    second: u8,
+}
+
+impl SimdPairFinder {
+    pub fn new(first: u8, second: u8) -> Self {
+        Self { first, second }
+    }
+
+    /// Returns the index of the first position where `first` is
+    /// immediately followed by `second`.
+    pub fn find_in(&self, haystack: &[u8]) -> Option<usize> {
+        #[cfg(target_arch = "x86_64")]
+        if avx2_available() {
+            // SAFETY: AVX2 presence verified at runtime.
+            return unsafe { self.find_in_avx2(haystack) };
+        }
+        #[cfg(target_arch = "aarch64")]
+        {
+            // SAFETY: NEON is mandatory on aarch64.
+            return unsafe { self.find_in_neon(haystack) };
+        }
+        #[allow(unreachable_code)]
+        self.find_in_scalar(haystack)
+    }
+
+    fn find_in_scalar(&self, haystack: &[u8]) -> Option<usize> {
+        haystack
+            .windows(2)
+            .position(|w| w[0] == self.first && w[1] == self.second)
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[target_feature(enable = "avx2")]
+    unsafe fn find_in_avx2(&self, haystack: &[u8]) -> Option<usize> {
+        let first = _mm256_set1_epi8(self.first as i8);
+        let second = _mm256_set1_epi8(self.second as i8);
+        let mut offset = 0;
+        while offset + 32 <= haystack.len() {
+            let block = _mm256_loadu_si256(haystack.as_ptr().add(offset) as *const __m256i);
+            let first_mask = _mm256_movemask_epi8(_mm256_cmpeq_epi8(block, first)) as u32;
+            let second_mask = _mm256_movemask_epi8(_mm256_cmpeq_epi8(block, second)) as u32;
+            // A pair is a first-byte lane whose right neighbour matched the
+            // second byte, so align the two masks and intersect them.
+            let pair_mask = first_mask & (second_mask >> 1);
+            if pair_mask != 0 {
+                return Some(offset + pair_mask.trailing_zeros() as usize);
+            }
+            // The last lane's partner lives in the next block.
+            if first_mask >> 31 != 0 && haystack.get(offset + 32) == Some(&self.second) {
+                return Some(offset + 31);
+            }
+            offset += 32;
+        }
+        self.find_in_scalar(&haystack[offset..])
+            .map(|pos| offset + pos)
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    #[target_feature(enable = "neon")]
+    unsafe fn find_in_neon(&self, haystack: &[u8]) -> Option<usize> {
+        let first = vdupq_n_u8(self.first);
+        let second = vdupq_n_u8(self.second);
+        let mut offset = 0;
+        while offset + 16 <= haystack.len() {
+            let block = vld1q_u8(haystack.as_ptr().add(offset));
+            let first_mask = neon_lane_mask(vceqq_u8(block, first));
+            let second_mask = neon_lane_mask(vceqq_u8(block, second));
+            // Each lane occupies four mask bits, so the right neighbour sits
+            // one nibble over.
+            let pair_mask = first_mask & (second_mask >> 4);
+            if pair_mask != 0 {
+                return Some(offset + pair_mask.trailing_zeros() as usize / 4);
+            }
+            // The last lane's partner lives in the next block.
+            if first_mask >> 60 != 0 && haystack.get(offset + 16) == Some(&self.second) {
+                return Some(offset + 15);
+            }
+            offset += 16;
+        }
+        self.find_in_scalar(&haystack[offset..])
+            .map(|pos| offset + pos)
+    }
+}
+
+/// Finds the first CRLF (`\r\n`) sequence in a buffer.
+#[derive(Debug, Clone, Copy)]
+pub struct SimdCrlfFinder {
+    pair: SimdPairFinder,
+}
+
+impl SimdCrlfFinder {
+    pub fn new() -> Self {
+        Self {
+            pair: SimdPairFinder::new(b'\r', b'\n'),
+        }
+    }
+
+    /// Returns the index of the `\r` of the first CRLF pair.
+    pub fn find_crlf(&self, haystack: &[u8]) -> Option<usize> {
+        self.pair.find_in(haystack)
+    }
+}
+
+impl Default for SimdCrlfFinder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns whether `b` is an RFC 7230 `tchar`, legal in tokens such as
+/// header names and method names.
+const fn is_tchar(b: u8) -> bool {
+    matches!(b,
+        b'!' | b'#' | b'$' | b'%' | b'&' | b'\'' | b'*' | b'+' | b'-' | b'.'
+        | b'^' | b'_' | b'`' | b'|' | b'~'
+        | b'0'..=b'9' | b'a'..=b'z' | b'A'..=b'Z')
+}
+
+/// Per-byte tchar validity, for the scalar path and the block tails.
+const TCHAR_TABLE: [bool; 256] = {
+    let mut table = [false; 256];
+    let mut b = 0usize;
+    while b < 256 {
+        table[b] = is_tchar(b as u8);
+        b += 1;
+    }
+    table
+};
+
+/// Nibble-shuffle membership table for the AVX2 path: indexed by a byte's
+/// low nibble, each entry holds one bit per high nibble 0–7 that is set when
+/// that (high, low) combination is a tchar. High nibbles 8–15 are never
+/// tchars, so bytes above 0x7F fail the bit test.
+const TCHAR_ROWS: [u8; 16] = {
+    let mut rows = [0u8; 16];
+    let mut low = 0usize;
+    while low < 16 {
+        let mut high = 0usize;
+        while high < 8 {
+            if is_tchar((high * 16 + low) as u8) {
+                rows[low] |= 1 << high;
+            }
+            high += 1;
+        }
+        low += 1;
+    }
+    rows
+};
+
+/// Validates that a byte slice consists entirely of RFC 7230 `tchar`s,
+/// rejecting separators such as `(`, `,`, `/`, `:`, `<`, `@`, and `"` as
+/// well as controls and non-ASCII bytes.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SimdTokenValidator;
+
+impl SimdTokenValidator {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Returns `true` if every byte of `input` is a tchar. The empty slice
+    /// is not a valid token.
+    pub fn is_valid_token(&self, input: &[u8]) -> bool {
+        if input.is_empty() {
+            return false;
+        }
+        #[cfg(target_arch = "x86_64")]
+        if avx2_available() {
+            // SAFETY: AVX2 presence verified at runtime.
+            return unsafe { self.is_valid_token_avx2(input) };
+        }
+        self.is_valid_token_scalar(input)
+    }
+
+    fn is_valid_token_scalar(&self, input: &[u8]) -> bool {
+        input.iter().all(|&b| TCHAR_TABLE[b as usize])
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[target_feature(enable = "avx2")]
+    unsafe fn is_valid_token_avx2(&self, input: &[u8]) -> bool {
+        let rows_128 = _mm_loadu_si128(TCHAR_ROWS.as_ptr() as *const __m128i);
+        let rows = _mm256_broadcastsi128_si256(rows_128);
+        // bit_for_high[h] = 1 << h for h in 0..8, zero for 8..16, so bytes
+        // with the top bit set select an empty mask and are rejected.
+        let bit_for_high = _mm256_broadcastsi128_si256(_mm_setr_epi8(
+            1, 2, 4, 8, 16, 32, 64, -128, 0, 0, 0, 0, 0, 0, 0, 0,
+        ));
+        let low_mask = _mm256_set1_epi8(0x0f);
+        let zero = _mm256_setzero_si256();
+        let mut offset = 0;
+        while offset + 32 <= input.len() {
+            let block = _mm256_loadu_si256(input.as_ptr().add(offset) as *const __m256i);
+            let low = _mm256_and_si256(block, low_mask);
+            let high = _mm256_and_si256(_mm256_srli_epi16(block, 4), low_mask);
+            let row = _mm256_shuffle_epi8(rows, low);
+            let bit = _mm256_shuffle_epi8(bit_for_high, high);
+            let hit = _mm256_and_si256(row, bit);
+            // A byte is a tchar iff its selected row contains its high-nibble
+            // bit; `bit` is zero for bytes >= 0x80, which correctly fails.
+            let valid = _mm256_andnot_si256(
+                _mm256_cmpeq_epi8(hit, zero),
+                _mm256_set1_epi8(-1),
+            );
+            if _mm256_movemask_epi8(valid) as u32 != u32::MAX {
+                return false;
+            }
+            offset += 32;
+        }
+        input[offset..].iter().all(|&b| TCHAR_TABLE[b as usize])
+    }
+}
+
+/// Hex digit values, `0xff` marking non-hex bytes.
+const HEX_VALUE: [u8; 256] = {
+    let mut table = [0xff_u8; 256];
+    let mut b = 0usize;
+    while b < 256 {
+        table[b] = match b as u8 {
+            digit @ b'0'..=b'9' => digit - b'0',
+            lower @ b'a'..=b'f' => lower - b'a' + 10,
+            upper @ b'A'..=b'F' => upper - b'A' + 10,
+            _ => 0xff,
+        };
+        b += 1;
+    }
+    table
+};
+
+/// Parses leading hexadecimal digits, as found in chunk-size lines.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SimdHexParser;
+
+impl SimdHexParser {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Parses hex digits from the start of `input`, returning the value and
+    /// the number of bytes consumed. Parsing stops at the first non-hex
+    /// byte. Returns `None` when `input` does not begin with a hex digit or
+    /// the value would overflow `u64`.
+    pub fn parse_hex(&self, input: &[u8]) -> Option<(u64, usize)> {
+        let run = self.hex_run_len(input);
+        if run == 0 {
+            return None;
+        }
+        let zeros = input[..run].iter().take_while(|&&b| b == b'0').count();
+        if run - zeros > 16 {
+            return None;
+        }
+        let mut value = 0u64;
+        for &b in &input[zeros..run] {
+            value = (value << 4) | u64::from(HEX_VALUE[b as usize]);
+        }
+        Some((value, run))
+    }
+
+    /// Returns the length of the leading run of hex digits.
+    fn hex_run_len(&self, input: &[u8]) -> usize {
+        #[cfg(target_arch = "x86_64")]
+        if avx2_available() {
+            // SAFETY: AVX2 presence verified at runtime.
+            return unsafe { self.hex_run_len_avx2(input) };
+        }
+        self.hex_run_len_scalar(input)
+    }
+
+    fn hex_run_len_scalar(&self, input: &[u8]) -> usize {
+        input
+            .iter()
+            .position(|&b| HEX_VALUE[b as usize] == 0xff)
+            .unwrap_or(input.len())
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[target_feature(enable = "avx2")]
+    unsafe fn hex_run_len_avx2(&self, input: &[u8]) -> usize {
+        let below_0 = _mm256_set1_epi8(b'0' as i8 - 1);
+        let above_9 = _mm256_set1_epi8(b'9' as i8 + 1);
+        let below_upper_a = _mm256_set1_epi8(b'A' as i8 - 1);
+        let above_upper_f = _mm256_set1_epi8(b'F' as i8 + 1);
+        let below_lower_a = _mm256_set1_epi8(b'a' as i8 - 1);
+        let above_lower_f = _mm256_set1_epi8(b'f' as i8 + 1);
+        let mut offset = 0;
+        while offset + 32 <= input.len() {
+            let block = _mm256_loadu_si256(input.as_ptr().add(offset) as *const __m256i);
+            let digit = _mm256_and_si256(
+                _mm256_cmpgt_epi8(block, below_0),
+                _mm256_cmpgt_epi8(above_9, block),
+            );
+            let upper = _mm256_and_si256(
+                _mm256_cmpgt_epi8(block, below_upper_a),
+                _mm256_cmpgt_epi8(above_upper_f, block),
+            );
+            let lower = _mm256_and_si256(
+                _mm256_cmpgt_epi8(block, below_lower_a),
+                _mm256_cmpgt_epi8(above_lower_f, block),
+            );
+            let hex = _mm256_or_si256(digit, _mm256_or_si256(upper, lower));
+            let non_hex = !(_mm256_movemask_epi8(hex) as u32);
+            if non_hex != 0 {
+                return offset + non_hex.trailing_zeros() as usize;
+            }
+            offset += 32;
+        }
+        offset + self.hex_run_len_scalar(&input[offset..])
+    }
+}
+
+/// Skips leading SP / HTAB characters.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SimdWhitespaceSkipper;
+
+impl SimdWhitespaceSkipper {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Returns the index of the first byte that is not SP or HTAB.
+    pub fn skip(&self, input: &[u8]) -> usize {
+        input
+            .iter()
+            .position(|&b| b != b' ' && b != b'\t')
+            .unwrap_or(input.len())
+    }
+
+    /// Returns the input without trailing SP / HTAB bytes, scanning
+    /// backward in 32-byte blocks; all-whitespace input trims to empty.
+    pub fn trim_end<'a>(&self, input: &'a [u8]) -> &'a [u8] {
+        #[cfg(target_arch = "x86_64")]
+        if avx2_available() {
+            // SAFETY: AVX2 presence verified at runtime.
+            return unsafe { self.trim_end_avx2(input) };
+        }
+        self.trim_end_scalar(input)
+    }
+
+    fn trim_end_scalar<'a>(&self, input: &'a [u8]) -> &'a [u8] {
+        let end = input
+            .iter()
+            .rposition(|&b| b != b' ' && b != b'\t')
+            .map_or(0, |pos| pos + 1);
+        &input[..end]
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[target_feature(enable = "avx2")]
+    unsafe fn trim_end_avx2<'a>(&self, input: &'a [u8]) -> &'a [u8] {
+        let sp = _mm256_set1_epi8(b' ' as i8);
+        let tab = _mm256_set1_epi8(b'\t' as i8);
+        let mut end = input.len();
+        while end >= 32 {
+            let block = _mm256_loadu_si256(input.as_ptr().add(end - 32) as *const __m256i);
+            let ws = _mm256_or_si256(
+                _mm256_cmpeq_epi8(block, sp),
+                _mm256_cmpeq_epi8(block, tab),
+            );
+            let non_ws = !(_mm256_movemask_epi8(ws) as u32);
+            if non_ws != 0 {
+                // The highest set bit is the last non-whitespace byte.
+                return &input[..end - non_ws.leading_zeros() as usize];
+            }
+            end -= 32;
+        }
+        self.trim_end_scalar(&input[..end])
+    }
+}
+
+/// Reports whether a buffer contains any ASCII uppercase byte.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SimdUppercaseScanner;
+
+impl SimdUppercaseScanner {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn contains_uppercase(&self, buf: &[u8]) -> bool {
+        #[cfg(target_arch = "x86_64")]
+        if avx2_available() {
+            // SAFETY: AVX2 presence verified at runtime.
+            return unsafe { self.contains_uppercase_avx2(buf) };
+        }
+        #[cfg(target_arch = "aarch64")]
+        {
+            // SAFETY: NEON is mandatory on aarch64.
+            return unsafe { self.contains_uppercase_neon(buf) };
+        }
+        #[allow(unreachable_code)]
+        self.contains_uppercase_scalar(buf)
+    }
+
+    fn contains_uppercase_scalar(&self, buf: &[u8]) -> bool {
+        buf.iter().any(u8::is_ascii_uppercase)
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[target_feature(enable = "avx2")]
+    unsafe fn contains_uppercase_avx2(&self, buf: &[u8]) -> bool {
+        let upper_a = _mm256_set1_epi8(b'A' as i8 - 1);
+        let upper_z = _mm256_set1_epi8(b'Z' as i8 + 1);
+        let mut offset = 0;
+        while offset + 32 <= buf.len() {
+            let block = _mm256_loadu_si256(buf.as_ptr().add(offset) as *const __m256i);
+            let gt = _mm256_cmpgt_epi8(block, upper_a);
+            let lt = _mm256_cmpgt_epi8(upper_z, block);
+            if _mm256_movemask_epi8(_mm256_and_si256(gt, lt)) != 0 {
+                return true;
+            }
+            offset += 32;
+        }
+        self.contains_uppercase_scalar(&buf[offset..])
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    #[target_feature(enable = "neon")]
+    unsafe fn contains_uppercase_neon(&self, buf: &[u8]) -> bool {
+        let upper_a = vdupq_n_u8(b'A');
+        let upper_z = vdupq_n_u8(b'Z');
+        let mut offset = 0;
+        while offset + 16 <= buf.len() {
+            let block = vld1q_u8(buf.as_ptr().add(offset));
+            let ge = vcgeq_u8(block, upper_a);
+            let le = vcleq_u8(block, upper_z);
+            if vmaxvq_u8(vandq_u8(ge, le)) != 0 {
+                return true;
+            }
+            offset += 16;
+        }
+        self.contains_uppercase_scalar(&buf[offset..])
+    }
+}
+
+/// Converts ASCII `a`–`z` to uppercase in place, leaving other bytes alone.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SimdUppercaseConverter;
+
+impl SimdUppercaseConverter {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn convert(&self, buf: &mut [u8]) {
+        #[cfg(target_arch = "x86_64")]
+        if avx2_available() {
+            // SAFETY: AVX2 presence verified at runtime.
+            unsafe { self.convert_avx2(buf) };
+            return;
+        }
+        #[cfg(target_arch = "aarch64")]
+        {
+            // SAFETY: NEON is mandatory on aarch64.
+            unsafe { self.convert_neon(buf) };
+            return;
+        }
+        #[allow(unreachable_code)]
+        self.convert_scalar(buf)
+    }
+
+    fn convert_scalar(&self, buf: &mut [u8]) {
+        for b in buf {
+            b.make_ascii_uppercase();
+        }
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[target_feature(enable = "avx2")]
+    unsafe fn convert_avx2(&self, buf: &mut [u8]) {
+        let lower_a = _mm256_set1_epi8(b'a' as i8 - 1);
+        let lower_z = _mm256_set1_epi8(b'z' as i8 + 1);
+        let case_bit = _mm256_set1_epi8(0x20);
+        let mut offset = 0;
+        while offset + 32 <= buf.len() {
+            let ptr = buf.as_mut_ptr().add(offset) as *mut __m256i;
+            let block = _mm256_loadu_si256(ptr as *const __m256i);
+            let gt = _mm256_cmpgt_epi8(block, lower_a);
+            let lt = _mm256_cmpgt_epi8(lower_z, block);
+            let is_lower = _mm256_and_si256(gt, lt);
+            let to_clear = _mm256_and_si256(is_lower, case_bit);
+            _mm256_storeu_si256(ptr, _mm256_xor_si256(block, to_clear));
+            offset += 32;
+        }
+        for b in &mut buf[offset..] {
+            b.make_ascii_uppercase();
+        }
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    #[target_feature(enable = "neon")]
+    unsafe fn convert_neon(&self, buf: &mut [u8]) {
+        let lower_a = vdupq_n_u8(b'a');
+        let lower_z = vdupq_n_u8(b'z');
+        let case_bit = vdupq_n_u8(0x20);
+        let mut offset = 0;
+        while offset + 16 <= buf.len() {
+            let ptr = buf.as_mut_ptr().add(offset);
+            let block = vld1q_u8(ptr);
+            let ge = vcgeq_u8(block, lower_a);
+            let le = vcleq_u8(block, lower_z);
+            let is_lower = vandq_u8(ge, le);
+            let to_clear = vandq_u8(is_lower, case_bit);
+            vst1q_u8(ptr, veorq_u8(block, to_clear));
+            offset += 16;
+        }
+        for b in &mut buf[offset..] {
+            b.make_ascii_uppercase();
+        }
+    }
+}
+
+/// Converts ASCII `A`–`Z` to lowercase in place, leaving other bytes alone.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SimdLowercaseConverter;
+
+impl SimdLowercaseConverter {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn convert(&self, buf: &mut [u8]) {
+        #[cfg(target_arch = "x86_64")]
+        if avx2_available() {
+            // SAFETY: AVX2 presence verified at runtime.
+            unsafe { self.convert_avx2(buf) };
+            return;
+        }
+        #[cfg(target_arch = "aarch64")]
+        {
+            // SAFETY: NEON is mandatory on aarch64.
+            unsafe { self.convert_neon(buf) };
+            return;
+        }
+        #[allow(unreachable_code)]
+        self.convert_scalar(buf)
+    }
+
+    fn convert_scalar(&self, buf: &mut [u8]) {
+        for b in buf {
+            b.make_ascii_lowercase();
+        }
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[target_feature(enable = "avx2")]
+    unsafe fn convert_avx2(&self, buf: &mut [u8]) {
+        let upper_a = _mm256_set1_epi8(b'A' as i8 - 1);
+        let upper_z = _mm256_set1_epi8(b'Z' as i8 + 1);
+        let case_bit = _mm256_set1_epi8(0x20);
+        let mut offset = 0;
+        while offset + 32 <= buf.len() {
+            let ptr = buf.as_mut_ptr().add(offset) as *mut __m256i;
+            let block = _mm256_loadu_si256(ptr as *const __m256i);
+            let gt = _mm256_cmpgt_epi8(block, upper_a);
+            let lt = _mm256_cmpgt_epi8(upper_z, block);
+            let is_upper = _mm256_and_si256(gt, lt);
+            let to_set = _mm256_and_si256(is_upper, case_bit);
+            _mm256_storeu_si256(ptr, _mm256_xor_si256(block, to_set));
+            offset += 32;
+        }
+        for b in &mut buf[offset..] {
+            b.make_ascii_lowercase();
+        }
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    #[target_feature(enable = "neon")]
+    unsafe fn convert_neon(&self, buf: &mut [u8]) {
+        let upper_a = vdupq_n_u8(b'A');
+        let upper_z = vdupq_n_u8(b'Z');
+        let case_bit = vdupq_n_u8(0x20);
+        let mut offset = 0;
+        while offset + 16 <= buf.len() {
+            let ptr = buf.as_mut_ptr().add(offset);
+            let block = vld1q_u8(ptr);
+            let ge = vcgeq_u8(block, upper_a);
+            let le = vcleq_u8(block, upper_z);
+            let is_upper = vandq_u8(ge, le);
+            let to_set = vandq_u8(is_upper, case_bit);
+            vst1q_u8(ptr, veorq_u8(block, to_set));
+            offset += 16;
+        }
+        for b in &mut buf[offset..] {
+            b.make_ascii_lowercase();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delimiter_finder_matches_scalar_positions() {
+        let finder = SimdDelimiterFinder::new(b' ');
+        let haystack = b"GET /a/very/long/path/that/spans/more/than/thirty-two/bytes HTTP/1.1";
+        assert_eq!(finder.find_in(haystack), Some(3));
+        assert_eq!(finder.find_in(b"no-delimiter-here"), None);
+        assert_eq!(finder.find_in(b""), None);
+    }
+
+    #[test]
+    fn delimiter_finder_past_block_boundary() {
+        let mut haystack = vec![b'x'; 70];
+        haystack[65] = b',';
+        let finder = SimdDelimiterFinder::new(b',');
+        assert_eq!(finder.find_in(&haystack), Some(65));
+    }
+
+    #[test]
+    fn find_all_yields_every_position() {
+        let finder = SimdDelimiterFinder::new(b',');
+        let value = b"gzip, deflate, br, zstd, identity, chunked, trailers, compress";
+        let positions: Vec<usize> = finder.find_all(value).collect();
+        let expected: Vec<usize> = value
+            .iter()
+            .enumerate()
+            .filter(|(_, &b)| b == b',')
+            .map(|(i, _)| i)
+            .collect();
+        assert_eq!(positions, expected);
+        assert_eq!(finder.count_in(value), expected.len());
+    }
+
+    #[test]
+    fn find_all_across_block_boundaries() {
+        let finder = SimdDelimiterFinder::new(b',');
+        let mut value = vec![b'x'; 100];
+        for pos in [0, 31, 32, 33, 63, 64, 99] {
+            value[pos] = b',';
+        }
+        let positions: Vec<usize> = finder.find_all(&value).collect();
+        assert_eq!(positions, vec![0, 31, 32, 33, 63, 64, 99]);
+        assert!(finder.find_all(b"").next().is_none());
+    }
+
+    #[test]
+    fn multi_delimiter_finder_reports_match() {
+        let finder = SimdMultiDelimiterFinder::new(b" ?#");
+        let mut haystack = vec![b'a'; 80];
+        haystack[50] = b'?';
+        haystack[60] = b' ';
+        assert_eq!(finder.find_in(&haystack), Some((50, b'?')));
+        assert_eq!(finder.find_in(b"/path#frag?query"), Some((5, b'#')));
+        assert_eq!(finder.find_in(b"nothing-to-see"), None);
+    }
+
+    #[test]
+    fn multi_delimiter_finder_matches_scalar() {
+        let finder = SimdMultiDelimiterFinder::new(b" ?#");
+        let mut haystack = noisy_buffer(257);
+        haystack[33] = b'#';
+        assert_eq!(finder.find_in(&haystack), finder.find_in_scalar(&haystack));
+    }
+
+    #[test]
+    #[should_panic(expected = "between 1 and 8 delimiters")]
+    fn multi_delimiter_finder_rejects_too_many() {
+        SimdMultiDelimiterFinder::new(b"abcdefghi");
+    }
+
+    #[test]
+    fn crlf_finder_basic() {
+        let finder = SimdCrlfFinder::new();
+        assert_eq!(finder.find_crlf(b"GET / HTTP/1.1\r\nHost: x\r\n"), Some(14));
+        assert_eq!(finder.find_crlf(b"no terminator"), None);
+        assert_eq!(finder.find_crlf(b"\r"), None);
+    }
+
+    #[test]
+    fn crlf_finder_ignores_bare_cr() {
+        let finder = SimdCrlfFinder::new();
+        let mut haystack = vec![b'\r'; 40];
+        haystack.push(b'\n');
+        assert_eq!(finder.find_crlf(&haystack), Some(39));
+    }
+
+    #[test]
+    fn finders_handle_sixteen_byte_blocks() {
+        // NEON operates on 16-byte blocks; make sure matches right at and
+        // just past that boundary are found on every arch.
+        let mut haystack = vec![b'x'; 20];
+        haystack[15] = b'?';
+        assert_eq!(SimdDelimiterFinder::new(b'?').find_in(&haystack), Some(15));
+        haystack[15] = b'\r';
+        haystack[16] = b'\n';
+        assert_eq!(SimdCrlfFinder::new().find_crlf(&haystack), Some(15));
+    }
+
+    #[test]
+    fn crlf_buried_in_a_run_of_bare_crs() {
+        // Every lane of every block matches CR; only one is followed by LF.
+        let mut haystack = vec![b'\r'; 96];
+        haystack[50] = b'\n';
+        assert_eq!(SimdCrlfFinder::new().find_crlf(&haystack), Some(49));
+    }
+
+    #[test]
+    fn pair_finder_finds_double_dash_across_block_boundaries() {
+        let finder = SimdPairFinder::new(b'-', b'-');
+        // Straddle the AVX2 32-byte block boundary: `-` at 31, `-` at 32.
+        let mut haystack = vec![b'x'; 64];
+        haystack[31] = b'-';
+        haystack[32] = b'-';
+        assert_eq!(finder.find_in(&haystack), Some(31));
+        assert_eq!(finder.find_in_scalar(&haystack), Some(31));
+    }
+
+    #[test]
+    fn pair_finder_skips_lone_first_bytes() {
+        let finder = SimdPairFinder::new(b'-', b'-');
+        let mut haystack = vec![b'-'; 40];
+        for b in haystack.iter_mut().step_by(2) {
+            *b = b'x';
+        }
+        assert_eq!(finder.find_in(&haystack), None);
+        haystack.extend_from_slice(b"x--");
+        assert_eq!(finder.find_in(&haystack), Some(41));
+    }
+
+    #[test]
+    fn hex_parser_parses_values() {
+        let parser = SimdHexParser::new();
+        assert_eq!(parser.parse_hex(b"ffff"), Some((0xffff, 4)));
+        assert_eq!(parser.parse_hex(b"DeadBeef"), Some((0xdead_beef, 8)));
+        assert_eq!(parser.parse_hex(b"0"), Some((0, 1)));
+        assert_eq!(parser.parse_hex(b"1a; ext=1"), Some((0x1a, 2)));
+        assert_eq!(
+            parser.parse_hex(b"ffffffffffffffff"),
+            Some((u64::MAX, 16))
+        );
+    }
+
+    #[test]
+    fn hex_parser_rejects_invalid_input() {
+        let parser = SimdHexParser::new();
+        assert_eq!(parser.parse_hex(b""), None);
+        assert_eq!(parser.parse_hex(b"xyz"), None);
+        // 20 digits overflow u64.
+        assert_eq!(parser.parse_hex(b"ffffffffffffffffffff"), None);
+        // Leading zeros do not count against the overflow limit.
+        assert_eq!(parser.parse_hex(b"0000000000000000000f"), Some((0xf, 20)));
+    }
+
+    #[test]
+    fn hex_parser_long_runs_match_scalar() {
+        let parser = SimdHexParser::new();
+        let mut input = vec![b'0'; 100];
+        input[40] = b'g';
+        assert_eq!(parser.hex_run_len(&input), parser.hex_run_len_scalar(&input));
+        assert_eq!(parser.hex_run_len(&input), 40);
+    }
+
+    #[test]
+    fn whitespace_skipper() {
+        let skipper = SimdWhitespaceSkipper::new();
+        assert_eq!(skipper.skip(b"  \tvalue"), 3);
+        assert_eq!(skipper.skip(b"value"), 0);
+        assert_eq!(skipper.skip(b"   "), 3);
+    }
+
+    #[test]
+    fn trim_end_strips_long_trailing_whitespace() {
+        let skipper = SimdWhitespaceSkipper::new();
+        let mut input = b"value".to_vec();
+        input.extend(std::iter::repeat_n(b' ', 40));
+        assert_eq!(skipper.trim_end(&input), b"value");
+        assert_eq!(
+            skipper.trim_end(&input),
+            skipper.trim_end_scalar(&input)
+        );
+    }
+
+    #[test]
+    fn trim_end_of_all_whitespace_is_empty() {
+        let skipper = SimdWhitespaceSkipper::new();
+        assert_eq!(skipper.trim_end(b" \t \t "), b"");
+        assert_eq!(skipper.trim_end(&[b'\t'; 64]), b"");
+        assert_eq!(skipper.trim_end(b""), b"");
+    }
+
+    #[test]
+    fn trim_end_preserves_internal_whitespace() {
+        let skipper = SimdWhitespaceSkipper::new();
+        assert_eq!(skipper.trim_end(b"a b\tc  "), b"a b\tc");
+        assert_eq!(skipper.trim_end(b"no-trailing"), b"no-trailing");
+    }
+
+    #[test]
+    fn token_validator_accepts_tchars() {
+        let validator = SimdTokenValidator::new();
+        assert!(validator.is_valid_token(b"Content-Type"));
+        assert!(validator.is_valid_token(b"a-fairly-long-header-name-over-32-bytes-x"));
+        assert!(validator.is_valid_token(b"!#$%&'*+-.^_`|~09azAZ"));
+        assert!(!validator.is_valid_token(b""));
+    }
+
+    #[test]
+    fn token_validator_rejects_separators_in_simd_blocks() {
+        let validator = SimdTokenValidator::new();
+        // 40 characters with an embedded colon, so the offending byte sits
+        // inside a full 32-byte SIMD block.
+        let name = b"X-Some-Header-Name:With-Embedded-Colon-Z";
+        assert_eq!(name.len(), 40);
+        assert!(!validator.is_valid_token(name));
+        for sep in b"()<>@,;\\\"/[]?={} \t" {
+            let mut token = vec![b'a'; 40];
+            token[20] = *sep;
+            assert!(!validator.is_valid_token(&token), "separator {:?}", *sep as char);
+        }
+        let mut high = vec![b'a'; 40];
+        high[33] = 0xe9;
+        assert!(!validator.is_valid_token(&high));
+    }
+
+    #[test]
+    fn token_validator_matches_scalar() {
+        let validator = SimdTokenValidator::new();
+        for len in [1usize, 31, 32, 33, 64, 100] {
+            let buf = noisy_buffer(len);
+            assert_eq!(
+                validator.is_valid_token(&buf),
+                validator.is_valid_token_scalar(&buf),
+                "len {len}"
+            );
+        }
+    }
+
+    #[test]
+    fn uppercase_converter_long_buffer() {
+        let converter = SimdUppercaseConverter::new();
+        let mut buf = b"content-type: text/html; charset=utf-8 and some more text".to_vec();
+        let mut expected = buf.clone();
+        expected.make_ascii_uppercase();
+        converter.convert(&mut buf);
+        assert_eq!(buf, expected);
+    }
+
+    #[test]
+    fn lowercase_converter_long_buffer() {
+        let converter = SimdLowercaseConverter::new();
+        // A full 64-byte buffer of uppercase: two whole AVX2 blocks.
+        let mut buf = vec![b'Q'; 64];
+        converter.convert(&mut buf);
+        assert_eq!(buf, vec![b'q'; 64]);
+
+        let mut name = b"Content-Type: text/html; charset=UTF-8 and some more text".to_vec();
+        let mut expected = name.clone();
+        expected.make_ascii_lowercase();
+        converter.convert(&mut name);
+        assert_eq!(name, expected);
+
+        let mut dispatched = noisy_buffer(513);
+        let mut scalar = dispatched.clone();
+        converter.convert(&mut dispatched);
+        converter.convert_scalar(&mut scalar);
+        assert_eq!(dispatched, scalar);
+    }
+
+    #[test]
+    fn uppercase_scanner_matches_scalar() {
+        let scanner = SimdUppercaseScanner::new();
+        assert!(!scanner.contains_uppercase(b"content-type"));
+        assert!(scanner.contains_uppercase(b"Content-Type"));
+        // An uppercase byte past the first vector block.
+        let mut buf = vec![b'x'; 70];
+        assert!(!scanner.contains_uppercase(&buf));
+        buf[65] = b'Q';
+        assert!(scanner.contains_uppercase(&buf));
+        for len in [1usize, 31, 32, 33, 64, 100] {
+            let buf = noisy_buffer(len);
+            assert_eq!(
+                scanner.contains_uppercase(&buf),
+                scanner.contains_uppercase_scalar(&buf),
+                "len {len}"
+            );
+        }
+    }
+
+    /// A deterministic pseudo-random buffer exercising block boundaries.
+    fn noisy_buffer(len: usize) -> Vec<u8> {
+        let mut state = 0x243f_6a88u32;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                (state >> 24) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn dispatched_paths_match_forced_scalar() {
+        let haystack = noisy_buffer(513);
+
+        for delimiter in [b' ', b'\r', b'\x00', b'\xff'] {
+            let finder = SimdDelimiterFinder::new(delimiter);
+            assert_eq!(
+                finder.find_in(&haystack),
+                finder.find_in_scalar(&haystack),
+                "delimiter {delimiter:#x}"
+            );
+        }
+
+        let crlf = SimdCrlfFinder::new();
+        assert_eq!(crlf.find_crlf(&haystack), crlf.pair.find_in_scalar(&haystack));
+        let mut with_crlf = haystack.clone();
+        with_crlf[200] = b'\r';
+        with_crlf[201] = b'\n';
+        assert_eq!(
+            crlf.find_crlf(&with_crlf),
+            crlf.pair.find_in_scalar(&with_crlf)
+        );
+
+        let converter = SimdUppercaseConverter::new();
+        let mut dispatched = haystack.clone();
+        converter.convert(&mut dispatched);
+        let mut scalar = haystack.clone();
+        converter.convert_scalar(&mut scalar);
+        assert_eq!(dispatched, scalar);
+    }
+}
@@ -0,0 +1,135 @@
+//! Server-Sent Events (`text/event-stream`) response framing, chunked so
+//! the connection can stay open while events trickle out.
+
+use std::io::Write as _;
+
+/// Builds a streaming event-source response: the response head first,
+/// then each event as one chunked-encoding chunk.
+///
+/// Call [`take`](Self::take) after writing to drain the bytes ready to
+/// send, and [`finish`](Self::finish) to close the stream with the
+/// terminal chunk.
+pub struct EventStreamBuilder {
+    out: Vec<u8>,
+}
+
+impl EventStreamBuilder {
+    /// Starts a stream, queuing the `200 OK` head with the event-stream
+    /// content type, `Cache-Control: no-cache`, and chunked framing.
+    pub fn new() -> Self {
+        let mut out = Vec::with_capacity(256);
+        out.extend_from_slice(
+            b"HTTP/1.1 200 OK\r\n\
+              Content-Type: text/event-stream\r\n\
+              Cache-Control: no-cache\r\n\
+              Transfer-Encoding: chunked\r\n\r\n",
+        );
+        Self { out }
+    }
+
+    /// Queues one event. Each line of `data` becomes its own `data:`
+    /// field, as the wire format has no other way to carry a newline.
+    pub fn write_event(&mut self, event: Option<&str>, data: &str, id: Option<&str>) {
+        let mut payload = Vec::with_capacity(data.len() + 32);
+        if let Some(event) = event {
+            payload.extend_from_slice(b"event: ");
+            payload.extend_from_slice(event.as_bytes());
+            payload.push(b'\n');
+        }
+        for line in data.split('\n') {
+            payload.extend_from_slice(b"data: ");
+            payload.extend_from_slice(line.as_bytes());
+            payload.push(b'\n');
+        }
+        if let Some(id) = id {
+            payload.extend_from_slice(b"id: ");
+            payload.extend_from_slice(id.as_bytes());
+            payload.push(b'\n');
+        }
+        payload.push(b'\n');
+        self.write_chunk(&payload);
+    }
+
+    /// Queues a comment (`: text`), the conventional keep-alive so
+    /// proxies don't drop an idle stream.
+    pub fn write_comment(&mut self, comment: &str) {
+        let mut payload = Vec::with_capacity(comment.len() + 4);
+        payload.extend_from_slice(b": ");
+        payload.extend_from_slice(comment.as_bytes());
+        payload.extend_from_slice(b"\n\n");
+        self.write_chunk(&payload);
+    }
+
+    /// Drains the bytes queued so far, ready to write to the connection.
+    pub fn take(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.out)
+    }
+
+    /// Ends the stream, returning any queued bytes plus the terminal
+    /// zero-length chunk.
+    pub fn finish(mut self) -> Vec<u8> {
+        self.out.extend_from_slice(b"0\r\n\r\n");
+        self.out
+    }
+
+    fn write_chunk(&mut self, payload: &[u8]) {
+        let _ = write!(self.out, "{:x}\r\n", payload.len());
+        self.out.extend_from_slice(payload);
+        self.out.extend_from_slice(b"\r\n");
+    }
+}
+
+impl Default for EventStreamBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn head_advertises_event_stream_framing() {
+        let mut stream = EventStreamBuilder::new();
+        let head = String::from_utf8(stream.take()).unwrap();
+        assert!(head.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(head.contains("Content-Type: text/event-stream\r\n"));
+        assert!(head.contains("Cache-Control: no-cache\r\n"));
+        assert!(head.contains("Transfer-Encoding: chunked\r\n"));
+        assert!(head.ends_with("\r\n\r\n"));
+    }
+
+    #[test]
+    fn multi_line_data_becomes_multiple_data_fields() {
+        let mut stream = EventStreamBuilder::new();
+        stream.take();
+        stream.write_event(Some("update"), "line one\nline two", Some("7"));
+        assert_eq!(
+            stream.take(),
+            b"33\r\nevent: update\ndata: line one\ndata: line two\nid: 7\n\n\r\n"
+        );
+    }
+
+    #[test]
+    fn bare_data_event_has_no_optional_fields() {
+        let mut stream = EventStreamBuilder::new();
+        stream.take();
+        stream.write_event(None, "tick", None);
+        assert_eq!(stream.take(), b"c\r\ndata: tick\n\n\r\n");
+    }
+
+    #[test]
+    fn keep_alive_comment_is_chunked() {
+        let mut stream = EventStreamBuilder::new();
+        stream.take();
+        stream.write_comment("ping");
+        assert_eq!(stream.take(), b"8\r\n: ping\n\n\r\n");
+    }
+
+    #[test]
+    fn finish_appends_the_terminal_chunk() {
+        let stream = EventStreamBuilder::new();
+        assert!(stream.finish().ends_with(b"0\r\n\r\n"));
+    }
+}
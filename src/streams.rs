@@ -0,0 +1,531 @@
+//! HTTP/2 stream bookkeeping (RFC 7540 §5): per-stream state and the
+//! concurrency limit a connection enforces over its peer.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// The RST_STREAM error code for a protocol violation (RFC 7540 §7).
+pub const PROTOCOL_ERROR: u32 = 0x1;
+
+/// The RST_STREAM error code for a frame-size violation confined to one
+/// stream (RFC 7540 §7).
+pub const FRAME_SIZE_ERROR: u32 = 0x6;
+
+/// The RST_STREAM error code for a stream refused before processing
+/// (RFC 7540 §7).
+pub const REFUSED_STREAM: u32 = 0x7;
+
+/// The RST_STREAM error code for a peer generating excessive load
+/// (RFC 7540 §7), used when a stream's body outgrows the configured
+/// request-size cap.
+pub const ENHANCE_YOUR_CALM: u32 = 0xb;
+
+/// The weight of a stream that never carried a PRIORITY (RFC 7540 §5.3.5).
+pub const DEFAULT_PRIORITY_WEIGHT: u16 = 16;
+
+/// The lifecycle state of one stream (RFC 7540 §5.1).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamState {
+    Open,
+    HalfClosedLocal,
+    HalfClosedRemote,
+    Closed,
+}
+
+/// A single HTTP/2 stream.
+#[derive(Debug, Clone)]
+pub struct Stream {
+    pub id: u32,
+    pub state: StreamState,
+    /// When the stream last saw a frame; drives idle reaping.
+    pub last_activity: Instant,
+    /// DATA bytes received so far, checked against the connection's
+    /// request-size cap.
+    pub received_bytes: u64,
+}
+
+/// Errors produced by stream bookkeeping.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamError {
+    /// Opening the stream would exceed the advertised concurrency limit;
+    /// answer with `RST_STREAM(REFUSED_STREAM)`, which tells the peer the
+    /// request is safe to retry (RFC 7540 §8.1.4).
+    RefusedStream,
+    /// A stream declared itself as its own dependency; answer with
+    /// `RST_STREAM(PROTOCOL_ERROR)` (RFC 7540 §5.3.1).
+    SelfDependency,
+}
+
+impl fmt::Display for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StreamError::RefusedStream => f.write_str("stream refused: concurrency limit reached"),
+            StreamError::SelfDependency => f.write_str("stream cannot depend on itself"),
+        }
+    }
+}
+
+impl std::error::Error for StreamError {}
+
+/// Tracks the streams of one connection against a concurrency limit.
+#[derive(Debug, Clone, Default)]
+pub struct StreamManager {
+    streams: HashMap<u32, Stream>,
+    /// `SETTINGS_MAX_CONCURRENT_STREAMS`; `None` means unlimited.
+    max_concurrent: Option<u32>,
+}
+
+impl StreamManager {
+    pub fn new(max_concurrent: Option<u32>) -> Self {
+        Self {
+            streams: HashMap::new(),
+            max_concurrent,
+        }
+    }
+
+    /// Applies a newly negotiated concurrency limit. Streams already open
+    /// are unaffected; only new openings are checked against it.
+    pub fn set_max_concurrent(&mut self, limit: Option<u32>) {
+        self.max_concurrent = limit;
+    }
+
+    pub fn max_concurrent(&self) -> Option<u32> {
+        self.max_concurrent
+    }
+
+    /// Number of streams counting against the concurrency limit.
+    pub fn active_count(&self) -> usize {
+        self.streams
+            .values()
+            .filter(|stream| stream.state != StreamState::Closed)
+            .count()
+    }
+
+    pub fn get(&self, id: u32) -> Option<&Stream> {
+        self.streams.get(&id)
+    }
+
+    pub fn get_mut(&mut self, id: u32) -> Option<&mut Stream> {
+        self.streams.get_mut(&id)
+    }
+
+    /// Opens the stream, enforcing the concurrency limit. Opening a stream
+    /// that already exists only refreshes its activity timestamp.
+    pub fn open(&mut self, id: u32) -> Result<&mut Stream, StreamError> {
+        if !self.streams.contains_key(&id) {
+            if let Some(limit) = self.max_concurrent {
+                if self.active_count() >= limit as usize {
+                    return Err(StreamError::RefusedStream);
+                }
+            }
+            self.streams.insert(
+                id,
+                Stream {
+                    id,
+                    state: StreamState::Open,
+                    last_activity: Instant::now(),
+                    received_bytes: 0,
+                },
+            );
+        }
+        let stream = self.streams.get_mut(&id).expect("inserted above");
+        stream.last_activity = Instant::now();
+        Ok(stream)
+    }
+
+    /// Marks the stream closed; it stops counting against the limit and
+    /// is reaped by [`StreamManager::cleanup_closed_streams`].
+    pub fn close(&mut self, id: u32) {
+        if let Some(stream) = self.streams.get_mut(&id) {
+            stream.state = StreamState::Closed;
+        }
+    }
+
+    /// Removes every closed stream, returning how many were removed.
+    pub fn cleanup_closed_streams(&mut self) -> usize {
+        let before = self.streams.len();
+        self.streams
+            .retain(|_, stream| stream.state != StreamState::Closed);
+        before - self.streams.len()
+    }
+
+    /// Removes streams that are closed or half-closed and have been idle
+    /// longer than `max_idle`, returning how many were reaped. Open
+    /// streams are never reaped here — only ones a peer could have
+    /// finished but abandoned.
+    pub fn cleanup_idle(&mut self, max_idle: Duration) -> usize {
+        let before = self.streams.len();
+        self.streams.retain(|_, stream| {
+            stream.state == StreamState::Open || stream.last_activity.elapsed() <= max_idle
+        });
+        before - self.streams.len()
+    }
+}
+
+/// One stream's position in the dependency tree.
+#[derive(Debug, Clone, Copy)]
+struct PriorityNode {
+    /// The stream this one depends on; 0 is the virtual root.
+    parent: u32,
+    weight: u16,
+}
+
+/// The stream dependency tree a client shapes with PRIORITY frames
+/// (RFC 7540 §5.3), used to order response scheduling.
+#[derive(Debug, Clone, Default)]
+pub struct PriorityTree {
+    nodes: HashMap<u32, PriorityNode>,
+}
+
+impl PriorityTree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The stream `stream` depends on; 0 — the root — when it never
+    /// carried a PRIORITY.
+    pub fn parent(&self, stream: u32) -> u32 {
+        self.nodes.get(&stream).map_or(0, |node| node.parent)
+    }
+
+    /// The effective weight of `stream`, 1–256.
+    pub fn weight(&self, stream: u32) -> u16 {
+        self.nodes
+            .get(&stream)
+            .map_or(DEFAULT_PRIORITY_WEIGHT, |node| node.weight)
+    }
+
+    /// Makes `stream` depend on `dependency` with the given weight.
+    ///
+    /// A dependency on the stream itself is a stream error (§5.3.1). When
+    /// the new parent is currently a descendant of `stream`, it is first
+    /// moved up to `stream`'s old parent so the tree stays acyclic
+    /// (§5.3.3). An exclusive dependency adopts the parent's other
+    /// children.
+    pub fn reprioritize(
+        &mut self,
+        stream: u32,
+        exclusive: bool,
+        dependency: u32,
+        weight: u16,
+    ) -> Result<(), StreamError> {
+        if stream == dependency {
+            return Err(StreamError::SelfDependency);
+        }
+        if self.is_descendant(dependency, stream) {
+            let grandparent = self.parent(stream);
+            self.nodes
+                .entry(dependency)
+                .or_insert(PriorityNode {
+                    parent: 0,
+                    weight: DEFAULT_PRIORITY_WEIGHT,
+                })
+                .parent = grandparent;
+        }
+        if exclusive {
+            let siblings: Vec<u32> = self
+                .nodes
+                .iter()
+                .filter(|&(&id, node)| node.parent == dependency && id != stream)
+                .map(|(&id, _)| id)
+                .collect();
+            for id in siblings {
+                self.nodes.get_mut(&id).expect("collected above").parent = stream;
+            }
+        }
+        self.nodes.insert(
+            stream,
+            PriorityNode {
+                parent: dependency,
+                weight,
+            },
+        );
+        Ok(())
+    }
+
+    /// Whether `candidate` sits in the subtree rooted at `ancestor`.
+    fn is_descendant(&self, candidate: u32, ancestor: u32) -> bool {
+        let mut cursor = candidate;
+        while cursor != 0 {
+            if cursor == ancestor {
+                return true;
+            }
+            cursor = self.parent(cursor);
+        }
+        false
+    }
+
+    /// The order streams should be scheduled in: parents before their
+    /// subtrees, heavier siblings first, ties broken by stream id. A
+    /// stream depending on one the tree never saw counts as a child of
+    /// the root.
+    pub fn scheduling_order(&self) -> Vec<u32> {
+        let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+        for (&id, node) in &self.nodes {
+            let parent = if node.parent != 0 && !self.nodes.contains_key(&node.parent) {
+                0
+            } else {
+                node.parent
+            };
+            children.entry(parent).or_default().push(id);
+        }
+        for siblings in children.values_mut() {
+            siblings.sort_by(|a, b| self.weight(*b).cmp(&self.weight(*a)).then(a.cmp(b)));
+        }
+
+        let mut order = Vec::with_capacity(self.nodes.len());
+        let mut stack = vec![0u32];
+        while let Some(id) = stack.pop() {
+            if id != 0 {
+                order.push(id);
+            }
+            if let Some(siblings) = children.get(&id) {
+                stack.extend(siblings.iter().rev());
+            }
+        }
+        order
+    }
+}
+
+/// How queued DATA frames are ordered across streams.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SchedulingPolicy {
+    /// Each stream with pending DATA sends one frame per round.
+    #[default]
+    RoundRobin,
+    /// Streams drain in the order of the PRIORITY tree; streams the tree
+    /// never saw come last, in stream-id order.
+    Priority,
+}
+
+/// Queues a connection's outbound frames and hands them back in send
+/// order: control frames (SETTINGS, PING, WINDOW_UPDATE, RST_STREAM)
+/// always jump ahead of DATA, and DATA interleaves across streams per
+/// the configured [`SchedulingPolicy`].
+#[derive(Debug, Clone, Default)]
+pub struct FrameScheduler {
+    policy: SchedulingPolicy,
+    control: VecDeque<Vec<u8>>,
+    data: HashMap<u32, VecDeque<Vec<u8>>>,
+    /// Streams awaiting their round-robin turn, rotated on each dequeue.
+    rotation: VecDeque<u32>,
+}
+
+impl FrameScheduler {
+    pub fn new(policy: SchedulingPolicy) -> Self {
+        Self {
+            policy,
+            ..Self::default()
+        }
+    }
+
+    pub fn policy(&self) -> SchedulingPolicy {
+        self.policy
+    }
+
+    /// Queues a control frame; these drain before any DATA.
+    pub fn enqueue_control(&mut self, frame: Vec<u8>) {
+        self.control.push_back(frame);
+    }
+
+    /// Queues a DATA frame behind whatever `stream` already has pending.
+    pub fn enqueue_data(&mut self, stream: u32, frame: Vec<u8>) {
+        let queue = self.data.entry(stream).or_default();
+        if queue.is_empty() {
+            self.rotation.push_back(stream);
+        }
+        queue.push_back(frame);
+    }
+
+    /// Total frames still queued.
+    pub fn len(&self) -> usize {
+        self.control.len() + self.data.values().map(VecDeque::len).sum::<usize>()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.control.is_empty() && self.data.values().all(VecDeque::is_empty)
+    }
+
+    /// Hands back the next frame to write, consulting `tree` only under
+    /// the priority policy.
+    pub fn dequeue(&mut self, tree: &PriorityTree) -> Option<Vec<u8>> {
+        if let Some(frame) = self.control.pop_front() {
+            return Some(frame);
+        }
+        let stream = match self.policy {
+            SchedulingPolicy::RoundRobin => self.rotation.pop_front()?,
+            SchedulingPolicy::Priority => self.next_by_priority(tree)?,
+        };
+        let queue = self.data.get_mut(&stream).expect("queued streams exist");
+        let frame = queue.pop_front().expect("queued streams are non-empty");
+        if queue.is_empty() {
+            self.data.remove(&stream);
+            self.rotation.retain(|&id| id != stream);
+        } else if self.policy == SchedulingPolicy::RoundRobin {
+            self.rotation.push_back(stream);
+        }
+        Some(frame)
+    }
+
+    /// The first stream of the PRIORITY scheduling order with pending
+    /// DATA, falling back to unprioritized streams in id order.
+    fn next_by_priority(&self, tree: &PriorityTree) -> Option<u32> {
+        for id in tree.scheduling_order() {
+            if self.data.get(&id).is_some_and(|queue| !queue.is_empty()) {
+                return Some(id);
+            }
+        }
+        self.data
+            .iter()
+            .filter(|(_, queue)| !queue.is_empty())
+            .map(|(&id, _)| id)
+            .min()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limit_refuses_excess_streams() {
+        let mut manager = StreamManager::new(Some(2));
+        manager.open(1).unwrap();
+        manager.open(3).unwrap();
+        assert_eq!(manager.open(5).unwrap_err(), StreamError::RefusedStream);
+        // Re-opening an existing stream is not an opening.
+        manager.open(3).unwrap();
+
+        // Closing frees a slot for a new stream.
+        manager.close(1);
+        manager.open(5).unwrap();
+        assert_eq!(manager.active_count(), 2);
+    }
+
+    #[test]
+    fn cleanup_idle_reaps_only_stale_finished_streams() {
+        let mut manager = StreamManager::new(None);
+        let stale = Instant::now() - Duration::from_secs(120);
+        for (id, state, last_activity) in [
+            (1, StreamState::HalfClosedRemote, stale),
+            (3, StreamState::Closed, stale),
+            (5, StreamState::HalfClosedLocal, Instant::now()),
+            (7, StreamState::Open, stale),
+        ] {
+            let stream = manager.open(id).unwrap();
+            stream.state = state;
+            stream.last_activity = last_activity;
+        }
+
+        assert_eq!(manager.cleanup_idle(Duration::from_secs(60)), 2);
+        assert!(manager.get(1).is_none(), "stale half-closed is reaped");
+        assert!(manager.get(3).is_none(), "stale closed is reaped");
+        assert!(manager.get(5).is_some(), "recent half-closed survives");
+        assert!(manager.get(7).is_some(), "open streams are never idle-reaped");
+    }
+
+    #[test]
+    fn dependency_chain_schedules_parents_first() {
+        let mut tree = PriorityTree::new();
+        tree.reprioritize(1, false, 0, 16).unwrap();
+        tree.reprioritize(3, false, 1, 200).unwrap();
+        tree.reprioritize(5, false, 1, 10).unwrap();
+        assert_eq!(tree.scheduling_order(), vec![1, 3, 5]);
+
+        // An exclusive dependency adopts the parent's other children.
+        tree.reprioritize(7, true, 1, 16).unwrap();
+        assert_eq!(tree.parent(3), 7);
+        assert_eq!(tree.parent(5), 7);
+        assert_eq!(tree.scheduling_order(), vec![1, 7, 3, 5]);
+    }
+
+    #[test]
+    fn self_dependency_is_a_stream_error() {
+        let mut tree = PriorityTree::new();
+        tree.reprioritize(3, false, 0, 16).unwrap();
+        assert_eq!(
+            tree.reprioritize(3, false, 3, 16).unwrap_err(),
+            StreamError::SelfDependency
+        );
+        // The failed frame leaves the tree untouched.
+        assert_eq!(tree.parent(3), 0);
+    }
+
+    #[test]
+    fn dependency_cycle_is_broken_by_moving_the_parent_up() {
+        let mut tree = PriorityTree::new();
+        tree.reprioritize(1, false, 0, 16).unwrap();
+        tree.reprioritize(3, false, 1, 16).unwrap();
+        // 1 now depends on its own descendant: 3 first moves up to 1's
+        // old parent, keeping the tree acyclic.
+        tree.reprioritize(1, false, 3, 16).unwrap();
+        assert_eq!(tree.parent(3), 0);
+        assert_eq!(tree.parent(1), 3);
+        assert_eq!(tree.scheduling_order(), vec![3, 1]);
+    }
+
+    #[test]
+    fn control_frames_jump_ahead_of_queued_data() {
+        let mut scheduler = FrameScheduler::new(SchedulingPolicy::RoundRobin);
+        let tree = PriorityTree::new();
+        scheduler.enqueue_data(1, vec![0xd1]);
+        scheduler.enqueue_data(1, vec![0xd2]);
+        scheduler.enqueue_control(vec![0xc1]);
+        scheduler.enqueue_control(vec![0xc2]);
+
+        assert_eq!(scheduler.dequeue(&tree), Some(vec![0xc1]));
+        assert_eq!(scheduler.dequeue(&tree), Some(vec![0xc2]));
+        assert_eq!(scheduler.dequeue(&tree), Some(vec![0xd1]));
+        // A control frame arriving mid-drain still cuts the line.
+        scheduler.enqueue_control(vec![0xc3]);
+        assert_eq!(scheduler.dequeue(&tree), Some(vec![0xc3]));
+        assert_eq!(scheduler.dequeue(&tree), Some(vec![0xd2]));
+        assert!(scheduler.is_empty());
+    }
+
+    #[test]
+    fn round_robin_interleaves_two_streams_fairly() {
+        let mut scheduler = FrameScheduler::new(SchedulingPolicy::RoundRobin);
+        let tree = PriorityTree::new();
+        for frame in [1u8, 2, 3] {
+            scheduler.enqueue_data(1, vec![0x10, frame]);
+            scheduler.enqueue_data(3, vec![0x30, frame]);
+        }
+        assert_eq!(scheduler.len(), 6);
+
+        let streams: Vec<u8> = std::iter::from_fn(|| scheduler.dequeue(&tree))
+            .map(|frame| frame[0])
+            .collect();
+        assert_eq!(streams, vec![0x10, 0x30, 0x10, 0x30, 0x10, 0x30]);
+    }
+
+    #[test]
+    fn priority_policy_drains_the_tree_order_first() {
+        let mut scheduler = FrameScheduler::new(SchedulingPolicy::Priority);
+        let mut tree = PriorityTree::new();
+        tree.reprioritize(3, false, 0, 200).unwrap();
+        tree.reprioritize(5, false, 0, 10).unwrap();
+        scheduler.enqueue_data(5, vec![5]);
+        scheduler.enqueue_data(3, vec![3]);
+        // Stream 9 never carried a PRIORITY and goes last.
+        scheduler.enqueue_data(9, vec![9]);
+
+        assert_eq!(scheduler.dequeue(&tree), Some(vec![3]));
+        assert_eq!(scheduler.dequeue(&tree), Some(vec![5]));
+        assert_eq!(scheduler.dequeue(&tree), Some(vec![9]));
+        assert_eq!(scheduler.dequeue(&tree), None);
+    }
+
+    #[test]
+    fn cleanup_reaps_only_closed_streams() {
+        let mut manager = StreamManager::new(None);
+        for id in [1, 3, 5] {
+            manager.open(id).unwrap();
+        }
+        manager.close(3);
+        assert_eq!(manager.cleanup_closed_streams(), 1);
+        assert!(manager.get(3).is_none());
+        assert!(manager.get(1).is_some());
+    }
+}
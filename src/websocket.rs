@@ -0,0 +1,255 @@
+//! WebSocket upgrade handshake support (RFC 6455 §4.2).
+
+use crate::error::Error;
+use crate::http1::Request;
+
+/// The fixed GUID appended to the client key when computing the accept value
+/// (RFC 6455 §1.3).
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Validates a WebSocket upgrade request and returns the complete
+/// `101 Switching Protocols` response bytes.
+///
+/// The request must carry `Upgrade: websocket`, a `Connection` header
+/// containing the `upgrade` token, `Sec-WebSocket-Version: 13`, and a
+/// `Sec-WebSocket-Key`. Any missing or malformed header yields a descriptive
+/// [`Error::ParseError`].
+pub fn accept_handshake(request: &Request) -> Result<Vec<u8>, Error> {
+    let upgrade = request
+        .header("Upgrade")
+        .ok_or_else(|| Error::ParseError("missing Upgrade header".into()))?;
+    if !upgrade.eq_ignore_ascii_case("websocket") {
+        return Err(Error::ParseError(format!(
+            "Upgrade header is not websocket: {upgrade}"
+        )));
+    }
+
+    let connection = request
+        .header("Connection")
+        .ok_or_else(|| Error::ParseError("missing Connection header".into()))?;
+    if !connection
+        .split(',')
+        .any(|token| token.trim().eq_ignore_ascii_case("upgrade"))
+    {
+        return Err(Error::ParseError(format!(
+            "Connection header lacks upgrade token: {connection}"
+        )));
+    }
+
+    let version = request
+        .header("Sec-WebSocket-Version")
+        .ok_or_else(|| Error::ParseError("missing Sec-WebSocket-Version header".into()))?;
+    if version.trim() != "13" {
+        return Err(Error::ParseError(format!(
+            "unsupported Sec-WebSocket-Version: {version}"
+        )));
+    }
+
+    let key = request
+        .header("Sec-WebSocket-Key")
+        .ok_or_else(|| Error::ParseError("missing Sec-WebSocket-Key header".into()))?;
+    let key = key.trim();
+    // The key is 16 bytes base64-encoded, which is always 24 characters.
+    if key.len() != 24 {
+        return Err(Error::ParseError(format!(
+            "malformed Sec-WebSocket-Key: {key}"
+        )));
+    }
+
+    let accept = compute_accept(key);
+
+    let mut response = Vec::with_capacity(128);
+    response.extend_from_slice(b"HTTP/1.1 101 Switching Protocols\r\n");
+    response.extend_from_slice(b"Upgrade: websocket\r\n");
+    response.extend_from_slice(b"Connection: Upgrade\r\n");
+    response.extend_from_slice(b"Sec-WebSocket-Accept: ");
+    response.extend_from_slice(accept.as_bytes());
+    response.extend_from_slice(b"\r\n\r\n");
+    Ok(response)
+}
+
+/// Computes the `Sec-WebSocket-Accept` value for a client key.
+fn compute_accept(key: &str) -> String {
+    let mut input = Vec::with_capacity(key.len() + WEBSOCKET_GUID.len());
+    input.extend_from_slice(key.as_bytes());
+    input.extend_from_slice(WEBSOCKET_GUID.as_bytes());
+    base64_encode(&sha1::digest(&input))
+}
+
+/// Standard-alphabet base64 encoding, used only for the accept value.
+fn base64_encode(input: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] =
+        b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(input.len().div_ceil(3) * 4);
+    for chunk in input.chunks(3) {
+        let b0 = chunk[0] as u32;
+        let b1 = chunk.get(1).copied().unwrap_or(0) as u32;
+        let b2 = chunk.get(2).copied().unwrap_or(0) as u32;
+        let group = (b0 << 16) | (b1 << 8) | b2;
+        out.push(ALPHABET[(group >> 18) as usize & 0x3f] as char);
+        out.push(ALPHABET[(group >> 12) as usize & 0x3f] as char);
+        if chunk.len() > 1 {
+            out.push(ALPHABET[(group >> 6) as usize & 0x3f] as char);
+        } else {
+            out.push('=');
+        }
+        if chunk.len() > 2 {
+            out.push(ALPHABET[group as usize & 0x3f] as char);
+        } else {
+            out.push('=');
+        }
+    }
+    out
+}
+
+/// A minimal SHA-1 implementation (FIPS 180-4), sufficient for the handshake.
+///
+/// SHA-1 is broken for collision resistance but is mandated by RFC 6455 for
+/// the accept computation, where no security property is required of it.
+/// [`crate::conditional`] borrows it as a content fingerprint for ETags,
+/// another use where collisions carry no security weight.
+pub(crate) mod sha1 {
+    pub fn digest(input: &[u8]) -> [u8; 20] {
+        let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+
+        let mut message = input.to_vec();
+        let bit_len = (input.len() as u64) * 8;
+        message.push(0x80);
+        while message.len() % 64 != 56 {
+            message.push(0);
+        }
+        message.extend_from_slice(&bit_len.to_be_bytes());
+
+        let mut w = [0u32; 80];
+        for block in message.chunks_exact(64) {
+            for (i, word) in block.chunks_exact(4).enumerate() {
+                w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+            }
+            for i in 16..80 {
+                w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+            }
+
+            let [mut a, mut b, mut c, mut d, mut e] = h;
+            for (i, &word) in w.iter().enumerate() {
+                let (f, k) = match i {
+                    0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                    20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                    40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                    _ => (b ^ c ^ d, 0xCA62C1D6),
+                };
+                let temp = a
+                    .rotate_left(5)
+                    .wrapping_add(f)
+                    .wrapping_add(e)
+                    .wrapping_add(k)
+                    .wrapping_add(word);
+                e = d;
+                d = c;
+                c = b.rotate_left(30);
+                b = a;
+                a = temp;
+            }
+
+            h[0] = h[0].wrapping_add(a);
+            h[1] = h[1].wrapping_add(b);
+            h[2] = h[2].wrapping_add(c);
+            h[3] = h[3].wrapping_add(d);
+            h[4] = h[4].wrapping_add(e);
+        }
+
+        let mut out = [0u8; 20];
+        for (i, word) in h.iter().enumerate() {
+            out[i * 4..i * 4 + 4].copy_from_slice(&word.to_be_bytes());
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http1::{Header, Method, Version};
+    use std::borrow::Cow;
+
+    fn upgrade_request(headers: Vec<Header<'_>>) -> Request<'_> {
+        Request {
+            method: Method::Get,
+            target: "/chat",
+            version: Version::Http11,
+            headers,
+            body: Cow::Borrowed(&[]),
+            trailers: Vec::new(),
+        }
+    }
+
+    fn full_headers() -> Vec<Header<'static>> {
+        vec![
+            Header {
+                name: "Host",
+                value: "server.example.com",
+            },
+            Header {
+                name: "Upgrade",
+                value: "websocket",
+            },
+            Header {
+                name: "Connection",
+                value: "Upgrade",
+            },
+            Header {
+                name: "Sec-WebSocket-Key",
+                value: "dGhlIHNhbXBsZSBub25jZQ==",
+            },
+            Header {
+                name: "Sec-WebSocket-Version",
+                value: "13",
+            },
+        ]
+    }
+
+    #[test]
+    fn rfc6455_example_key_produces_canonical_accept() {
+        // The example handshake from RFC 6455 §1.3.
+        let req = upgrade_request(full_headers());
+        let response = accept_handshake(&req).unwrap();
+        let text = std::str::from_utf8(&response).unwrap();
+        assert!(text.starts_with("HTTP/1.1 101 Switching Protocols\r\n"));
+        assert!(text.contains("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n"));
+        assert!(text.ends_with("\r\n\r\n"));
+    }
+
+    #[test]
+    fn missing_key_is_rejected() {
+        let headers = full_headers()
+            .into_iter()
+            .filter(|h| h.name != "Sec-WebSocket-Key")
+            .collect();
+        let req = upgrade_request(headers);
+        let err = accept_handshake(&req).unwrap_err();
+        assert!(err.to_string().contains("Sec-WebSocket-Key"));
+    }
+
+    #[test]
+    fn wrong_version_is_rejected() {
+        let mut headers = full_headers();
+        for h in &mut headers {
+            if h.name == "Sec-WebSocket-Version" {
+                h.value = "8";
+            }
+        }
+        let req = upgrade_request(headers);
+        assert!(accept_handshake(&req).is_err());
+    }
+
+    #[test]
+    fn connection_header_token_list_is_accepted() {
+        let mut headers = full_headers();
+        for h in &mut headers {
+            if h.name == "Connection" {
+                h.value = "keep-alive, Upgrade";
+            }
+        }
+        let req = upgrade_request(headers);
+        assert!(accept_handshake(&req).is_ok());
+    }
+}